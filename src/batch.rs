@@ -0,0 +1,75 @@
+use crate::calc_error::CalcError;
+use crate::index_tree::{IndexTreeBuilder, IndexTreeReturnValue};
+use crate::labels::{NoisePolicy, NormalizedPartition};
+use ndarray::{Array1, Array2, ArrayView1, ArrayView2};
+use rayon::prelude::*;
+
+#[cfg_attr(feature = "python", pyo3::pyclass)]
+#[derive(Clone, Debug)]
+pub struct BatchValue {
+    pub results: Vec<IndexTreeReturnValue>,
+}
+
+impl BatchValue {
+    // one row per labeling and one column per selected index, NaN where the index failed
+    pub fn table(&self) -> (Vec<&'static str>, Array2<f64>) {
+        let Some(first) = self.results.first() else {
+            return (Vec::new(), Array2::zeros((0, 0)));
+        };
+        let columns = first
+            .scalars()
+            .into_iter()
+            .enumerate()
+            .filter(|(_, (_, value))| value.is_some())
+            .map(|(i, (name, _))| (i, name))
+            .collect::<Vec<(usize, &'static str)>>();
+        let mut table = Array2::from_elem((self.results.len(), columns.len()), f64::NAN);
+        for (row, res) in self.results.iter().enumerate() {
+            let scalars = res.scalars();
+            for (col, (i, _)) in columns.iter().enumerate() {
+                if let Some(Ok(v)) = scalars[*i].1 {
+                    table[[row, col]] = v;
+                }
+            }
+        }
+        (columns.into_iter().map(|(_, name)| name).collect(), table)
+    }
+}
+
+// evaluates every labeling of the same features, the products that dont depend on the labels
+// (total dispersion, pairwise distances) are computed once and the labelings run in parallel.
+// Every labeling is normalized with the `noise` policy, see `IndexTree::compute_normalized`.
+// Invalid features fail the whole batch, only labels and weights are checked per labeling
+pub fn compute_batch<F>(
+    x: ArrayView2<f64>,
+    labelings: &[Array1<i32>],
+    weights: Option<ArrayView1<f64>>,
+    noise: NoisePolicy,
+    configure: F,
+) -> Result<BatchValue, CalcError>
+where
+    F: for<'b> Fn(IndexTreeBuilder<'b>) -> IndexTreeBuilder<'b> + Sync,
+{
+    if labelings.iter().any(|y| y.len() != x.nrows()) {
+        return Err(CalcError::shape(
+            "Labeling length doesnt match number of observations",
+        ));
+    }
+    let shared = configure(IndexTreeBuilder::default()).shared_products(&x, weights.as_ref())?;
+    let results = labelings
+        .par_iter()
+        .map(|y| {
+            let mut data = NormalizedPartition::new(x, y.view(), noise)?;
+            if let Some(weights) = weights {
+                data = data.with_weights(weights)?;
+            }
+            let mut builder = configure(IndexTreeBuilder::default());
+            // the shared products were computed from all observations
+            if data.keeps_all() {
+                builder = builder.shared(&shared);
+            }
+            Ok(builder.finish().compute_normalized(&data))
+        })
+        .collect::<Result<Vec<IndexTreeReturnValue>, CalcError>>()?;
+    Ok(BatchValue { results })
+}
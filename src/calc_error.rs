@@ -1,44 +1,99 @@
-use pyo3::{exceptions::PyValueError, prelude::PyErr};
-use std::{ops::Deref, sync::Arc};
+use std::{fmt, sync::Arc};
 
 #[derive(Debug, Clone)]
-pub struct CalcError {
-    message: Arc<String>,
+pub enum CalcError {
+    // arrays whose lengths or shapes dont match, or empty input
+    Shape(Arc<String>),
+    // NaN or infinite value in the features or distances of an observation
+    NonFinite {
+        observation: usize,
+    },
+    // labels other than 0..q-1 with every cluster present
+    Labels(Arc<String>),
+    TooFewClusters {
+        clusters: usize,
+    },
+    TooFewObservations {
+        observations: usize,
+        clusters: usize,
+    },
+    SingletonCluster {
+        cluster: i32,
+    },
+    // negative or non-finite observation weights, or a cluster without weight
+    Weights(Arc<String>),
+    // input the index needs but wasnt given, like the features or a reference partition
+    MissingInput(Arc<String>),
+    // failure while computing an index
+    Computation(Arc<String>),
+}
+impl CalcError {
+    pub fn shape(message: impl Into<String>) -> Self {
+        Self::Shape(Arc::new(message.into()))
+    }
+    pub fn labels(message: impl Into<String>) -> Self {
+        Self::Labels(Arc::new(message.into()))
+    }
+    pub fn missing_input(message: impl Into<String>) -> Self {
+        Self::MissingInput(Arc::new(message.into()))
+    }
+    pub fn weights(message: impl Into<String>) -> Self {
+        Self::Weights(Arc::new(message.into()))
+    }
 }
 
 pub trait CombineErrors<T, U> {
     fn combine<'a>(&'a self, other: &'a Result<U, CalcError>) -> Result<(&'a T, &'a U), CalcError>;
 }
 impl<T, U> CombineErrors<T, U> for Result<T, CalcError> {
+    // the first error is kept when both fail, it usually is the cause of the second one
     fn combine<'a>(&'a self, other: &'a Result<U, CalcError>) -> Result<(&'a T, &'a U), CalcError> {
         match (self, other) {
             (Ok(self_data), Ok(other_data)) => Ok((self_data, other_data)),
-            (Err(self_err), Ok(_)) => Err(self_err.clone()),
-            (Ok(_), Err(other_err)) => Err(other_err.clone()),
-            (Err(self_err), Err(other_err)) => Err(CalcError::from(
-                format!("{self_err:?}/{other_err:?}").as_str(),
-            )),
+            (Err(err), _) | (Ok(_), Err(err)) => Err(err.clone()),
         }
     }
 }
 
-impl From<CalcError> for PyErr {
-    fn from(value: CalcError) -> Self {
-        PyValueError::new_err(value.message.deref().clone())
+impl fmt::Display for CalcError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Shape(message)
+            | Self::Labels(message)
+            | Self::MissingInput(message)
+            | Self::Weights(message)
+            | Self::Computation(message) => f.write_str(message),
+            Self::NonFinite { observation } => {
+                write!(f, "Observation {observation} has NaN or infinite values")
+            }
+            Self::TooFewClusters { clusters } => {
+                write!(
+                    f,
+                    "Partition has {clusters} cluster(s), at least 2 are required"
+                )
+            }
+            Self::TooFewObservations {
+                observations,
+                clusters,
+            } => write!(
+                f,
+                "{observations} observations are too few for {clusters} clusters"
+            ),
+            Self::SingletonCluster { cluster } => {
+                write!(f, "Cluster {cluster} has a single observation")
+            }
+        }
     }
 }
+impl std::error::Error for CalcError {}
 
 impl From<&str> for CalcError {
     fn from(message: &str) -> Self {
-        Self {
-            message: Arc::new(message.to_string()),
-        }
+        Self::Computation(Arc::new(message.to_string()))
     }
 }
 impl From<String> for CalcError {
     fn from(message: String) -> Self {
-        Self {
-            message: Arc::new(message),
-        }
+        Self::Computation(Arc::new(message))
     }
 }
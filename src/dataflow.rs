@@ -0,0 +1,648 @@
+use crate::calc_error::CalcError;
+use crate::indexes::helpers::adjacent::{NextPartition, PreviousPartition};
+use crate::indexes::helpers::distances::DistanceMatrix;
+use crate::metric::Metric;
+use crate::profile::{NodeTrace, Profile};
+use ndarray::{ArrayView1, ArrayView2};
+use std::any::{type_name, Any, TypeId};
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write;
+use std::marker::PhantomData;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+// a value computed by a node, products are told apart by their type
+pub trait Product: Clone + Send + Sync + 'static {
+    // estimate of the bytes held by the product, reported by traced executions
+    fn memory(&self) -> usize {
+        std::mem::size_of::<Self>()
+    }
+}
+
+// partition a product is computed for, the neighbouring partitions have one cluster
+// less and one cluster more than the evaluated one
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Side {
+    Current,
+    Previous,
+    Next,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Key {
+    id: TypeId,
+    pub name: &'static str,
+    pub side: Side,
+}
+impl Key {
+    pub fn of<T: Product>(side: Side) -> Self {
+        // the path of the type is left out, generic arguments keep theirs
+        let name = type_name::<T>();
+        let end = name.find('<').unwrap_or(name.len());
+        let start = name[..end].rfind("::").map_or(0, |i| i + 2);
+        Self {
+            id: TypeId::of::<T>(),
+            name: &name[start..],
+            side,
+        }
+    }
+    fn on(self, side: Side) -> Self {
+        Self { side, ..self }
+    }
+}
+
+// input of a run, nodes read it through their scope
+pub struct Context<'a> {
+    pub features: Result<ArrayView2<'a, f64>, CalcError>,
+    pub labels: Result<ArrayView1<'a, i32>, CalcError>,
+    pub previous: Result<ArrayView1<'a, i32>, CalcError>,
+    pub next: Result<ArrayView1<'a, i32>, CalcError>,
+    pub reference: Result<ArrayView1<'a, i32>, CalcError>,
+    pub weights: Result<Option<ArrayView1<'a, f64>>, CalcError>,
+    pub precomputed: Result<(DistanceMatrix<'a>, usize), CalcError>,
+    pub metric: Metric,
+}
+
+// the input as seen by a node computing a product of one partition
+pub struct Scope<'s, 'a> {
+    ctx: &'s Context<'a>,
+    side: Side,
+}
+impl<'s, 'a> Scope<'s, 'a> {
+    pub fn side(&self) -> Side {
+        self.side
+    }
+    pub fn features(&self) -> Result<ArrayView2<'a, f64>, CalcError> {
+        self.ctx.features.clone()
+    }
+    pub fn labels(&self) -> Result<ArrayView1<'a, i32>, CalcError> {
+        match self.side {
+            Side::Current => self.ctx.labels.clone(),
+            Side::Previous => self.ctx.previous.clone(),
+            Side::Next => self.ctx.next.clone(),
+        }
+    }
+    // labels of the partition with one cluster more than the one of the scope
+    pub fn next_labels(&self) -> Result<ArrayView1<'a, i32>, CalcError> {
+        match self.side {
+            Side::Previous => self.ctx.labels.clone(),
+            Side::Current => self.ctx.next.clone(),
+            Side::Next => Err(CalcError::missing_input(
+                "Index requires the partition after the next one",
+            )),
+        }
+    }
+    // features and labels, the error of the features comes first
+    pub fn raw_data(&self) -> Result<(ArrayView2<'a, f64>, ArrayView1<'a, i32>), CalcError> {
+        self.features().and_then(|x| self.labels().map(|y| (x, y)))
+    }
+    pub fn reference(&self) -> Result<ArrayView1<'a, i32>, CalcError> {
+        self.ctx.reference.clone()
+    }
+    pub fn weights(&self) -> Result<Option<ArrayView1<'a, f64>>, CalcError> {
+        self.ctx.weights.clone()
+    }
+    pub fn precomputed(&self) -> Result<(DistanceMatrix<'a>, usize), CalcError> {
+        self.ctx.precomputed.clone()
+    }
+    pub fn metric(&self) -> &Metric {
+        &self.ctx.metric
+    }
+}
+
+type Value = Arc<dyn Any + Send + Sync>;
+
+fn memory<O: Product>(value: &Value) -> usize {
+    value.downcast_ref::<O>().map_or(0, Product::memory)
+}
+
+// products computed so far
+#[derive(Default)]
+pub struct Products {
+    values: HashMap<Key, Result<Value, CalcError>>,
+    // products that dont depend on the labels are stored once for all partitions
+    aliases: HashMap<Key, Key>,
+}
+impl Products {
+    fn resolve(&self, key: Key) -> Key {
+        self.aliases.get(&key).copied().unwrap_or(key)
+    }
+    pub fn contains(&self, key: Key) -> bool {
+        self.values.contains_key(&self.resolve(key))
+    }
+    pub fn insert<T: Product>(&mut self, side: Side, value: Result<T, CalcError>) {
+        self.values
+            .insert(Key::of::<T>(side), value.map(|v| Arc::new(v) as Value));
+    }
+    // the products `keys` refer to, along with the aliases to find them
+    fn select(&self, keys: &[Key]) -> Products {
+        let values = keys
+            .iter()
+            .filter_map(|key| {
+                let key = self.resolve(*key);
+                self.values.get(&key).map(|value| (key, value.clone()))
+            })
+            .collect();
+        Products {
+            values,
+            aliases: self.aliases.clone(),
+        }
+    }
+    pub fn get<T: Product>(&self, side: Side) -> Result<T, CalcError> {
+        let key = self.resolve(Key::of::<T>(side));
+        match self.values.get(&key) {
+            Some(Ok(value)) => value
+                .downcast_ref::<T>()
+                .cloned()
+                .ok_or_else(|| CalcError::from(format!("{} has a different type", key.name))),
+            Some(Err(err)) => Err(err.clone()),
+            None => Err(CalcError::from(format!("{} was not computed", key.name))),
+        }
+    }
+}
+
+// a product a node reads, of its own partition unless wrapped in `PreviousPartition`
+// or `NextPartition`
+pub trait Input: Sized {
+    fn key(side: Side) -> Key;
+    fn fetch(products: &Products, side: Side) -> Result<Self, CalcError>;
+}
+impl<T: Product> Input for T {
+    fn key(side: Side) -> Key {
+        Key::of::<T>(side)
+    }
+    fn fetch(products: &Products, side: Side) -> Result<Self, CalcError> {
+        products.get(side)
+    }
+}
+impl<T: Product> Input for PreviousPartition<T> {
+    fn key(_: Side) -> Key {
+        Key::of::<T>(Side::Previous)
+    }
+    fn fetch(products: &Products, _: Side) -> Result<Self, CalcError> {
+        products.get(Side::Previous).map(PreviousPartition)
+    }
+}
+impl<T: Product> Input for NextPartition<T> {
+    fn key(_: Side) -> Key {
+        Key::of::<T>(Side::Next)
+    }
+    fn fetch(products: &Products, _: Side) -> Result<Self, CalcError> {
+        products.get(Side::Next).map(NextPartition)
+    }
+}
+
+// all inputs of a node, the first failed one is reported as the node's error
+pub trait Inputs: Sized {
+    fn keys(side: Side) -> Vec<Key>;
+    fn fetch(products: &Products, side: Side) -> Result<Self, CalcError>;
+}
+impl Inputs for () {
+    fn keys(_: Side) -> Vec<Key> {
+        Vec::new()
+    }
+    fn fetch(_: &Products, _: Side) -> Result<Self, CalcError> {
+        Ok(())
+    }
+}
+macro_rules! impl_inputs {
+    ($($input:ident),+) => {
+        impl<$($input: Input),+> Inputs for ($($input,)+) {
+            fn keys(side: Side) -> Vec<Key> {
+                vec![$($input::key(side)),+]
+            }
+            fn fetch(products: &Products, side: Side) -> Result<Self, CalcError> {
+                Ok(($($input::fetch(products, side)?,)+))
+            }
+        }
+    };
+}
+impl_inputs!(A);
+impl_inputs!(A, B);
+impl_inputs!(A, B, C);
+impl_inputs!(A, B, C, D);
+impl_inputs!(A, B, C, D, E);
+
+type Run = dyn for<'s, 'a> Fn(&Scope<'s, 'a>, &Products) -> Result<Value, CalcError> + Send + Sync;
+type InputKeys = dyn Fn(Side) -> Vec<Key> + Send + Sync;
+
+#[derive(Clone)]
+pub struct NodeTemplate {
+    pub name: &'static str,
+    inputs: Arc<InputKeys>,
+    // products of the labels are computed for every partition that needs them
+    partitioned: bool,
+    run: Arc<Run>,
+    memory: fn(&Value) -> usize,
+}
+
+// computes the product `O` from its inputs, adding an index means writing one of these
+pub struct Node<O> {
+    template: NodeTemplate,
+    output: PhantomData<O>,
+}
+impl<O: Product> Node<O> {
+    pub fn new<I, F>(name: &'static str, f: F) -> Self
+    where
+        I: Inputs + 'static,
+        F: for<'s, 'a> Fn(&Scope<'s, 'a>, I) -> Result<O, CalcError> + Send + Sync + 'static,
+    {
+        let run = move |scope: &Scope, products: &Products| {
+            let inputs = I::fetch(products, scope.side)?;
+            f(scope, inputs).map(|v| Arc::new(v) as Value)
+        };
+        Self {
+            template: NodeTemplate {
+                name,
+                inputs: Arc::new(I::keys),
+                partitioned: true,
+                run: Arc::new(run),
+                memory: memory::<O>,
+            },
+            output: PhantomData,
+        }
+    }
+    // a node whose inputs are only known at runtime, `inputs` are products of its own
+    // partition and `f` reads them from the products itself
+    pub fn dynamic<F>(name: &'static str, inputs: Vec<Key>, f: F) -> Self
+    where
+        F: for<'s, 'a> Fn(&Scope<'s, 'a>, &Products) -> Result<O, CalcError>
+            + Send
+            + Sync
+            + 'static,
+    {
+        let run = move |scope: &Scope, products: &Products| {
+            f(scope, products).map(|v| Arc::new(v) as Value)
+        };
+        let keys = move |side: Side| inputs.iter().map(|key| key.on(side)).collect();
+        Self {
+            template: NodeTemplate {
+                name,
+                inputs: Arc::new(keys),
+                partitioned: true,
+                run: Arc::new(run),
+                memory: memory::<O>,
+            },
+            output: PhantomData,
+        }
+    }
+    // the product doesnt depend on the labels, the neighbouring partitions share it
+    pub fn label_independent(mut self) -> Self {
+        self.template.partitioned = false;
+        self
+    }
+}
+
+// nodes able to compute every product, and the products asked for
+#[derive(Clone, Default)]
+pub struct Graph {
+    templates: HashMap<TypeId, NodeTemplate>,
+    targets: Vec<Key>,
+}
+impl Graph {
+    // a later node computing the same product replaces the earlier one
+    pub fn add<O: Product>(&mut self, node: Node<O>) {
+        self.templates.insert(TypeId::of::<O>(), node.template);
+    }
+    // adds the nodes and requests of `other`, its nodes replace the ones computing
+    // the same products
+    pub fn extend(&mut self, other: &Graph) {
+        self.templates
+            .extend(other.templates.iter().map(|(id, t)| (*id, t.clone())));
+        for key in &other.targets {
+            if !self.targets.contains(key) {
+                self.targets.push(*key);
+            }
+        }
+    }
+    pub fn request<O: Product>(&mut self) {
+        let key = Key::of::<O>(Side::Current);
+        if !self.targets.contains(&key) {
+            self.targets.push(key);
+        }
+    }
+    // only the nodes the requested products depend on are instantiated, products in
+    // `provided` are given by the caller instead of computed
+    pub fn plan(&self, provided: &[Key]) -> Plan {
+        let mut planner = Planner {
+            graph: self,
+            provided,
+            plan: Plan::default(),
+            visiting: HashSet::new(),
+            done: HashSet::new(),
+        };
+        for target in &self.targets {
+            planner.resolve(*target);
+        }
+        let mut plan = planner.plan;
+        plan.targets = self.targets.clone();
+        let mut skipped = self
+            .templates
+            .values()
+            .map(|template| template.name)
+            .filter(|name| !plan.nodes.iter().any(|node| node.template.name == *name))
+            .collect::<Vec<&'static str>>();
+        skipped.sort();
+        plan.skipped = skipped;
+        plan
+    }
+}
+
+struct Planner<'g> {
+    graph: &'g Graph,
+    provided: &'g [Key],
+    plan: Plan,
+    visiting: HashSet<Key>,
+    done: HashSet<Key>,
+}
+impl<'g> Planner<'g> {
+    fn resolve(&mut self, key: Key) {
+        if self.done.contains(&key) {
+            return;
+        }
+        if self.provided.contains(&key) {
+            self.plan.provided.push(key);
+            self.done.insert(key);
+            return;
+        }
+        let Some(template) = self.graph.templates.get(&key.id) else {
+            self.plan.missing.push((key, "No node computes it"));
+            self.done.insert(key);
+            return;
+        };
+        if !template.partitioned && key.side != Side::Current {
+            let shared = key.on(Side::Current);
+            self.plan.aliases.push((key, shared));
+            self.done.insert(key);
+            return self.resolve(shared);
+        }
+        if !self.visiting.insert(key) {
+            self.plan.missing.push((key, "It depends on itself"));
+            self.done.insert(key);
+            return;
+        }
+        let inputs = (template.inputs)(key.side);
+        for input in &inputs {
+            self.resolve(*input);
+        }
+        self.visiting.remove(&key);
+        self.done.insert(key);
+        // inputs are resolved first, so the nodes are in topological order
+        self.plan.nodes.push(Instance {
+            template: template.clone(),
+            side: key.side,
+            inputs,
+            output: key,
+        });
+    }
+}
+
+#[derive(Clone)]
+pub struct Instance {
+    pub template: NodeTemplate,
+    pub side: Side,
+    pub inputs: Vec<Key>,
+    pub output: Key,
+}
+
+#[derive(Clone, Default)]
+pub struct Plan {
+    pub nodes: Vec<Instance>,
+    pub targets: Vec<Key>,
+    // products no node can compute, with the reason
+    pub missing: Vec<(Key, &'static str)>,
+    // products the caller gives instead of a node computing them
+    pub provided: Vec<Key>,
+    // nodes of the graph no requested product depends on
+    pub skipped: Vec<&'static str>,
+    aliases: Vec<(Key, Key)>,
+}
+impl Plan {
+    pub fn computes<T: Product>(&self) -> bool {
+        let key = Key::of::<T>(Side::Current);
+        self.nodes.iter().any(|node| node.output == key)
+    }
+
+    // the plan as Graphviz DOT. Requested products are boxes, products given by the caller
+    // are dashed, missing ones red and skipped nodes are grouped apart
+    pub fn dot(&self) -> String {
+        let id = |key: &Key| format!("\"{}:{:?}\"", key.name, key.side);
+        let shared = |key: &Key| {
+            self.aliases
+                .iter()
+                .find(|(alias, _)| alias == key)
+                .map_or(*key, |(_, shared)| *shared)
+        };
+        let mut dot = String::from("digraph plan {\n    rankdir=LR;\n");
+        for key in &self.provided {
+            let _ = writeln!(
+                dot,
+                "    {} [label=\"{}\", style=dashed];",
+                id(key),
+                key.name
+            );
+        }
+        for (key, reason) in &self.missing {
+            let _ = writeln!(
+                dot,
+                "    {} [label=\"{}\\n{reason}\", color=red];",
+                id(key),
+                key.name
+            );
+        }
+        for node in &self.nodes {
+            let label = match node.side {
+                Side::Current => node.template.name.to_string(),
+                side => format!("{} ({side:?})", node.template.name),
+            };
+            let shape = if self.targets.contains(&node.output) {
+                "box"
+            } else {
+                "ellipse"
+            };
+            let _ = writeln!(
+                dot,
+                "    {} [label=\"{label}\", shape={shape}];",
+                id(&node.output)
+            );
+            for input in &node.inputs {
+                let _ = writeln!(dot, "    {} -> {};", id(&shared(input)), id(&node.output));
+            }
+        }
+        if !self.skipped.is_empty() {
+            dot.push_str("    subgraph cluster_skipped {\n        label=\"skipped\";\n");
+            for name in &self.skipped {
+                let _ = writeln!(dot, "        \"{name}\" [style=dotted];");
+            }
+            dot.push_str("    }\n");
+        }
+        dot.push_str("}\n");
+        dot
+    }
+
+    // runs every node as soon as its inputs are available, independent nodes run in
+    // parallel. Products are dropped once no pending node reads them
+    pub fn execute(&self, ctx: &Context, products: Products) -> Products {
+        self.run(ctx, products, None).0
+    }
+    // `execute` recording when, where and how long every node ran
+    pub fn execute_traced(&self, ctx: &Context, products: Products) -> (Products, Profile) {
+        let origin = Instant::now();
+        let (products, mut nodes) = self.run(ctx, products, Some(origin));
+        let wall = origin.elapsed();
+        // traces are stored after taking the lock, which doesnt follow the end times
+        nodes.sort_by_key(|node| node.start + node.duration);
+        (products, Profile { nodes, wall })
+    }
+    fn run(
+        &self,
+        ctx: &Context,
+        mut products: Products,
+        origin: Option<Instant>,
+    ) -> (Products, Vec<NodeTrace>) {
+        products.aliases.extend(self.aliases.iter().copied());
+        for (key, reason) in &self.missing {
+            let err = CalcError::missing_input(format!("{}: {reason}", key.name));
+            products.values.insert(*key, Err(err));
+        }
+        let mut uses: HashMap<Key, usize> = HashMap::new();
+        for key in self.nodes.iter().flat_map(|node| node.inputs.iter()) {
+            *uses.entry(products.resolve(*key)).or_default() += 1;
+        }
+        let targets = self
+            .targets
+            .iter()
+            .map(|key| products.resolve(*key))
+            .collect::<HashSet<Key>>();
+
+        // nodes are in topological order, so an input neither given nor computed by an
+        // earlier node is a provided product that wasnt given
+        let mut computed = HashSet::new();
+        for node in &self.nodes {
+            let given = node.inputs.iter().all(|key| {
+                let key = products.resolve(*key);
+                products.values.contains_key(&key) || computed.contains(&key)
+            });
+            if given {
+                computed.insert(node.output);
+            } else {
+                let err =
+                    CalcError::missing_input(format!("{}: Inputs not given", node.output.name));
+                products.values.insert(node.output, Err(err));
+            }
+        }
+        let mut dependants: HashMap<Key, Vec<usize>> = HashMap::new();
+        let mut waiting = vec![0; self.nodes.len()];
+        let mut ready = Vec::new();
+        for (i, node) in self.nodes.iter().enumerate() {
+            if !computed.contains(&node.output) {
+                continue;
+            }
+            for key in &node.inputs {
+                let key = products.resolve(*key);
+                if !products.values.contains_key(&key) {
+                    dependants.entry(key).or_default().push(i);
+                    waiting[i] += 1;
+                }
+            }
+            if waiting[i] == 0 {
+                ready.push(i);
+            }
+        }
+
+        let execution = Execution {
+            ctx,
+            nodes: &self.nodes,
+            origin,
+            dependants,
+            targets,
+            state: Mutex::new(State {
+                products,
+                uses,
+                waiting,
+                traces: Vec::new(),
+            }),
+        };
+        rayon::scope(|tasks| {
+            for i in ready {
+                execution.spawn(tasks, i);
+            }
+        });
+        let state = execution.state.into_inner().unwrap();
+        (state.products, state.traces)
+    }
+}
+
+// a run of a plan, every node is spawned once the last of its inputs is stored
+struct Execution<'e, 'a> {
+    ctx: &'e Context<'a>,
+    nodes: &'e [Instance],
+    origin: Option<Instant>,
+    // nodes waiting for every product
+    dependants: HashMap<Key, Vec<usize>>,
+    targets: HashSet<Key>,
+    state: Mutex<State>,
+}
+struct State {
+    products: Products,
+    // pending nodes reading every product, it is dropped at 0 unless requested
+    uses: HashMap<Key, usize>,
+    // inputs every node still waits for
+    waiting: Vec<usize>,
+    traces: Vec<NodeTrace>,
+}
+impl<'e, 'a> Execution<'e, 'a> {
+    fn spawn<'s>(&'s self, tasks: &rayon::Scope<'s>, i: usize) {
+        tasks.spawn(move |tasks| {
+            for next in self.run_node(i) {
+                self.spawn(tasks, next);
+            }
+        });
+    }
+    // runs the node `i`, returns the nodes it was the last input of
+    fn run_node(&self, i: usize) -> Vec<usize> {
+        let node = &self.nodes[i];
+        // the node reads its own copy of its inputs, so the lock isnt held while it runs
+        let inputs = self.state.lock().unwrap().products.select(&node.inputs);
+        let scope = Scope {
+            ctx: self.ctx,
+            side: node.side,
+        };
+        let start = self.origin.map(|origin| origin.elapsed());
+        let value = (node.template.run)(&scope, &inputs);
+        drop(inputs);
+        let trace = self.origin.zip(start).map(|(origin, start)| NodeTrace {
+            name: node.template.name,
+            product: node.output.name,
+            side: node.side,
+            thread: rayon::current_thread_index(),
+            start,
+            duration: origin.elapsed() - start,
+            memory: value.as_ref().map_or(0, node.template.memory),
+            ok: value.is_ok(),
+        });
+
+        let mut state = self.state.lock().unwrap();
+        let state = &mut *state;
+        state.products.values.insert(node.output, value);
+        state.traces.extend(trace);
+        for key in &node.inputs {
+            let key = state.products.resolve(*key);
+            let Some(count) = state.uses.get_mut(&key) else {
+                continue;
+            };
+            *count -= 1;
+            if *count == 0 && !self.targets.contains(&key) {
+                state.products.values.remove(&key);
+            }
+        }
+        let mut ready = Vec::new();
+        for next in self.dependants.get(&node.output).into_iter().flatten() {
+            state.waiting[*next] -= 1;
+            if state.waiting[*next] == 0 {
+                ready.push(*next);
+            }
+        }
+        ready
+    }
+}
@@ -1,18 +1,30 @@
+use crate::indexes::adjusted_rand::AdjustedRandIndexValue;
 use crate::indexes::ball_hall::BallHallIndexValue;
+use crate::indexes::beale::BealeIndexValue;
 use crate::indexes::c_index::CIndexValue;
 use crate::indexes::calinski_harabasz::CalinskiHarabaszIndexValue;
+use crate::indexes::ccc::CCCIndexValue;
 use crate::indexes::davies_bouldin::DaviesBouldinIndexValue;
+use crate::indexes::dindex::DIndexValue;
+use crate::indexes::duda::DudaIndexValue;
 use crate::indexes::dunn::DunnIndexValue;
+use crate::indexes::fowlkes_mallows::FowlkesMallowsIndexValue;
+use crate::indexes::frey::FreyIndexValue;
 use crate::indexes::friedman::FriedmanIndexValue;
 use crate::indexes::gamma::GammaIndexValue;
+use crate::indexes::gap::{GapConfig, GapIndexValue};
 use crate::indexes::gplus::GplusIndexValue;
-use crate::indexes::helpers::s_plus_and_minus::SPlusAndMinusNode;
-use crate::indexes::helpers::scat::ScatValue;
-use crate::indexes::helpers::total_dispercion::TDValue;
+use crate::indexes::hartigan::HartiganIndexValue;
 use crate::indexes::hubert::HubertIndexValue;
+use crate::indexes::jaccard::JaccardIndexValue;
+use crate::indexes::kl::KLIndexValue;
 use crate::indexes::mariott::MariottIndexValue;
 use crate::indexes::mcclain::McclainIndexValue;
+use crate::indexes::mutual_information::{AMIIndexValue, NMIIndexValue};
+use crate::indexes::pseudot2::PseudoT2IndexValue;
 use crate::indexes::ptbiserial::PtbiserialIndexValue;
+use crate::indexes::purity::PurityIndexValue;
+use crate::indexes::rand_index::RandIndexValue;
 use crate::indexes::ratkowsky::RatkowskyIndexValue;
 use crate::indexes::rubin::RubinIndexValue;
 use crate::indexes::scott::ScottIndexValue;
@@ -22,48 +34,46 @@ use crate::indexes::silhouette::SilhouetteIndexValue;
 use crate::indexes::tau::TauIndexValue;
 use crate::indexes::tracew::TracewIndexValue;
 use crate::indexes::trcovw::TrcovwIndexValue;
+use crate::indexes::v_measure::VMeasureIndexValue;
 
 use crate::indexes::helpers::between_group_dispercion::BGDValue;
 use crate::indexes::helpers::clusters_centroids::ClustersCentroidsValue;
+use crate::indexes::helpers::contingency::ContingencyValue;
+use crate::indexes::helpers::counts::CountsValue;
+use crate::indexes::helpers::distances::{
+    DistanceMatrix, DistanceSource, Distances, DistancesValue, PairwiseEngine,
+};
+use crate::indexes::helpers::pair_stats::PairStatsValue;
+use crate::indexes::helpers::pairs_and_distances::PairsAndDistancesValue;
+use crate::indexes::helpers::scat::ScatValue;
+use crate::indexes::helpers::total_dispercion::{TDValue, TD};
 use crate::indexes::helpers::within_group_dispercion::WGDValue;
+use crate::sampling::{self, PairIndex, SampledEstimate, SampledValue, SamplingConfig};
 
 use crate::{
     calc_error::CalcError,
+    dataflow::{Context, Graph, Key, Node, Plan, Product, Products, Side},
     indexes::{
-        ball_hall::Node as BallHallNode,
-        c_index::Node as CIndexNode,
-        calinski_harabasz::Node as CalinskiHarabaszNode,
-        davies_bouldin::Node as DaviesBouldinNode,
-        dunn::Node as DunnNode,
-        friedman::Node as FriedmanNode,
-        gamma::Node as GammaNode,
-        gplus::Node as GplusNode,
+        adjusted_rand, ball_hall, beale, c_index, calinski_harabasz, ccc, davies_bouldin, dindex,
+        duda, dunn, fowlkes_mallows, frey, friedman, gamma, gap, gplus, hartigan,
         helpers::{
-            between_group_dispercion::BGDNode, clusters_centroids::ClustersCentroidsNode,
-            counts::CountsNode, pairs_and_distances::PairsAndDistancesNode, raw_data::RawDataNode,
-            scat::Node as ScatNode, total_dispercion::TDNode, within_group_dispercion::WGDNode,
+            between_group_dispercion, clusters_centroids, contingency, counts, distances,
+            pair_stats, pairs_and_distances, s_plus_and_minus, scat, split, total_dispercion,
+            within_group_dispercion,
         },
-        hubert::Node as HubertNode,
-        mariott::Node as MariottNode,
-        mcclain::Node as McclainNode,
-        ptbiserial::Node as PtbiserialNode,
-        ratkowsky::Node as RatkowskyNode,
-        rubin::Node as RubinNode,
-        scott::Node as ScottNode,
-        sd::Node as SDNode,
-        sdbw::Node as SDBWNode,
-        silhouette::Node as SilhouetteNode,
-        tau::Node as TauNode,
-        tracew::Node as TracewNode,
-        trcovw::Node as TrcovwNode,
+        hubert, jaccard, kl, mariott, mcclain, mutual_information, pseudot2, ptbiserial, purity,
+        rand_index, ratkowsky, rubin, scott, sd, sdbw, silhouette, tau, tracew, trcovw, v_measure,
     },
-    sender::{Sender, Subscriber},
+    labels::NormalizedPartition,
+    metric::{self, Metric},
+    plugin::{self, Helper, IndexPlugin, PluginsValue},
+    profile::Profile,
+    validation::{validate, validate_features, ValidationConfig},
 };
-use ndarray::{ArcArray1, ArrayView1, ArrayView2};
-use pyo3::{pyclass, pymethods};
-use std::sync::{Arc, Mutex};
+use ndarray::{ArcArray1, ArcArray2, ArrayView1, ArrayView2};
+use std::sync::Arc;
 
-#[pyclass]
+#[cfg_attr(feature = "python", pyo3::pyclass)]
 #[derive(Default, Debug, Clone)]
 pub struct IndexTreeReturnValue {
     pub ball_hall: Option<Result<BallHallIndexValue, CalcError>>,
@@ -87,467 +97,659 @@ pub struct IndexTreeReturnValue {
     pub hubert: Option<Result<HubertIndexValue, CalcError>>,
     pub sd: Option<Result<SDIndexValue, CalcError>>,
     pub sdbw: Option<Result<SDBWIndexValue, CalcError>>,
+    pub kl: Option<Result<KLIndexValue, CalcError>>,
+    pub hartigan: Option<Result<HartiganIndexValue, CalcError>>,
+    pub frey: Option<Result<FreyIndexValue, CalcError>>,
+    pub duda: Option<Result<DudaIndexValue, CalcError>>,
+    pub pseudot2: Option<Result<PseudoT2IndexValue, CalcError>>,
+    pub beale: Option<Result<BealeIndexValue, CalcError>>,
+    pub gap: Option<Result<GapIndexValue, CalcError>>,
+    pub ccc: Option<Result<CCCIndexValue, CalcError>>,
+    pub dindex: Option<Result<DIndexValue, CalcError>>,
+    pub adjusted_rand: Option<Result<AdjustedRandIndexValue, CalcError>>,
+    pub rand: Option<Result<RandIndexValue, CalcError>>,
+    pub jaccard: Option<Result<JaccardIndexValue, CalcError>>,
+    pub fowlkes_mallows: Option<Result<FowlkesMallowsIndexValue, CalcError>>,
+    pub nmi: Option<Result<NMIIndexValue, CalcError>>,
+    pub ami: Option<Result<AMIIndexValue, CalcError>>,
+    pub v_measure: Option<Result<VMeasureIndexValue, CalcError>>,
+    pub purity: Option<Result<PurityIndexValue, CalcError>>,
+    pub contingency: Option<Result<ContingencyValue, CalcError>>,
+    // products of the helper nodes, kept when requested with `add_helper`
+    pub counts: Option<Result<CountsValue, CalcError>>,
+    pub centroids: Option<Result<ClustersCentroidsValue, CalcError>>,
+    pub wgd: Option<Result<WGDValue, CalcError>>,
+    pub bgd: Option<Result<BGDValue, CalcError>>,
+    pub td: Option<Result<TDValue, CalcError>>,
+    pub pairs_and_distances: Option<Result<PairsAndDistancesValue, CalcError>>,
+    pub scat: Option<Result<ScatValue, CalcError>>,
+    // confidence intervals of the indexes estimated on subsamples
+    pub estimates: Vec<(&'static str, SampledEstimate)>,
+    // values of the custom indexes by name, in the order they were added
+    pub plugins: Vec<(String, Result<f64, CalcError>)>,
+    // time, thread and memory of every node, set when the tree was built with `tracing`
+    pub profile: Option<Profile>,
+    // original id of every row of the per-cluster outputs, set for normalized partitions
+    pub cluster_ids: Option<ArcArray1<i32>>,
 }
 
-#[pymethods]
+fn scalar<T>(
+    value: &Option<Result<T, CalcError>>,
+    val: fn(&T) -> f64,
+) -> Option<Result<f64, CalcError>> {
+    value
+        .as_ref()
+        .map(|r| r.as_ref().map(val).map_err(Clone::clone))
+}
 impl IndexTreeReturnValue {
-    #[getter]
-    fn get_ball_hall(&self) -> Result<Option<f64>, CalcError> {
-        self.ball_hall.clone().map(|f| f.map(|v| v.val)).transpose()
-    }
-    #[getter]
-    fn get_davies_bouldin(&self) -> Result<Option<f64>, CalcError> {
-        self.davies_bouldin
-            .clone()
-            .map(|f| f.map(|v| v.val))
-            .transpose()
-    }
-    #[getter]
-    fn get_c_index(&self) -> Result<Option<f64>, CalcError> {
-        self.c_index.clone().map(|f| f.map(|v| v.val)).transpose()
-    }
-    #[getter]
-    fn get_calinski_harabasz(&self) -> Result<Option<f64>, CalcError> {
-        self.calinski_harabasz
-            .clone()
-            .map(|f| f.map(|v| v.val))
-            .transpose()
-    }
-    #[getter]
-    fn get_dunn(&self) -> Result<Option<f64>, CalcError> {
-        self.dunn.clone().map(|f| f.map(|v| v.val)).transpose()
-    }
-    #[getter]
-    fn get_silhouette(&self) -> Result<Option<f64>, CalcError> {
-        self.silhouette
-            .clone()
-            .map(|f| f.map(|v| v.val))
-            .transpose()
-    }
-    #[getter]
-    fn get_rubin(&self) -> Result<Option<f64>, CalcError> {
-        self.rubin.clone().map(|f| f.map(|v| v.val)).transpose()
-    }
-    #[getter]
-    fn get_mariott(&self) -> Result<Option<f64>, CalcError> {
-        self.mariott.clone().map(|f| f.map(|v| v.val)).transpose()
-    }
-    #[getter]
-    fn get_scott(&self) -> Result<Option<f64>, CalcError> {
-        self.scott.clone().map(|f| f.map(|v| v.val)).transpose()
-    }
-    #[getter]
-    fn get_friedman(&self) -> Result<Option<f64>, CalcError> {
-        self.friedman.clone().map(|f| f.map(|v| v.val)).transpose()
-    }
-    #[getter]
-    fn get_tau(&self) -> Result<Option<f64>, CalcError> {
-        self.friedman.clone().map(|f| f.map(|v| v.val)).transpose()
-    }
-    #[getter]
-    fn get_gamma(&self) -> Result<Option<f64>, CalcError> {
-        self.gamma.clone().map(|f| f.map(|v| v.val)).transpose()
-    }
-    #[getter]
-    fn get_gplus(&self) -> Result<Option<f64>, CalcError> {
-        self.gplus.clone().map(|f| f.map(|v| v.val)).transpose()
-    }
-    #[getter]
-    fn get_tracew(&self) -> Result<Option<f64>, CalcError> {
-        self.tracew.clone().map(|f| f.map(|v| v.val)).transpose()
-    }
-    #[getter]
-    fn get_mcclain(&self) -> Result<Option<f64>, CalcError> {
-        self.mcclain.clone().map(|f| f.map(|v| v.val)).transpose()
-    }
-    #[getter]
-    fn get_ptbiserial(&self) -> Result<Option<f64>, CalcError> {
-        self.ptbiserial
-            .clone()
-            .map(|f| f.map(|v| v.val))
-            .transpose()
-    }
-    #[getter]
-    fn get_ratkowsky(&self) -> Result<Option<f64>, CalcError> {
-        self.ratkowsky.clone().map(|f| f.map(|v| v.val)).transpose()
-    }
-    #[getter]
-    fn get_trcovw(&self) -> Result<Option<f64>, CalcError> {
-        self.trcovw.clone().map(|f| f.map(|v| v.val)).transpose()
-    }
-    #[getter]
-    fn get_hubertw(&self) -> Result<Option<f64>, CalcError> {
-        self.hubert.clone().map(|f| f.map(|v| v.val)).transpose()
-    }
-    #[getter]
-    fn get_sd(&self) -> Result<Option<f64>, CalcError> {
-        self.sd.clone().map(|f| f.map(|v| v.val)).transpose()
-    }
-    #[getter]
-    fn get_sdbw(&self) -> Result<Option<f64>, CalcError> {
-        self.sdbw.clone().map(|f| f.map(|v| v.val)).transpose()
+    /// Scalar value of every index by name, `None` when the index was not requested.
+    pub fn scalars(&self) -> Vec<(&'static str, Option<Result<f64, CalcError>>)> {
+        vec![
+            ("ball_hall", scalar(&self.ball_hall, |v| v.val)),
+            ("davies_bouldin", scalar(&self.davies_bouldin, |v| v.val)),
+            ("c_index", scalar(&self.c_index, |v| v.val)),
+            (
+                "calinski_harabasz",
+                scalar(&self.calinski_harabasz, |v| v.val),
+            ),
+            ("dunn", scalar(&self.dunn, |v| v.val)),
+            ("silhouette", scalar(&self.silhouette, |v| v.val)),
+            ("rubin", scalar(&self.rubin, |v| v.val)),
+            ("mariott", scalar(&self.mariott, |v| v.val)),
+            ("scott", scalar(&self.scott, |v| v.val)),
+            ("friedman", scalar(&self.friedman, |v| v.val)),
+            ("tau", scalar(&self.tau, |v| v.val)),
+            ("gamma", scalar(&self.gamma, |v| v.val)),
+            ("gplus", scalar(&self.gplus, |v| v.val)),
+            ("tracew", scalar(&self.tracew, |v| v.val)),
+            ("mcclain", scalar(&self.mcclain, |v| v.val)),
+            ("ptbiserial", scalar(&self.ptbiserial, |v| v.val)),
+            ("ratkowsky", scalar(&self.ratkowsky, |v| v.val)),
+            ("trcovw", scalar(&self.trcovw, |v| v.val)),
+            ("hubert", scalar(&self.hubert, |v| v.val)),
+            ("sd", scalar(&self.sd, |v| v.val)),
+            ("sdbw", scalar(&self.sdbw, |v| v.val)),
+            ("kl", scalar(&self.kl, |v| v.val)),
+            ("hartigan", scalar(&self.hartigan, |v| v.val)),
+            ("frey", scalar(&self.frey, |v| v.val)),
+            ("duda", scalar(&self.duda, |v| v.val)),
+            ("pseudot2", scalar(&self.pseudot2, |v| v.val)),
+            ("beale", scalar(&self.beale, |v| v.val)),
+            ("gap", scalar(&self.gap, |v| v.val)),
+            ("ccc", scalar(&self.ccc, |v| v.val)),
+            ("dindex", scalar(&self.dindex, |v| v.val)),
+            ("adjusted_rand", scalar(&self.adjusted_rand, |v| v.val)),
+            ("rand", scalar(&self.rand, |v| v.val)),
+            ("jaccard", scalar(&self.jaccard, |v| v.val)),
+            ("fowlkes_mallows", scalar(&self.fowlkes_mallows, |v| v.val)),
+            ("nmi", scalar(&self.nmi, |v| v.val)),
+            ("ami", scalar(&self.ami, |v| v.val)),
+            ("v_measure", scalar(&self.v_measure, |v| v.val)),
+            ("purity", scalar(&self.purity, |v| v.val)),
+        ]
     }
 }
 
-impl Subscriber<BallHallIndexValue> for IndexTreeReturnValue {
-    fn recieve_data(&mut self, data: Result<BallHallIndexValue, CalcError>) {
-        self.ball_hall = Some(data);
+impl IndexTreeReturnValue {
+    // spreads the estimates of the sampled pair indexes over their fields
+    fn set_sampled(&mut self, data: SampledValue) {
+        for (index, estimate) in data.estimates {
+            if let Ok(estimate) = estimate.as_ref() {
+                self.estimates.push((index.name(), *estimate));
+            }
+            let val = estimate.map(|e| e.val);
+            match index {
+                // per-observation values of a subsample dont describe the whole dataset
+                PairIndex::Silhouette => {
+                    self.silhouette = Some(val.map(|val| SilhouetteIndexValue {
+                        val,
+                        samples: ArcArray1::zeros(0),
+                        clusters: ArcArray1::zeros(0),
+                        nearest: ArcArray1::zeros(0),
+                    }))
+                }
+                PairIndex::Hubert => self.hubert = Some(val.map(|val| HubertIndexValue { val })),
+                PairIndex::CIndex => self.c_index = Some(val.map(|val| CIndexValue { val })),
+                PairIndex::Dunn => {
+                    self.dunn = Some(val.map(|val| DunnIndexValue {
+                        val,
+                        diameters: ArcArray1::zeros(0),
+                        separations: ArcArray2::zeros((0, 0)),
+                    }))
+                }
+                PairIndex::Gamma => self.gamma = Some(val.map(|val| GammaIndexValue { val })),
+                PairIndex::Tau => self.tau = Some(val.map(|val| TauIndexValue { val })),
+                PairIndex::Gplus => self.gplus = Some(val.map(|val| GplusIndexValue { val })),
+                PairIndex::Mcclain => self.mcclain = Some(val.map(|val| McclainIndexValue { val })),
+                PairIndex::Ptbiserial => {
+                    self.ptbiserial = Some(val.map(|val| PtbiserialIndexValue { val }))
+                }
+            }
+        }
     }
 }
-impl Subscriber<DaviesBouldinIndexValue> for IndexTreeReturnValue {
-    fn recieve_data(&mut self, data: Result<DaviesBouldinIndexValue, CalcError>) {
-        self.davies_bouldin = Some(data);
-    }
+// moves a computed product into the field of the return value holding it
+type Output = Arc<dyn Fn(&Products, &mut IndexTreeReturnValue) + Send + Sync>;
+// the field of the return value holding a product
+type Field<T> = fn(&mut IndexTreeReturnValue) -> &mut Option<Result<T, CalcError>>;
+
+pub struct IndexTree<'a> {
+    plan: Plan,
+    outputs: Vec<Output>,
+    shared: Option<&'a SharedProducts>,
+    metric: Metric,
+    validation: ValidationConfig,
+    tracing: bool,
 }
-impl Subscriber<CIndexValue> for IndexTreeReturnValue {
-    fn recieve_data(&mut self, data: Result<CIndexValue, CalcError>) {
-        self.c_index = Some(data);
+impl<'a> IndexTree<'a> {
+    // the helper and index nodes the tree runs, and the ones it skips, as Graphviz DOT
+    pub fn dot(&self) -> String {
+        self.plan.dot()
     }
-}
-impl Subscriber<CalinskiHarabaszIndexValue> for IndexTreeReturnValue {
-    fn recieve_data(&mut self, data: Result<CalinskiHarabaszIndexValue, CalcError>) {
-        self.calinski_harabasz = Some(data);
+    pub fn compute(self, data: (ArrayView2<'a, f64>, ArrayView1<'a, i32>)) -> IndexTreeReturnValue {
+        self.compute_with_adjacent(data, None, None)
+    }
+    // `previous` and `next` are the partitions with one cluster less and one cluster more,
+    // required by indexes comparing neighbouring partitions (KL, Hartigan, Frey)
+    pub fn compute_with_adjacent(
+        self,
+        data: (ArrayView2<'a, f64>, ArrayView1<'a, i32>),
+        previous: Option<ArrayView1<'a, i32>>,
+        next: Option<ArrayView1<'a, i32>>,
+    ) -> IndexTreeReturnValue {
+        self.compute_with_partitions(data, previous, next, None)
+    }
+    // `reference` holds the ground truth labels required by external indexes
+    pub fn compute_with_reference(
+        self,
+        data: (ArrayView2<'a, f64>, ArrayView1<'a, i32>),
+        reference: ArrayView1<'a, i32>,
+    ) -> IndexTreeReturnValue {
+        self.compute_with_partitions(data, None, None, Some(reference))
+    }
+    pub fn compute_with_partitions(
+        self,
+        data: (ArrayView2<'a, f64>, ArrayView1<'a, i32>),
+        previous: Option<ArrayView1<'a, i32>>,
+        next: Option<ArrayView1<'a, i32>>,
+        reference: Option<ArrayView1<'a, i32>>,
+    ) -> IndexTreeReturnValue {
+        let (x, y) = data;
+        self.run(Ok(x), None, y, None, previous, next, reference)
+    }
+    // `weights` holds a non-negative weight for every observation, counts, centroids and
+    // dispersion matrices become weighted, so an integer weight acts like repeated rows,
+    // gap reports a weights error
+    pub fn compute_with_weights(
+        self,
+        data: (ArrayView2<'a, f64>, ArrayView1<'a, i32>),
+        weights: ArrayView1<'a, f64>,
+    ) -> IndexTreeReturnValue {
+        let (x, y) = data;
+        self.run(Ok(x), None, y, Some(weights), None, None, None)
+    }
+    // evaluates the partition `y` from dissimilarities alone, indexes that need the features
+    // (centroids, dispersion matrices) report an error
+    pub fn compute_with_distances(
+        self,
+        distances: DistanceMatrix<'a>,
+        y: ArrayView1<'a, i32>,
+        reference: Option<ArrayView1<'a, i32>>,
+    ) -> IndexTreeReturnValue {
+        let features = Err(CalcError::missing_input("Index requires feature data"));
+        self.run(features, Some(distances), y, None, None, None, reference)
+    }
+    // evaluates a partition with noise or arbitrary cluster ids, per-cluster outputs
+    // refer to the original ids listed in `cluster_ids`
+    pub fn compute_normalized(self, data: &'a NormalizedPartition<'_>) -> IndexTreeReturnValue {
+        let features = data
+            .features()
+            .ok_or_else(|| CalcError::missing_input("Index requires feature data"));
+        let res = self.run(
+            features,
+            data.distances(),
+            data.labels(),
+            data.weights(),
+            data.previous(),
+            data.next(),
+            data.reference(),
+        );
+        data.restore(res)
+    }
+    #[allow(clippy::too_many_arguments)]
+    fn run(
+        self,
+        features: Result<ArrayView2<'a, f64>, CalcError>,
+        distances: Option<DistanceMatrix<'a>>,
+        y: ArrayView1<'a, i32>,
+        weights: Option<ArrayView1<'a, f64>>,
+        previous: Option<ArrayView1<'a, i32>>,
+        next: Option<ArrayView1<'a, i32>>,
+        reference: Option<ArrayView1<'a, i32>>,
+    ) -> IndexTreeReturnValue {
+        let n = y.len();
+        // invalid input reaches every index as the same error. Shared features (and the
+        // distances computed from them) were checked once by `shared_products`
+        let valid = match (self.shared, features.as_ref()) {
+            (Some(_), Ok(x)) if x.nrows() != n => Err(CalcError::shape(
+                "Number of labels doesnt match number of observations",
+            )),
+            (Some(_), _) => validate(
+                None,
+                distances.as_ref(),
+                &y,
+                weights.as_ref(),
+                &self.validation,
+            ),
+            (None, features) => validate(
+                features.ok(),
+                distances.as_ref(),
+                &y,
+                weights.as_ref(),
+                &self.validation,
+            ),
+        };
+        let distances = distances.or_else(|| {
+            self.shared
+                .and_then(|shared| shared.distances.as_ref())
+                .map(|d| DistanceMatrix::Condensed(d.view()))
+        });
+        let (features, labels) = match valid {
+            Ok(()) => (features, Ok(y)),
+            Err(err) => (Err(err.clone()), Err(err)),
+        };
+        let partition = |y: Option<ArrayView1<'a, i32>>, name: &str| match y {
+            Some(y) if y.len() == n => Ok(y),
+            Some(_) => Err(CalcError::shape(format!(
+                "Length of {name} partition doesnt match number of observations"
+            ))),
+            None => Err(CalcError::missing_input(format!(
+                "Index requires {name} partition"
+            ))),
+        };
+        let adjacent = |y: Option<ArrayView1<'a, i32>>, name: &str| {
+            labels.clone().and_then(|_| partition(y, name))
+        };
+        let precomputed = match (&labels, distances) {
+            (Err(err), _) => Err(err.clone()),
+            (Ok(_), Some(d)) => Ok((d, n)),
+            (Ok(_), None) => Err(CalcError::from("Distances are computed from the features")),
+        };
+        let ctx = Context {
+            previous: adjacent(previous, "previous"),
+            next: adjacent(next, "next"),
+            reference: partition(reference, "reference"),
+            weights: labels.clone().map(|_| weights),
+            features,
+            labels: labels.clone(),
+            precomputed,
+            metric: self.metric.clone(),
+        };
+        let mut products = Products::default();
+        if let Some(shared) = self.shared {
+            let td = match (&labels, shared.td.as_ref()) {
+                (Err(err), _) => Err(err.clone()),
+                (Ok(_), Some(td)) => Ok(TDValue { val: td.clone() }),
+                (Ok(_), None) => Err(CalcError::from("Shared products lack total dispersion")),
+            };
+            products.insert(Side::Current, td);
+        }
+        let (products, profile) = if self.tracing {
+            let (products, profile) = self.plan.execute_traced(&ctx, products);
+            (products, Some(profile))
+        } else {
+            (self.plan.execute(&ctx, products), None)
+        };
+        let mut retval = IndexTreeReturnValue {
+            profile,
+            ..Default::default()
+        };
+        for output in &self.outputs {
+            output(&products, &mut retval);
+        }
+        retval
     }
 }
-impl Subscriber<DunnIndexValue> for IndexTreeReturnValue {
-    fn recieve_data(&mut self, data: Result<DunnIndexValue, CalcError>) {
-        self.dunn = Some(data);
-    }
+
+// nodes computing the products shared by several indexes, only the ones the
+// requested indexes depend on are run
+fn helpers(engine: PairwiseEngine) -> Graph {
+    let mut graph = Graph::default();
+    graph.add(counts::node());
+    graph.add(clusters_centroids::node());
+    graph.add(within_group_dispercion::node());
+    graph.add(between_group_dispercion::node());
+    graph.add(total_dispercion::node());
+    graph.add(scat::node());
+    graph.add(split::node());
+    graph.add(metric::node());
+    graph.add(distances::node(engine));
+    graph.add(pair_stats::node(engine));
+    graph.add(pairs_and_distances::node());
+    graph.add(s_plus_and_minus::node());
+    graph.add(contingency::node());
+    graph
 }
-impl Subscriber<SilhouetteIndexValue> for IndexTreeReturnValue {
-    fn recieve_data(&mut self, data: Result<SilhouetteIndexValue, CalcError>) {
-        self.silhouette = Some(data);
-    }
+
+#[derive(Default, Clone)]
+pub struct IndexTreeBuilder<'a> {
+    graph: Graph,
+    outputs: Vec<Output>,
+    engine: PairwiseEngine,
+    pair_indexes: Vec<PairIndex>,
+    sampling: Option<SamplingConfig>,
+    plugins: Vec<Arc<dyn IndexPlugin>>,
+    validation: ValidationConfig,
+    metric: Metric,
+    shared: Option<&'a SharedProducts>,
+    tracing: bool,
 }
-impl Subscriber<RubinIndexValue> for IndexTreeReturnValue {
-    fn recieve_data(&mut self, data: Result<RubinIndexValue, CalcError>) {
-        self.rubin = Some(data);
-    }
+
+// products that dont depend on the labels, computed once for all the partitions of the same
+// features (and weights) evaluated by trees built with the same selection
+pub struct SharedProducts {
+    td: Option<ArcArray2<f64>>,
+    distances: Option<ArcArray1<f64>>,
 }
-impl Subscriber<MariottIndexValue> for IndexTreeReturnValue {
-    fn recieve_data(&mut self, data: Result<MariottIndexValue, CalcError>) {
-        self.mariott = Some(data);
+
+impl<'a> IndexTreeBuilder<'a> {
+    // runs `node` and puts its product in the field of the return value holding it
+    fn add_index<T: Product>(mut self, node: Node<T>, field: Field<T>) -> Self {
+        self.graph.add(node);
+        self.add_product(field)
+    }
+    fn add_product<T: Product>(mut self, field: Field<T>) -> Self {
+        self.graph.request::<T>();
+        self.outputs.push(Arc::new(move |products, retval| {
+            *field(retval) = Some(products.get::<T>(Side::Current));
+        }));
+        self
     }
-}
-impl Subscriber<ScottIndexValue> for IndexTreeReturnValue {
-    fn recieve_data(&mut self, data: Result<ScottIndexValue, CalcError>) {
-        self.scott = Some(data);
+    // keeps the product of a helper node instead of dropping it once the indexes read it
+    pub fn add_helper(self, helper: Helper) -> Self {
+        match helper {
+            Helper::Counts => self.add_product(|r| &mut r.counts),
+            Helper::Centroids => self.add_product(|r| &mut r.centroids),
+            Helper::WithinGroupDispersion => self.add_product(|r| &mut r.wgd),
+            Helper::BetweenGroupDispersion => self.add_product(|r| &mut r.bgd),
+            Helper::TotalDispersion => self.add_product(|r| &mut r.td),
+            Helper::PairsAndDistances => self.add_product(|r| &mut r.pairs_and_distances),
+            Helper::Scat => self.add_product(|r| &mut r.scat),
+        }
     }
-}
-impl Subscriber<FriedmanIndexValue> for IndexTreeReturnValue {
-    fn recieve_data(&mut self, data: Result<FriedmanIndexValue, CalcError>) {
-        self.friedman = Some(data);
+    pub fn add_ball_hall(self) -> Self {
+        self.add_index(ball_hall::node(), |r| &mut r.ball_hall)
     }
-}
-impl Subscriber<TauIndexValue> for IndexTreeReturnValue {
-    fn recieve_data(&mut self, data: Result<TauIndexValue, CalcError>) {
-        self.tau = Some(data);
+    pub fn add_silhouette(self) -> Self {
+        self.add_pair_index(PairIndex::Silhouette)
     }
-}
-impl Subscriber<GammaIndexValue> for IndexTreeReturnValue {
-    fn recieve_data(&mut self, data: Result<GammaIndexValue, CalcError>) {
-        self.gamma = Some(data);
+    pub fn add_davies_bouldin(self) -> Self {
+        self.add_index(davies_bouldin::node(), |r| &mut r.davies_bouldin)
     }
-}
-impl Subscriber<GplusIndexValue> for IndexTreeReturnValue {
-    fn recieve_data(&mut self, data: Result<GplusIndexValue, CalcError>) {
-        self.gplus = Some(data);
+    pub fn add_calinski_harabasz(self) -> Self {
+        self.add_index(calinski_harabasz::node(), |r| &mut r.calinski_harabasz)
     }
-}
-impl Subscriber<TracewIndexValue> for IndexTreeReturnValue {
-    fn recieve_data(&mut self, data: Result<TracewIndexValue, CalcError>) {
-        self.tracew = Some(data);
+    pub fn add_c_index(self) -> Self {
+        self.add_pair_index(PairIndex::CIndex)
     }
-}
-impl Subscriber<McclainIndexValue> for IndexTreeReturnValue {
-    fn recieve_data(&mut self, data: Result<McclainIndexValue, CalcError>) {
-        self.mcclain = Some(data);
+    pub fn add_dunn(self) -> Self {
+        self.add_pair_index(PairIndex::Dunn)
     }
-}
-impl Subscriber<PtbiserialIndexValue> for IndexTreeReturnValue {
-    fn recieve_data(&mut self, data: Result<PtbiserialIndexValue, CalcError>) {
-        self.ptbiserial = Some(data);
+    pub fn add_rubin(self) -> Self {
+        self.add_index(rubin::node(), |r| &mut r.rubin)
     }
-}
-impl Subscriber<RatkowskyIndexValue> for IndexTreeReturnValue {
-    fn recieve_data(&mut self, data: Result<RatkowskyIndexValue, CalcError>) {
-        self.ratkowsky = Some(data);
+    pub fn add_mariott(self) -> Self {
+        self.add_index(mariott::node(), |r| &mut r.mariott)
     }
-}
-impl Subscriber<TrcovwIndexValue> for IndexTreeReturnValue {
-    fn recieve_data(&mut self, data: Result<TrcovwIndexValue, CalcError>) {
-        self.trcovw = Some(data);
+    pub fn add_scott(self) -> Self {
+        self.add_index(scott::node(), |r| &mut r.scott)
     }
-}
-impl Subscriber<HubertIndexValue> for IndexTreeReturnValue {
-    fn recieve_data(&mut self, data: Result<HubertIndexValue, CalcError>) {
-        self.hubert = Some(data);
+    pub fn add_friedman(self) -> Self {
+        self.add_index(friedman::node(), |r| &mut r.friedman)
     }
-}
-impl Subscriber<SDIndexValue> for IndexTreeReturnValue {
-    fn recieve_data(&mut self, data: Result<SDIndexValue, CalcError>) {
-        self.sd = Some(data);
+    pub fn add_tau(self) -> Self {
+        self.add_pair_index(PairIndex::Tau)
     }
-}
-impl Subscriber<SDBWIndexValue> for IndexTreeReturnValue {
-    fn recieve_data(&mut self, data: Result<SDBWIndexValue, CalcError>) {
-        self.sdbw = Some(data);
+    pub fn add_gamma(self) -> Self {
+        self.add_pair_index(PairIndex::Gamma)
     }
-}
-pub struct IndexTree<'a> {
-    raw_data: RawDataNode<'a>,
-    retval: Arc<Mutex<IndexTreeReturnValue>>,
-}
-impl<'a> IndexTree<'a> {
-    pub fn compute(self, data: (ArrayView2<'a, f64>, ArrayView1<'a, i32>)) -> IndexTreeReturnValue {
-        self.raw_data.compute(data);
-        match self.retval.lock() {
-            Ok(lock) => lock.clone(),
-            Err(poison_err) => poison_err.into_inner().clone(),
-        }
+    pub fn add_gplus(self) -> Self {
+        self.add_pair_index(PairIndex::Gplus)
     }
-}
-
-#[derive(Default)]
-pub struct IndexTreeBuilder<'a> {
-    retval: Arc<Mutex<IndexTreeReturnValue>>,
-    clusters_centroids_sender: Sender<'a, ClustersCentroidsValue>,
-    raw_data_sender: Sender<'a, (ArrayView2<'a, f64>, ArrayView1<'a, i32>)>,
-    pairs_and_distances_sender: Sender<'a, (ArcArray1<i8>, ArcArray1<f64>)>,
-    counts_sender: Sender<'a, ArcArray1<usize>>,
-    wg_sender: Sender<'a, WGDValue>,
-    bg_sender: Sender<'a, BGDValue>,
-    td_sender: Sender<'a, TDValue>,
-    s_plus_and_minus_sender: Sender<'a, (usize, usize, usize)>,
-    scat_sender: Sender<'a, ScatValue>,
-}
-
-impl<'a> IndexTreeBuilder<'a> {
-    pub fn add_ball_hall(mut self) -> Self {
-        let ball_hall = Arc::new(Mutex::new(BallHallNode::new(Sender::new(vec![self
-            .retval
-            .clone()]))));
-        self.wg_sender.add_subscriber(ball_hall.clone());
-        self.counts_sender.add_subscriber(ball_hall);
-        self
+    pub fn add_tracew(self) -> Self {
+        self.add_index(tracew::node(), |r| &mut r.tracew)
     }
-    pub fn add_silhouette(mut self) -> Self {
-        let silhouette = Arc::new(Mutex::new(SilhouetteNode::new(Sender::new(vec![self
-            .retval
-            .clone()]))));
-        self.raw_data_sender.add_subscriber(silhouette);
-        self
+    pub fn add_mcclain(self) -> Self {
+        self.add_pair_index(PairIndex::Mcclain)
     }
-    pub fn add_davies_bouldin(mut self) -> Self {
-        let davies_bouldin = Arc::new(Mutex::new(DaviesBouldinNode::new(Sender::new(vec![self
-            .retval
-            .clone()]))));
-        self.raw_data_sender.add_subscriber(davies_bouldin.clone());
-
-        self.clusters_centroids_sender
-            .add_subscriber(davies_bouldin);
-        self
+    pub fn add_ptbiserial(self) -> Self {
+        self.add_pair_index(PairIndex::Ptbiserial)
     }
-    pub fn add_calinski_harabasz(mut self) -> Self {
-        let calinski_harabasz = Arc::new(Mutex::new(CalinskiHarabaszNode::new(Sender::new(vec![
-            self.retval.clone(),
-        ]))));
-        self.wg_sender.add_subscriber(calinski_harabasz.clone());
-
-        self.bg_sender.add_subscriber(calinski_harabasz.clone());
-        self.counts_sender.add_subscriber(calinski_harabasz);
-        self
+    pub fn add_ratkowsky(self) -> Self {
+        self.add_index(ratkowsky::node(), |r| &mut r.ratkowsky)
     }
-    pub fn add_c_index(mut self) -> Self {
-        let c_index = Arc::new(Mutex::new(CIndexNode::new(Sender::new(vec![self
-            .retval
-            .clone()]))));
-        self.pairs_and_distances_sender.add_subscriber(c_index);
-        self
+    pub fn add_trcovw(self) -> Self {
+        self.add_index(trcovw::node(), |r| &mut r.trcovw)
     }
-    pub fn add_dunn(mut self) -> Self {
-        let dunn = Arc::new(Mutex::new(DunnNode::new(Sender::new(vec![self
-            .retval
-            .clone()]))));
-        self.pairs_and_distances_sender.add_subscriber(dunn);
-        self
+    pub fn add_hubert(self) -> Self {
+        self.add_pair_index(PairIndex::Hubert)
     }
-    pub fn add_rubin(mut self) -> Self {
-        let rubin = Arc::new(Mutex::new(RubinNode::new(Sender::new(vec![self
-            .retval
-            .clone()]))));
-        self.wg_sender.add_subscriber(rubin.clone());
-        self.td_sender.add_subscriber(rubin);
-        self
+    pub fn add_sd(self) -> Self {
+        self.add_index(sd::node(), |r| &mut r.sd)
     }
-    pub fn add_mariott(mut self) -> Self {
-        let mariott = Arc::new(Mutex::new(MariottNode::new(Sender::new(vec![self
-            .retval
-            .clone()]))));
-        self.wg_sender.add_subscriber(mariott.clone());
-
-        self.counts_sender.add_subscriber(mariott);
-        self
+    pub fn add_sdbw(self) -> Self {
+        self.add_index(sdbw::node(), |r| &mut r.sdbw)
     }
-    pub fn add_scott(mut self) -> Self {
-        let scott = Arc::new(Mutex::new(ScottNode::new(Sender::new(vec![self
-            .retval
-            .clone()]))));
-        self.wg_sender.add_subscriber(scott.clone());
-        self.td_sender.add_subscriber(scott.clone());
-
-        self.counts_sender.add_subscriber(scott);
-        self
+    pub fn add_kl(self) -> Self {
+        self.add_index(kl::node(), |r| &mut r.kl)
     }
-    pub fn add_friedman(mut self) -> Self {
-        let friedman = Arc::new(Mutex::new(FriedmanNode::new(Sender::new(vec![self
-            .retval
-            .clone()]))));
-        self.wg_sender.add_subscriber(friedman.clone());
-        self.bg_sender.add_subscriber(friedman);
-        self
+    pub fn add_hartigan(self) -> Self {
+        self.add_index(hartigan::node(), |r| &mut r.hartigan)
     }
-    pub fn add_tau(mut self) -> Self {
-        let tau = Arc::new(Mutex::new(TauNode::new(Sender::new(vec![self
-            .retval
-            .clone()]))));
-        self.s_plus_and_minus_sender.add_subscriber(tau.clone());
-        self.pairs_and_distances_sender.add_subscriber(tau);
-        self
+    pub fn add_frey(self) -> Self {
+        self.add_index(frey::node(), |r| &mut r.frey)
     }
-    pub fn add_gamma(mut self) -> Self {
-        let gamma = Arc::new(Mutex::new(GammaNode::new(Sender::new(vec![self
-            .retval
-            .clone()]))));
-        self.s_plus_and_minus_sender.add_subscriber(gamma);
-        self
+    pub fn add_duda(self) -> Self {
+        self.add_index(duda::node(), |r| &mut r.duda)
+    }
+    pub fn add_pseudot2(self) -> Self {
+        self.add_index(pseudot2::node(), |r| &mut r.pseudot2)
+    }
+    pub fn add_beale(self) -> Self {
+        self.add_index(beale::node(), |r| &mut r.beale)
+    }
+    pub fn add_gap(self, config: GapConfig) -> Self {
+        self.add_index(gap::node(config), |r| &mut r.gap)
+    }
+    pub fn add_ccc(self) -> Self {
+        self.add_index(ccc::node(), |r| &mut r.ccc)
+    }
+    pub fn add_dindex(self) -> Self {
+        self.add_index(dindex::node(), |r| &mut r.dindex)
+    }
+    pub fn add_adjusted_rand(self) -> Self {
+        self.add_index(adjusted_rand::node(), |r| &mut r.adjusted_rand)
+    }
+    pub fn add_rand(self) -> Self {
+        self.add_index(rand_index::node(), |r| &mut r.rand)
+    }
+    pub fn add_jaccard(self) -> Self {
+        self.add_index(jaccard::node(), |r| &mut r.jaccard)
+    }
+    pub fn add_fowlkes_mallows(self) -> Self {
+        self.add_index(fowlkes_mallows::node(), |r| &mut r.fowlkes_mallows)
+    }
+    pub fn add_nmi(self) -> Self {
+        self.add_index(mutual_information::nmi_node(), |r| &mut r.nmi)
+    }
+    pub fn add_ami(self) -> Self {
+        self.add_index(mutual_information::ami_node(), |r| &mut r.ami)
+    }
+    pub fn add_v_measure(self) -> Self {
+        self.add_index(v_measure::node(), |r| &mut r.v_measure)
     }
-    pub fn add_gplus(mut self) -> Self {
-        let gplus = Arc::new(Mutex::new(GplusNode::new(Sender::new(vec![self
-            .retval
-            .clone()]))));
-        self.s_plus_and_minus_sender.add_subscriber(gplus.clone());
-        self.pairs_and_distances_sender.add_subscriber(gplus);
+    pub fn add_purity(self) -> Self {
+        self.add_index(purity::node(), |r| &mut r.purity)
+    }
+    pub fn add_contingency(self) -> Self {
+        self.add_index(contingency::node(), |r| &mut r.contingency)
+    }
+    // custom index computed from the products of the helper nodes, its value is added
+    // to `plugins` of the return value
+    pub fn add_plugin(mut self, plugin: Arc<dyn IndexPlugin>) -> Self {
+        self.plugins.push(plugin);
         self
     }
-    pub fn add_tracew(mut self) -> Self {
-        let tracew = Arc::new(Mutex::new(TracewNode::new(Sender::new(vec![self
-            .retval
-            .clone()]))));
-        self.wg_sender.add_subscriber(tracew);
+    // pair-based indexes are wired in `finish`, once it is known whether they are sampled
+    pub(crate) fn add_pair_index(mut self, index: PairIndex) -> Self {
+        self.pair_indexes.push(index);
         self
     }
-    pub fn add_mcclain(mut self) -> Self {
-        let mcclain = Arc::new(Mutex::new(McclainNode::new(Sender::new(vec![self
-            .retval
-            .clone()]))));
-        self.pairs_and_distances_sender.add_subscriber(mcclain);
+    fn wire_pair_index(self, index: PairIndex) -> Self {
+        match index {
+            PairIndex::Silhouette => self.add_index(silhouette::node(), |r| &mut r.silhouette),
+            PairIndex::Hubert => self.add_index(hubert::node(), |r| &mut r.hubert),
+            PairIndex::CIndex => self.add_index(c_index::node(), |r| &mut r.c_index),
+            PairIndex::Dunn => self.add_index(dunn::node(), |r| &mut r.dunn),
+            PairIndex::Gamma => self.add_index(gamma::node(), |r| &mut r.gamma),
+            PairIndex::Tau => self.add_index(tau::node(), |r| &mut r.tau),
+            PairIndex::Gplus => self.add_index(gplus::node(), |r| &mut r.gplus),
+            PairIndex::Mcclain => self.add_index(mcclain::node(), |r| &mut r.mcclain),
+            PairIndex::Ptbiserial => self.add_index(ptbiserial::node(), |r| &mut r.ptbiserial),
+        }
+    }
+    fn wire_pair_indexes(mut self) -> Self {
+        let pair_indexes = std::mem::take(&mut self.pair_indexes);
+        match self.sampling {
+            Some(config) if !pair_indexes.is_empty() => {
+                let node = sampling::node(config, pair_indexes.clone(), self.engine);
+                self.graph.add(node);
+                self.graph.request::<SampledValue>();
+                self.outputs.push(Arc::new(move |products, retval| {
+                    // errors are reported per index so that every sampled index receives one
+                    let sampled =
+                        products
+                            .get::<SampledValue>(Side::Current)
+                            .unwrap_or_else(|err| SampledValue {
+                                estimates: pair_indexes
+                                    .iter()
+                                    .map(|index| (*index, Err(err.clone())))
+                                    .collect(),
+                            });
+                    retval.set_sampled(sampled);
+                }));
+                self
+            }
+            Some(_) => self,
+            None => pair_indexes.into_iter().fold(self, Self::wire_pair_index),
+        }
+    }
+    fn wire_plugins(mut self) -> Self {
+        let plugins = std::mem::take(&mut self.plugins);
+        if plugins.is_empty() {
+            return self;
+        }
+        let names = plugins
+            .iter()
+            .map(|plugin| plugin.name().to_string())
+            .collect::<Vec<String>>();
+        self.graph.add(plugin::node(plugins));
+        self.graph.request::<PluginsValue>();
+        self.outputs.push(Arc::new(move |products, retval| {
+            // errors are reported per index, as for the sampled indexes
+            let values = products
+                .get::<PluginsValue>(Side::Current)
+                .unwrap_or_else(|err| PluginsValue {
+                    values: names
+                        .iter()
+                        .map(|name| (name.clone(), Err(err.clone())))
+                        .collect(),
+                });
+            retval.plugins.extend(values.values);
+        }));
         self
     }
-    pub fn add_ptbiserial(mut self) -> Self {
-        let ptbiserial = Arc::new(Mutex::new(PtbiserialNode::new(Sender::new(vec![self
-            .retval
-            .clone()]))));
-        self.pairs_and_distances_sender.add_subscriber(ptbiserial);
+    fn wire(self) -> Self {
+        self.wire_pair_indexes().wire_plugins()
+    }
+    // the selected indexes on top of the helper nodes, which they may replace
+    fn graph(&self) -> Graph {
+        let mut graph = helpers(self.engine);
+        graph.extend(&self.graph);
+        graph
+    }
+    // estimates pair-based indexes on subsamples instead of the whole dataset
+    pub fn sampling(mut self, config: SamplingConfig) -> Self {
+        self.sampling = Some(config);
         self
     }
-    pub fn add_ratkowsky(mut self) -> Self {
-        let ratkowsky = Arc::new(Mutex::new(RatkowskyNode::new(Sender::new(vec![self
-            .retval
-            .clone()]))));
-        self.counts_sender.add_subscriber(ratkowsky.clone());
-
-        self.td_sender.add_subscriber(ratkowsky.clone());
-        self.bg_sender.add_subscriber(ratkowsky);
+    // checks applied to the input before any index is computed
+    pub fn validation(mut self, config: ValidationConfig) -> Self {
+        self.validation = config;
         self
     }
-    pub fn add_trcovw(mut self) -> Self {
-        let trcovw = Arc::new(Mutex::new(TrcovwNode::new(Sender::new(vec![self
-            .retval
-            .clone()]))));
-
-        self.wg_sender.add_subscriber(trcovw);
+    // upper bound in bytes on the pairwise distance blocks computed at the same time
+    pub fn memory_budget(mut self, bytes: usize) -> Self {
+        self.engine = PairwiseEngine {
+            memory_budget: bytes,
+        };
         self
     }
-    pub fn add_hubert(mut self) -> Self {
-        let hubert = Arc::new(Mutex::new(HubertNode::new(Sender::new(vec![self
-            .retval
-            .clone()]))));
-
-        self.raw_data_sender.add_subscriber(hubert.clone());
-
-        self.clusters_centroids_sender.add_subscriber(hubert);
+    // records the time, thread and memory of every node in `profile` of the return value
+    pub fn tracing(mut self, enabled: bool) -> Self {
+        self.tracing = enabled;
         self
     }
-    pub fn add_sd(mut self) -> Self {
-        let sd = Arc::new(Mutex::new(SDNode::new(Sender::new(vec![self
-            .retval
-            .clone()]))));
-
-        self.scat_sender.add_subscriber(sd.clone());
-
-        self.clusters_centroids_sender.add_subscriber(sd);
+    // distance used by every index measuring distances, euclidean by default
+    pub fn metric(mut self, metric: Metric) -> Self {
+        self.metric = metric;
         self
     }
-    pub fn add_sdbw(mut self) -> Self {
-        let sdbw = Arc::new(Mutex::new(SDBWNode::new(Sender::new(vec![self
-            .retval
-            .clone()]))));
-
-        self.raw_data_sender.add_subscriber(sdbw.clone());
-        self.scat_sender.add_subscriber(sdbw.clone());
-
-        self.clusters_centroids_sender.add_subscriber(sdbw);
+    // computes the label-independent products the selected indexes need, the features are
+    // checked here once for all the partitions evaluated with them
+    pub fn shared_products(
+        &self,
+        x: &ArrayView2<f64>,
+        weights: Option<&ArrayView1<f64>>,
+    ) -> Result<SharedProducts, CalcError> {
+        validate_features(x)?;
+        let plan = self.clone().wire().graph().plan(&[]);
+        let td = if plan.computes::<TDValue>() {
+            Some(TD.compute(x, weights)?)
+        } else {
+            None
+        };
+        // sampled pair indexes dont need every distance
+        let needs_distances =
+            plan.computes::<DistancesValue>() || plan.computes::<PairStatsValue>();
+        let distances = if needs_distances {
+            let metric = self.metric.fit(x)?;
+            let source = DistanceSource::Features(x.view(), &metric);
+            Some(Distances::new(self.engine).compute(&source, x.nrows())?)
+        } else {
+            None
+        };
+        Ok(SharedProducts { td, distances })
+    }
+    // products computed by `shared_products` from the same features, they replace
+    // the nodes computing them
+    pub fn shared(mut self, shared: &'a SharedProducts) -> Self {
+        self.shared = Some(shared);
         self
     }
-    pub fn finish(mut self) -> IndexTree<'a> {
-        if !self.scat_sender.is_empty() {
-            let scat = Arc::new(Mutex::new(ScatNode::new(self.scat_sender)));
-            self.raw_data_sender.add_subscriber(scat.clone());
-        }
-        if !self.td_sender.is_empty() {
-            let td = Arc::new(Mutex::new(TDNode::new(self.td_sender)));
-            self.raw_data_sender.add_subscriber(td.clone());
-        }
-        if !self.bg_sender.is_empty() {
-            let bgd = Arc::new(Mutex::new(BGDNode::new(self.bg_sender)));
-            self.raw_data_sender.add_subscriber(bgd.clone());
-            self.clusters_centroids_sender.add_subscriber(bgd);
-        }
-        if !self.wg_sender.is_empty() {
-            let wgd = Arc::new(Mutex::new(WGDNode::new(self.wg_sender)));
-            self.raw_data_sender.add_subscriber(wgd.clone());
-            self.clusters_centroids_sender.add_subscriber(wgd);
-        }
-        if !self.clusters_centroids_sender.is_empty() {
-            let clusters_centroids = Arc::new(Mutex::new(ClustersCentroidsNode::new(
-                self.clusters_centroids_sender,
-            )));
-            self.raw_data_sender
-                .add_subscriber(clusters_centroids.clone());
-            self.counts_sender.add_subscriber(clusters_centroids);
-        }
-        if !self.counts_sender.is_empty() {
-            let counts = Arc::new(Mutex::new(CountsNode::new(self.counts_sender)));
-            self.raw_data_sender.add_subscriber(counts);
-        }
-
-        if !self.s_plus_and_minus_sender.is_empty() {
-            let spm = Arc::new(Mutex::new(SPlusAndMinusNode::new(
-                self.s_plus_and_minus_sender,
-            )));
-            self.pairs_and_distances_sender.add_subscriber(spm);
-        }
-        if !self.pairs_and_distances_sender.is_empty() {
-            let pairs_and_distances = Arc::new(Mutex::new(PairsAndDistancesNode::new(
-                self.pairs_and_distances_sender,
-            )));
-            self.raw_data_sender.add_subscriber(pairs_and_distances);
-        }
-        let raw_data = RawDataNode::new(self.raw_data_sender);
+    pub fn finish(self) -> IndexTree<'a> {
+        let builder = self.wire();
+        let provided = match builder.shared {
+            Some(_) => vec![Key::of::<TDValue>(Side::Current)],
+            None => Vec::new(),
+        };
         IndexTree {
-            raw_data,
-            retval: self.retval,
+            plan: builder.graph().plan(&provided),
+            outputs: builder.outputs,
+            shared: builder.shared,
+            metric: builder.metric,
+            validation: builder.validation,
+            tracing: builder.tracing,
         }
     }
 }
@@ -0,0 +1,34 @@
+use crate::calc_error::CalcError;
+use crate::dataflow::{Node, Product};
+use ndarray::ArrayView2;
+
+use super::helpers::contingency::{pair_counts, ContingencyValue};
+
+#[derive(Clone, Copy, Debug)]
+pub struct AdjustedRandIndexValue {
+    pub val: f64,
+}
+impl Product for AdjustedRandIndexValue {}
+#[derive(Default)]
+pub struct Index;
+impl Index {
+    fn compute(&self, contingency: &ArrayView2<usize>) -> Result<f64, CalcError> {
+        let (both, only_class, only_cluster, neither) = pair_counts(contingency);
+        let same_class = both + only_class;
+        let same_cluster = both + only_cluster;
+        let expected = same_class * same_cluster / (both + only_class + only_cluster + neither);
+        let max = (same_class + same_cluster) / 2.;
+        // both partitions put every observation in one cluster, or every one apart
+        if max == expected {
+            return Ok(1.);
+        }
+        Ok((both - expected) / (max - expected))
+    }
+}
+pub fn node() -> Node<AdjustedRandIndexValue> {
+    Node::new("adjusted_rand", |_, (contingency,): (ContingencyValue,)| {
+        Index
+            .compute(&contingency.val.view())
+            .map(|val| AdjustedRandIndexValue { val })
+    })
+}
@@ -1,35 +1,133 @@
-use super::*;
-use itertools::Itertools;
-pub struct Index {}
+use crate::calc_error::CalcError;
+use crate::dataflow::{Node, Product};
+use ndarray::{ArrayView1, ArrayView2};
+use std::f64::consts::PI;
+
+use super::helpers::split::{Split, SplitValue};
+
+#[derive(Clone, Copy, Debug)]
+pub struct BealeIndexValue {
+    pub val: f64,
+    // of the F test with p and (n - 2) p degrees of freedom
+    pub p_value: f64,
+}
+impl Product for BealeIndexValue {}
+impl BealeIndexValue {
+    // the parent is kept as a single cluster
+    pub fn accepted(&self) -> bool {
+        self.p_value >= 0.1
+    }
+}
+
+#[derive(Default)]
+pub struct Index;
 impl Index {
-    fn compute(&self, x: ArrayView2<f64>, y: ArrayView1<i32>) -> Result<f64, CalcError> {
-        if y.iter().counts().keys().len() != 2 {
-            return Err(CalcError::from("There is more than 2 clusters"));
+    // members of the parent cluster and the child each of them goes to
+    pub fn compute(
+        &self,
+        x: &ArrayView2<f64>,
+        y: &ArrayView1<i32>,
+        weights: Option<&ArrayView1<f64>>,
+    ) -> Result<BealeIndexValue, CalcError> {
+        Split.compute(x, y, weights).map(|split| self.value(&split))
+    }
+    fn value(&self, split: &SplitValue) -> BealeIndexValue {
+        let n = split.n;
+        let p = split.p as f64;
+        let je1 = split.parent_wss;
+        let je2 = split.children_wss;
+        let val = ((je1 - je2) / je2) / ((n - 1.) / (n - 2.) * 2_f64.powf(2. / p) - 1.);
+        BealeIndexValue {
+            val,
+            p_value: 1. - f_cdf(val, p, (n - 2.) * p),
         }
-        let dataset_mean = x.mean_axis(Axis(0)).ok_or("Cant calc mean")?;
-        let clusters = calc_clusters(&y);
-        let cluster_centers = calc_clusters_centers(&clusters, &x);
-
-        let within_group_dispersion_parent = {
-            let diff = &x - &dataset_mean;
-            diff.dot(&diff.t()).diag().sum()
-        };
-
-        let mut within_group_dispersion_children: HashMap<i32, f64> = HashMap::default();
-        for (cl_idx, idxs) in clusters.iter() {
-            for idx in idxs {
-                let diff = &x.row(*idx) - &cluster_centers[cl_idx];
-
-                within_group_dispersion_children
-                    .entry(*cl_idx)
-                    .and_modify(|v| *v += diff.dot(&diff))
-                    .or_insert(0.);
-            }
+    }
+}
+
+// P(X <= f) for X following the F distribution with d1 and d2 degrees of freedom
+fn f_cdf(f: f64, d1: f64, d2: f64) -> f64 {
+    if f.is_nan() {
+        return f64::NAN;
+    }
+    if f <= 0. {
+        return 0.;
+    }
+    regularized_beta(d1 * f / (d1 * f + d2), d1 / 2., d2 / 2.)
+}
+
+// I_x(a, b), the continued fraction converges quickly below (a + 1) / (a + b + 2) and the
+// symmetry I_x(a, b) = 1 - I_1-x(b, a) is used above
+fn regularized_beta(x: f64, a: f64, b: f64) -> f64 {
+    if x <= 0. {
+        return 0.;
+    }
+    if x >= 1. {
+        return 1.;
+    }
+    let front =
+        (ln_gamma(a + b) - ln_gamma(a) - ln_gamma(b) + a * x.ln() + b * (1. - x).ln()).exp();
+    if x < (a + 1.) / (a + b + 2.) {
+        front * beta_fraction(x, a, b) / a
+    } else {
+        1. - front * beta_fraction(1. - x, b, a) / b
+    }
+}
+
+// continued fraction of the incomplete beta function by the modified Lentz method
+fn beta_fraction(x: f64, a: f64, b: f64) -> f64 {
+    const EPS: f64 = 1e-14;
+    const TINY: f64 = 1e-300;
+    let clamp = |v: f64| if v.abs() < TINY { TINY } else { v };
+    let mut c = 1.;
+    let mut d = 1. / clamp(1. - (a + b) * x / (a + 1.));
+    let mut h = d;
+    for m in 1..=300 {
+        let m = m as f64;
+        // even and odd terms of the fraction
+        let even = m * (b - m) * x / ((a + 2. * m - 1.) * (a + 2. * m));
+        d = 1. / clamp(1. + even * d);
+        c = clamp(1. + even / c);
+        h *= d * c;
+        let odd = -(a + m) * (a + b + m) * x / ((a + 2. * m) * (a + 2. * m + 1.));
+        d = 1. / clamp(1. + odd * d);
+        c = clamp(1. + odd / c);
+        h *= d * c;
+        if (d * c - 1.).abs() < EPS {
+            break;
         }
-        let n = x.nrows();
-        let w_children_sum = within_group_dispersion_children.values().sum::<f64>();
-        let value = ((within_group_dispersion_parent - w_children_sum) / w_children_sum)
-            / (2.0 * (((n - 1) as f64) / ((n - 2) as f64)).powf(2.0 / x.ncols() as f64) - 1.0);
-        Ok(value)
     }
+    h
+}
+
+// Lanczos approximation with g = 7
+fn ln_gamma(x: f64) -> f64 {
+    const COEFFICIENTS: [f64; 9] = [
+        0.999_999_999_999_809_9,
+        676.520_368_121_885_1,
+        -1_259.139_216_722_402_8,
+        771.323_428_777_653_1,
+        -176.615_029_162_140_6,
+        12.507_343_278_686_905,
+        -0.138_571_095_265_720_12,
+        9.984_369_578_019_572e-6,
+        1.505_632_735_149_311_6e-7,
+    ];
+    if x < 0.5 {
+        // reflection formula
+        return (PI / (PI * x).sin()).ln() - ln_gamma(1. - x);
+    }
+    let x = x - 1.;
+    let t = x + 7.5;
+    let sum = COEFFICIENTS[1..]
+        .iter()
+        .enumerate()
+        .fold(COEFFICIENTS[0], |acc, (i, c)| acc + c / (x + i as f64 + 1.));
+    0.5 * (2. * PI).ln() + (x + 0.5) * t.ln() - t + sum.ln()
+}
+
+pub fn node() -> Node<BealeIndexValue> {
+    Node::new(
+        "beale",
+        |_, (split,): (SplitValue,)| Ok(Index.value(&split)),
+    )
 }
@@ -1,14 +1,16 @@
 use std::iter::zip;
 
+use super::helpers::pairs_and_distances::PairsAndDistancesValue;
 use crate::calc_error::CalcError;
-use crate::sender::{Sender, Subscriber};
+use crate::dataflow::{Node, Product};
 use itertools::Itertools;
-use ndarray::{ArcArray1, ArrayView1};
+use ndarray::ArrayView1;
 
 #[derive(Clone, Copy, Debug)]
 pub struct CIndexValue {
     pub val: f64,
 }
+impl Product for CIndexValue {}
 #[derive(Default)]
 pub struct Index;
 
@@ -25,7 +27,7 @@ impl Index {
         let sorted_distances = distances
             .iter()
             .sorted_unstable_by(|a, b| a.total_cmp(b))
-            .map(|e| *e)
+            .copied()
             .collect::<Vec<f64>>();
 
         //calculating sum of Nw minimum and maximum distances
@@ -94,29 +96,10 @@ impl Index {
     }
 }
 
-pub struct Node<'a> {
-    index: Index,
-    sender: Sender<'a, CIndexValue>,
-}
-
-impl<'a> Node<'a> {
-    pub fn new(sender: Sender<'a, CIndexValue>) -> Self {
-        Self {
-            index: Index,
-            sender,
-        }
-    }
-}
-
-impl<'a> Subscriber<(ArcArray1<i8>, ArcArray1<f64>)> for Node<'a> {
-    fn recieve_data(&mut self, data: Result<(ArcArray1<i8>, ArcArray1<f64>), CalcError>) {
-        let res = match data.as_ref() {
-            Ok((p, d)) => self
-                .index
-                .compute(&p.view(), &d.view())
-                .map(|val| CIndexValue { val }),
-            Err(err) => Err(err.clone()),
-        };
-        self.sender.send_to_subscribers(res);
-    }
+pub fn node() -> Node<CIndexValue> {
+    Node::new("c_index", |_, (pairs,): (PairsAndDistancesValue,)| {
+        Index
+            .compute(&pairs.pairs.view(), &pairs.distances.view())
+            .map(|val| CIndexValue { val })
+    })
 }
@@ -1,15 +1,23 @@
-use crate::calc_error::{CalcError, CombineErrors};
-use ndarray::{ArcArray2, Array1, ArrayView1, ArrayView2};
+use crate::calc_error::CalcError;
+use ndarray::{ArcArray1, Array1, ArrayView1, ArrayView2};
 use std::iter::zip;
 
-use crate::sender::{Sender, Subscriber};
+use crate::dataflow::{Node, Product};
+use crate::metric::Metric;
 
 use super::helpers::clusters_centroids::ClustersCentroidsValue;
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Debug)]
 pub struct DaviesBouldinIndexValue {
     pub val: f64,
+    // mean distance of the observations of every cluster to its centroid
+    pub scatter: ArcArray1<f64>,
+    // R_i, the worst similarity ratio of every cluster
+    pub ratios: ArcArray1<f64>,
+    // the cluster every R_i is attained with
+    pub worst: ArcArray1<i32>,
 }
+impl Product for DaviesBouldinIndexValue {}
 #[derive(Default)]
 pub struct Index;
 
@@ -19,15 +27,13 @@ impl Index {
         x: &ArrayView2<f64>,
         y: &ArrayView1<i32>,
         clusters_centroids: &ArrayView2<f64>,
-    ) -> Result<f64, CalcError> {
+        metric: &Metric,
+    ) -> Result<DaviesBouldinIndexValue, CalcError> {
         let q = clusters_centroids.nrows();
         let mut distances_to_center: Vec<Vec<f64>> = Vec::new();
         distances_to_center.resize(q, Vec::default());
         for (x, y) in zip(x.rows(), y) {
-            let d = (&x - &clusters_centroids.row(*y as usize))
-                .pow2()
-                .sum()
-                .sqrt();
+            let d = metric.distance(x, clusters_centroids.row(*y as usize))?;
             distances_to_center[*y as usize].push(d);
         }
         let mean_distances = distances_to_center
@@ -37,25 +43,31 @@ impl Index {
             .ok_or("Cant calc mean")?;
 
         let mut stor = Array1::zeros(q);
+        let mut worst = Array1::zeros(q);
         for i in 0..q {
             let mut arr = Vec::with_capacity(q - 1);
             for j in 0..q {
                 if i != j {
-                    let center_dist = (&clusters_centroids.row(i) - &clusters_centroids.row(j))
-                        .pow2()
-                        .sum()
-                        .sqrt();
+                    let center_dist =
+                        metric.distance(clusters_centroids.row(i), clusters_centroids.row(j))?;
                     let coef = (mean_distances[i] + mean_distances[j]) / center_dist;
-                    arr.push(coef);
+                    arr.push((j, coef));
                 }
             }
-            let max = arr
+            let (j, max) = arr
                 .into_iter()
-                .max_by(|a, b| a.total_cmp(b))
+                .max_by(|a, b| a.1.total_cmp(&b.1))
                 .ok_or("Cant calc max")?;
             *stor.get_mut(i).ok_or("Cant save max val")? = max;
+            worst[i] = j as i32;
         }
-        stor.mean().ok_or("Cant get mean".into())
+        let val = stor.mean().ok_or("Cant get mean")?;
+        Ok(DaviesBouldinIndexValue {
+            val,
+            scatter: Array1::from_vec(mean_distances).into_shared(),
+            ratios: stor.into_shared(),
+            worst: worst.into_shared(),
+        })
 
         // let mut stor: HashMap<i32, f64> = HashMap::default();
         //
@@ -94,52 +106,12 @@ impl Index {
     }
 }
 
-pub struct Node<'a> {
-    index: Index,
-    raw_data: Option<Result<(ArrayView2<'a, f64>, ArrayView1<'a, i32>), CalcError>>,
-    clusters_centroids: Option<Result<ArcArray2<f64>, CalcError>>,
-    sender: Sender<'a, DaviesBouldinIndexValue>,
-}
-
-impl<'a> Node<'a> {
-    fn process_when_ready(&mut self) {
-        if let (Some(raw_data), Some(clusters_centroids)) =
-            (self.raw_data.as_ref(), self.clusters_centroids.as_ref())
-        {
-            let res = match raw_data.combine(clusters_centroids) {
-                Ok(((x, y), cls_ctrds)) => self
-                    .index
-                    .compute(x, y, &cls_ctrds.view())
-                    .map(|val| DaviesBouldinIndexValue { val }),
-                Err(err) => Err(err),
-            };
-            self.sender.send_to_subscribers(res);
-            self.raw_data = None;
-            self.clusters_centroids = None;
-        }
-    }
-    pub fn new(sender: Sender<'a, DaviesBouldinIndexValue>) -> Self {
-        Self {
-            index: Index,
-            raw_data: None,
-            clusters_centroids: None,
-            sender,
-        }
-    }
-}
-
-impl<'a> Subscriber<(ArrayView2<'a, f64>, ArrayView1<'a, i32>)> for Node<'a> {
-    fn recieve_data(
-        &mut self,
-        data: Result<(ArrayView2<'a, f64>, ArrayView1<'a, i32>), CalcError>,
-    ) {
-        self.raw_data = Some(data);
-        self.process_when_ready();
-    }
-}
-impl<'a> Subscriber<ClustersCentroidsValue> for Node<'a> {
-    fn recieve_data(&mut self, data: Result<ClustersCentroidsValue, CalcError>) {
-        self.clusters_centroids = Some(data.map(|v| v.val));
-        self.process_when_ready();
-    }
+pub fn node() -> Node<DaviesBouldinIndexValue> {
+    Node::new(
+        "davies_bouldin",
+        |scope, (centroids, metric): (ClustersCentroidsValue, Metric)| {
+            let (x, y) = scope.raw_data()?;
+            Index.compute(&x, &y, &centroids.val.view(), &metric)
+        },
+    )
 }
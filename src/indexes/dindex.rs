@@ -0,0 +1,46 @@
+use crate::calc_error::CalcError;
+use ndarray::{ArrayView1, ArrayView2};
+use std::iter::zip;
+
+use crate::dataflow::{Node, Product};
+use crate::metric::Metric;
+
+use super::helpers::clusters_centroids::ClustersCentroidsValue;
+
+// Graphical index, the number of clusters is read from the knee of the curve over k,
+// see `NumberOfClustersValue::curve`
+#[derive(Clone, Copy, Debug)]
+pub struct DIndexValue {
+    pub val: f64,
+}
+impl Product for DIndexValue {}
+#[derive(Default)]
+pub struct Index;
+
+impl Index {
+    // mean distance of the observations to their cluster centroid
+    pub fn compute(
+        &self,
+        x: &ArrayView2<f64>,
+        y: &ArrayView1<i32>,
+        clusters_centroids: &ArrayView2<f64>,
+        metric: &Metric,
+    ) -> Result<f64, CalcError> {
+        let distances = zip(x.rows(), y)
+            .map(|(x, y)| metric.distance(x, clusters_centroids.row(*y as usize)))
+            .sum::<Result<f64, CalcError>>()?;
+        Ok(distances / x.nrows() as f64)
+    }
+}
+
+pub fn node() -> Node<DIndexValue> {
+    Node::new(
+        "dindex",
+        |scope, (centroids, metric): (ClustersCentroidsValue, Metric)| {
+            let (x, y) = scope.raw_data()?;
+            Index
+                .compute(&x, &y, &centroids.val.view(), &metric)
+                .map(|val| DIndexValue { val })
+        },
+    )
+}
@@ -1,42 +1,50 @@
-use crate::calc_error::{CalcError, CombineErrors};
-use ndarray::{Array1, ArrayView1, ArrayView2, Axis};
-use std::{collections::HashMap, sync::Arc};
+use crate::calc_error::CalcError;
+use crate::dataflow::{Node, Product};
+use ndarray::{ArrayView1, ArrayView2};
+use std::f64::consts::PI;
 
-use super::{Sender, Subscriber};
-use rayon::prelude::*;
-pub struct Index {}
+use super::helpers::split::{Split, SplitValue};
+
+#[derive(Clone, Copy, Debug)]
+pub struct DudaIndexValue {
+    pub val: f64,
+    // value below which the split is significant, z = 3.2 as in Milligan & Cooper
+    pub critical: f64,
+}
+impl Product for DudaIndexValue {}
+impl DudaIndexValue {
+    // the parent is kept as a single cluster
+    pub fn accepted(&self) -> bool {
+        self.val >= self.critical
+    }
+}
+
+// Je(2) / Je(1) of the split of a cluster in two
+#[derive(Default)]
+pub struct Index;
 impl Index {
+    // members of the parent cluster and the child each of them goes to
     pub fn compute(
         &self,
         x: &ArrayView2<f64>,
-        clusters_centroids: &HashMap<i32, Array1<f64>>,
-        clusters: &HashMap<i32, Array1<usize>>,
-    ) -> Result<f64, CalcError> {
-        if y.iter().counts().keys().len() != 2 {
-            return Err(CalcError::from("There is more than 2 clusters"));
+        y: &ArrayView1<i32>,
+        weights: Option<&ArrayView1<f64>>,
+    ) -> Result<DudaIndexValue, CalcError> {
+        Split.compute(x, y, weights).map(|split| self.value(&split))
+    }
+    fn value(&self, split: &SplitValue) -> DudaIndexValue {
+        DudaIndexValue {
+            val: split.children_wss / split.parent_wss,
+            critical: critical_value(split),
         }
+    }
+}
 
-        let dataset_mean = x.mean_axis(Axis(0)).ok_or("Cant calc mean")?;
-
-        let within_group_dispersion_parent = {
-            let diff = &x - &dataset_mean;
-            diff.dot(&diff.t()).diag().sum()
-        };
-
-        let mut within_group_dispersion_children: HashMap<i32, f64> = HashMap::default();
-        for (cl_idx, idxs) in clusters.iter() {
-            for idx in idxs {
-                let diff = &x.row(*idx) - &cluster_centers[cl_idx];
-
-                within_group_dispersion_children
-                    .entry(*cl_idx)
-                    .and_modify(|v| *v += diff.dot(&diff))
-                    .or_insert(0.);
-            }
-        }
+pub fn critical_value(split: &SplitValue) -> f64 {
+    let p = split.p as f64;
+    1. - 2. / (PI * p) - 3.2 * (2. * (1. - 8. / (PI * PI * p)) / (split.n * p)).sqrt()
+}
 
-        let value = within_group_dispersion_parent
-            / (within_group_dispersion_children.values().sum::<f64>());
-        Ok(value)
-    }
+pub fn node() -> Node<DudaIndexValue> {
+    Node::new("duda", |_, (split,): (SplitValue,)| Ok(Index.value(&split)))
 }
@@ -0,0 +1,29 @@
+use crate::calc_error::CalcError;
+use crate::dataflow::{Node, Product};
+use ndarray::ArrayView2;
+
+use super::helpers::contingency::{pair_counts, ContingencyValue};
+
+#[derive(Clone, Copy, Debug)]
+pub struct FowlkesMallowsIndexValue {
+    pub val: f64,
+}
+impl Product for FowlkesMallowsIndexValue {}
+#[derive(Default)]
+pub struct Index;
+impl Index {
+    fn compute(&self, contingency: &ArrayView2<usize>) -> Result<f64, CalcError> {
+        let (both, only_class, only_cluster, _) = pair_counts(contingency);
+        Ok(both / ((both + only_class) * (both + only_cluster)).sqrt())
+    }
+}
+pub fn node() -> Node<FowlkesMallowsIndexValue> {
+    Node::new(
+        "fowlkes_mallows",
+        |_, (contingency,): (ContingencyValue,)| {
+            Index
+                .compute(&contingency.val.view())
+                .map(|val| FowlkesMallowsIndexValue { val })
+        },
+    )
+}
@@ -0,0 +1,64 @@
+use crate::calc_error::CalcError;
+use crate::dataflow::{Node, Product};
+use ndarray::ArrayView1;
+use std::iter::zip;
+
+use super::helpers::{adjacent::NextPartition, pairs_and_distances::PairsAndDistancesValue};
+
+#[derive(Clone, Copy, Debug)]
+pub struct FreyIndexValue {
+    pub val: f64,
+}
+impl Product for FreyIndexValue {}
+#[derive(Default)]
+pub struct Index;
+impl Index {
+    fn mean_distances(
+        pairs_in_the_same_cluster: &ArrayView1<i8>,
+        distances: &ArrayView1<f64>,
+    ) -> (f64, f64) {
+        let (mut sw, mut nw, mut sb, mut nb) = (0., 0., 0., 0.);
+        for (p, d) in zip(pairs_in_the_same_cluster, distances) {
+            if *p == 1 {
+                sw += d;
+                nw += 1.;
+            } else {
+                sb += d;
+                nb += 1.;
+            }
+        }
+        (sw / nw, sb / nb)
+    }
+    fn compute(
+        &self,
+        pairs_in_the_same_cluster: &ArrayView1<i8>,
+        distances: &ArrayView1<f64>,
+        pairs_in_the_same_cluster_next: &ArrayView1<i8>,
+        distances_next: &ArrayView1<f64>,
+    ) -> Result<f64, CalcError> {
+        let (within, between) = Self::mean_distances(pairs_in_the_same_cluster, distances);
+        let (within_next, between_next) =
+            Self::mean_distances(pairs_in_the_same_cluster_next, distances_next);
+        Ok((between_next - between) / (within_next - within))
+    }
+}
+
+pub fn node() -> Node<FreyIndexValue> {
+    Node::new(
+        "frey",
+        |_,
+         (pairs, NextPartition(next)): (
+            PairsAndDistancesValue,
+            NextPartition<PairsAndDistancesValue>,
+        )| {
+            Index
+                .compute(
+                    &pairs.pairs.view(),
+                    &pairs.distances.view(),
+                    &next.pairs.view(),
+                    &next.distances.view(),
+                )
+                .map(|val| FreyIndexValue { val })
+        },
+    )
+}
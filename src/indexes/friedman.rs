@@ -1,13 +1,14 @@
 use super::helpers::{between_group_dispercion::BGDValue, within_group_dispercion::WGDValue};
-use crate::calc_error::{CalcError, CombineErrors};
-use crate::sender::{Sender, Subscriber};
-use ndarray::{ArcArray2, ArrayView2};
+use crate::calc_error::CalcError;
+use crate::dataflow::{Node, Product};
+use ndarray::ArrayView2;
 use ndarray_linalg::Inverse;
 
 #[derive(Clone, Copy, Debug)]
 pub struct FriedmanIndexValue {
     pub val: f64,
 }
+impl Product for FriedmanIndexValue {}
 #[derive(Default)]
 pub struct Index;
 impl Index {
@@ -17,48 +18,10 @@ impl Index {
         Ok(value)
     }
 }
-pub struct Node<'a> {
-    index: Index,
-    wg: Option<Result<ArcArray2<f64>, CalcError>>,
-    bg: Option<Result<ArcArray2<f64>, CalcError>>,
-    sender: Sender<'a, FriedmanIndexValue>,
-}
-
-impl<'a> Node<'a> {
-    fn process_when_ready(&mut self) {
-        if let (Some(wg), Some(bg)) = (self.wg.as_ref(), self.bg.as_ref()) {
-            let res = match wg.combine(bg) {
-                Ok((wg, bg)) => self
-                    .index
-                    .compute(&wg.view(), &bg.view())
-                    .map(|val| FriedmanIndexValue { val }),
-                Err(err) => Err(err),
-            };
-            self.sender.send_to_subscribers(res);
-            self.wg = None;
-            self.bg = None;
-        }
-    }
-    pub fn new(sender: Sender<'a, FriedmanIndexValue>) -> Self {
-        Self {
-            index: Index,
-            wg: None,
-            bg: None,
-            sender,
-        }
-    }
-}
-
-impl<'a> Subscriber<WGDValue> for Node<'a> {
-    fn recieve_data(&mut self, data: Result<WGDValue, CalcError>) {
-        self.wg = Some(data.map(|v| v.val));
-        self.process_when_ready();
-    }
-}
-
-impl<'a> Subscriber<BGDValue> for Node<'a> {
-    fn recieve_data(&mut self, data: Result<BGDValue, CalcError>) {
-        self.bg = Some(data.map(|v| v.val));
-        self.process_when_ready();
-    }
+pub fn node() -> Node<FriedmanIndexValue> {
+    Node::new("friedman", |_, (wg, bg): (WGDValue, BGDValue)| {
+        Index
+            .compute(&wg.val.view(), &bg.val.view())
+            .map(|val| FriedmanIndexValue { val })
+    })
 }
@@ -1,42 +1,29 @@
 use crate::calc_error::CalcError;
-use crate::sender::{Sender, Subscriber};
+use crate::dataflow::{Node, Product};
+
+use super::helpers::s_plus_and_minus::SPlusAndMinusValue;
 #[derive(Clone, Copy, Debug)]
 pub struct GammaIndexValue {
     pub val: f64,
 }
+impl Product for GammaIndexValue {}
 
 #[derive(Default)]
 pub struct Index;
 
 impl Index {
     fn compute(&self, s_plus: usize, s_minus: usize) -> Result<f64, CalcError> {
-        let value = (s_plus - s_minus) as f64 / (s_plus + s_minus) as f64;
-        Ok(value)
-    }
-}
-#[derive(Default)]
-pub struct Node<'a> {
-    index: Index,
-    sender: Sender<'a, GammaIndexValue>,
-}
-
-impl<'a> Node<'a> {
-    pub fn new(sender: Sender<'a, GammaIndexValue>) -> Self {
-        Self {
-            index: Index,
-            sender,
+        if s_plus + s_minus == 0 {
+            return Err("Gamma index is undefined when all distances are tied".into());
         }
+        let value = (s_plus as f64 - s_minus as f64) / (s_plus + s_minus) as f64;
+        Ok(value)
     }
 }
-impl<'a> Subscriber<(usize, usize, usize)> for Node<'a> {
-    fn recieve_data(&mut self, data: Result<(usize, usize, usize), CalcError>) {
-        let res = match data.as_ref() {
-            Ok((s_plus, s_minus, _)) => self
-                .index
-                .compute(*s_plus, *s_minus)
-                .map(|val| GammaIndexValue { val }),
-            Err(err) => Err(err.clone()),
-        };
-        self.sender.send_to_subscribers(res);
-    }
+pub fn node() -> Node<GammaIndexValue> {
+    Node::new("gamma", |_, (s,): (SPlusAndMinusValue,)| {
+        Index
+            .compute(s.s_plus, s.s_minus)
+            .map(|val| GammaIndexValue { val })
+    })
 }
@@ -0,0 +1,195 @@
+use crate::calc_error::CalcError;
+use crate::dataflow::{Node, Product};
+use ndarray::{Array1, Array2, ArrayView1, ArrayView2, Axis};
+use ndarray_linalg::{Eigh, UPLO};
+use rand::{seq::index::sample, Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use rayon::prelude::*;
+
+use super::helpers::{
+    clusters_centroids::ClustersCentroids, counts::Counts, counts::CountsValue,
+    within_group_dispercion::WGDValue, within_group_dispercion::WGD,
+};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum GapReference {
+    // uniform over the bounding box of the features
+    #[default]
+    Uniform,
+    // uniform over the box aligned with the principal components of the data
+    Pca,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct GapConfig {
+    pub reference: GapReference,
+    // number of reference datasets
+    pub b: usize,
+    // draws are reproducible when set
+    pub seed: Option<u64>,
+}
+impl Default for GapConfig {
+    fn default() -> Self {
+        Self {
+            reference: GapReference::Uniform,
+            b: 100,
+            seed: None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct GapIndexValue {
+    pub val: f64,
+    // standard error of the reference log(W), sd * sqrt(1 + 1 / B)
+    pub s_k: f64,
+}
+impl Product for GapIndexValue {}
+
+const KMEANS_MAX_ITER: usize = 100;
+
+pub struct Index {
+    config: GapConfig,
+}
+impl Index {
+    fn compute(
+        &self,
+        x: &ArrayView2<f64>,
+        wg: &ArrayView2<f64>,
+        counts: &ArrayView1<f64>,
+    ) -> Result<(f64, f64), CalcError> {
+        let b = self.config.b;
+        if b == 0 {
+            return Err("Gap index requires at least one reference dataset".into());
+        }
+        let k = counts.len();
+        let log_w = wg.diag().sum().ln();
+
+        // the reference box is sampled in (possibly rotated) coordinates and mapped back
+        let (data, rotation) = match self.config.reference {
+            GapReference::Uniform => (x.to_owned(), None),
+            GapReference::Pca => {
+                let mean = x.mean_axis(Axis(0)).ok_or("Cant calc mean")?;
+                let centered = x - &mean;
+                let (_, v) = centered
+                    .t()
+                    .dot(&centered)
+                    .eigh(UPLO::Lower)
+                    .map_err(|e| CalcError::from(format!("{e:?}")))?;
+                (centered.dot(&v), Some((v, mean)))
+            }
+        };
+        let min = data.fold_axis(Axis(0), f64::INFINITY, |a, b| a.min(*b));
+        let max = data.fold_axis(Axis(0), f64::NEG_INFINITY, |a, b| a.max(*b));
+
+        let seed = self.config.seed.unwrap_or_else(rand::random);
+        let log_ws = (0..b)
+            .into_par_iter()
+            .map(|i| {
+                // every draw gets its own stream so results dont depend on scheduling
+                let mut rng = ChaCha8Rng::seed_from_u64(seed);
+                rng.set_stream(i as u64);
+
+                let mut reference = Array2::from_shape_fn(data.dim(), |(_, j)| {
+                    if min[j] < max[j] {
+                        rng.gen_range(min[j]..max[j])
+                    } else {
+                        min[j]
+                    }
+                });
+                if let Some((v, mean)) = rotation.as_ref() {
+                    reference = reference.dot(&v.t()) + mean;
+                }
+                let labels = kmeans(&reference.view(), k, &mut rng)?;
+                let counts = Counts.compute(&labels.view(), None)?;
+                let centroids = ClustersCentroids.compute(
+                    &reference.view(),
+                    &labels.view(),
+                    &counts.view(),
+                    None,
+                )?;
+                let wg = WGD.compute(&reference.view(), &labels.view(), &centroids.view(), None)?;
+                Ok(wg.diag().sum().ln())
+            })
+            .collect::<Result<Vec<f64>, CalcError>>()?;
+
+        let log_ws = Array1::from(log_ws);
+        let mean = log_ws.mean().ok_or("Cant calc mean")?;
+        let s_k = log_ws.std(0.) * (1. + 1. / b as f64).sqrt();
+        Ok((mean - log_w, s_k))
+    }
+}
+
+// Lloyd's algorithm started from k distinct random observations
+fn kmeans(x: &ArrayView2<f64>, k: usize, rng: &mut ChaCha8Rng) -> Result<Array1<i32>, CalcError> {
+    let n = x.nrows();
+    if k == 0 || k > n {
+        return Err("Number of clusters doesnt fit the number of observations".into());
+    }
+    let mut centroids = Array2::zeros((k, x.ncols()));
+    for (c, i) in sample(rng, n, k).into_iter().enumerate() {
+        centroids.row_mut(c).assign(&x.row(i));
+    }
+    let mut labels = Array1::from_elem(n, -1);
+    for _ in 0..KMEANS_MAX_ITER {
+        let mut changed = false;
+        for (i, row) in x.rows().into_iter().enumerate() {
+            let closest = centroids
+                .rows()
+                .into_iter()
+                .map(|c| (&c - &row).mapv(|v| v * v).sum())
+                .enumerate()
+                .min_by(|(_, a), (_, b)| a.total_cmp(b))
+                .map(|(c, _)| c as i32)
+                .ok_or("Cant find closest centroid")?;
+            if labels[i] != closest {
+                labels[i] = closest;
+                changed = true;
+            }
+        }
+        if !changed {
+            break;
+        }
+        let mut sums: Array2<f64> = Array2::zeros(centroids.dim());
+        let mut sizes = vec![0usize; k];
+        for (row, c) in x.rows().into_iter().zip(labels.iter()) {
+            let mut sum = sums.row_mut(*c as usize);
+            sum += &row;
+            sizes[*c as usize] += 1;
+        }
+        // empty clusters keep their previous centroid
+        for (c, size) in sizes.into_iter().enumerate() {
+            if size > 0 {
+                centroids.row_mut(c).assign(&(&sums.row(c) / size as f64));
+            }
+        }
+    }
+    Ok(compact(labels))
+}
+
+// renumbers labels so that clusters emptied by k-means dont leave gaps
+pub fn compact(mut labels: Array1<i32>) -> Array1<i32> {
+    let mut ids = labels.to_vec();
+    ids.sort_unstable();
+    ids.dedup();
+    labels.mapv_inplace(|l| ids.binary_search(&l).unwrap_or_default() as i32);
+    labels
+}
+
+pub fn node(config: GapConfig) -> Node<GapIndexValue> {
+    let index = Index { config };
+    Node::new(
+        "gap",
+        move |scope, (wg, counts): (WGDValue, CountsValue)| {
+            let x = scope.features()?;
+            // the uniform reference draws have no counterpart of weighted observations,
+            // comparing their dispersion with the weighted one of the data would be biased
+            if scope.weights()?.is_some() {
+                return Err(CalcError::weights("Gap index doesnt support weights"));
+            }
+            index
+                .compute(&x, &wg.val.view(), &counts.val.view())
+                .map(|(val, s_k)| GapIndexValue { val, s_k })
+        },
+    )
+}
@@ -1,13 +1,16 @@
-use crate::calc_error::{CalcError, CombineErrors};
-use ndarray::{ArcArray1, ArrayView1, ArrayView2};
-use std::{iter::zip, sync::Arc};
+use crate::calc_error::CalcError;
+use crate::dataflow::{Node, Product};
+use ndarray::ArrayView1;
 
-use crate::sender::{Sender, Subscriber};
+use super::helpers::{
+    pairs_and_distances::PairsAndDistancesValue, s_plus_and_minus::SPlusAndMinusValue,
+};
 
 #[derive(Clone, Copy, Debug)]
 pub struct GplusIndexValue {
     pub val: f64,
 }
+impl Product for GplusIndexValue {}
 #[derive(Default)]
 pub struct Index;
 impl Index {
@@ -43,50 +46,13 @@ impl Index {
     }
 }
 
-pub struct Node<'a> {
-    index: Index,
-    s_plus_and_minus: Option<Result<(usize, usize, usize), CalcError>>,
-    pairs_and_distances: Option<Result<(ArcArray1<i8>, ArcArray1<f64>), CalcError>>,
-    sender: Sender<'a, GplusIndexValue>,
-}
-
-impl<'a> Node<'a> {
-    fn process_when_ready(&mut self) {
-        if let (Some(s_plus_and_minus), Some(pairs_and_distances)) = (
-            self.s_plus_and_minus.as_ref(),
-            self.pairs_and_distances.as_ref(),
-        ) {
-            let res = match s_plus_and_minus.combine(pairs_and_distances) {
-                Ok(((_, s_minus, _), (pairs, _))) => self
-                    .index
-                    .compute(&pairs.view(), *s_minus)
-                    .map(|val| GplusIndexValue { val }),
-                Err(err) => Err(err),
-            };
-            self.sender.send_to_subscribers(res);
-            self.s_plus_and_minus = None;
-            self.pairs_and_distances = None;
-        }
-    }
-    pub fn new(sender: Sender<'a, GplusIndexValue>) -> Self {
-        Self {
-            index: Index,
-            s_plus_and_minus: None,
-            pairs_and_distances: None,
-            sender,
-        }
-    }
-}
-
-impl<'a> Subscriber<(usize, usize, usize)> for Node<'a> {
-    fn recieve_data(&mut self, data: Result<(usize, usize, usize), CalcError>) {
-        self.s_plus_and_minus = Some(data);
-        self.process_when_ready();
-    }
-}
-impl<'a> Subscriber<(ArcArray1<i8>, ArcArray1<f64>)> for Node<'a> {
-    fn recieve_data(&mut self, data: Result<(ArcArray1<i8>, ArcArray1<f64>), CalcError>) {
-        self.pairs_and_distances = Some(data);
-        self.process_when_ready();
-    }
+pub fn node() -> Node<GplusIndexValue> {
+    Node::new(
+        "gplus",
+        |_, (s, pairs): (SPlusAndMinusValue, PairsAndDistancesValue)| {
+            Index
+                .compute(&pairs.pairs.view(), s.s_minus)
+                .map(|val| GplusIndexValue { val })
+        },
+    )
 }
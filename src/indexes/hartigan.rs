@@ -0,0 +1,39 @@
+use crate::calc_error::CalcError;
+use crate::dataflow::{Node, Product};
+use ndarray::{ArrayView1, ArrayView2};
+
+use super::helpers::{
+    adjacent::NextPartition, counts::CountsValue, within_group_dispercion::WGDValue,
+};
+
+#[derive(Clone, Copy, Debug)]
+pub struct HartiganIndexValue {
+    pub val: f64,
+}
+impl Product for HartiganIndexValue {}
+#[derive(Default)]
+pub struct Index;
+impl Index {
+    fn compute(
+        &self,
+        wg: &ArrayView2<f64>,
+        wg_next: &ArrayView2<f64>,
+        counts: &ArrayView1<f64>,
+    ) -> Result<f64, CalcError> {
+        let k = counts.len() as f64;
+        let n = counts.sum();
+        let val = (wg.diag().sum() / wg_next.diag().sum() - 1.) * (n - k - 1.);
+        Ok(val)
+    }
+}
+
+pub fn node() -> Node<HartiganIndexValue> {
+    Node::new(
+        "hartigan",
+        |_, (wg, NextPartition(wg_next), counts): (WGDValue, NextPartition<WGDValue>, CountsValue)| {
+            Index
+                .compute(&wg.val.view(), &wg_next.val.view(), &counts.val.view())
+                .map(|val| HartiganIndexValue { val })
+        },
+    )
+}
@@ -0,0 +1,6 @@
+// values computed for the partitions with one cluster less and one cluster more
+// than the evaluated one
+#[derive(Clone, Debug)]
+pub struct PreviousPartition<T>(pub T);
+#[derive(Clone, Debug)]
+pub struct NextPartition<T>(pub T);
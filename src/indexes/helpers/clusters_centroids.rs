@@ -1,13 +1,22 @@
 use crate::{
-    calc_error::{CalcError, CombineErrors},
-    sender::{Sender, Subscriber},
+    calc_error::CalcError,
+    dataflow::{Node, Product},
+    profile::array_memory,
 };
-use ndarray::{ArcArray1, ArcArray2, Array2, ArrayView1, ArrayView2};
+use ndarray::{ArcArray2, Array2, ArrayView1, ArrayView2};
 use std::iter::zip;
+
+use super::counts::CountsValue;
+use super::weights::weight;
 #[derive(Clone, Debug)]
 pub struct ClustersCentroidsValue {
     pub val: ArcArray2<f64>,
 }
+impl Product for ClustersCentroidsValue {
+    fn memory(&self) -> usize {
+        array_memory(&self.val)
+    }
+}
 #[derive(Default)]
 pub struct ClustersCentroids;
 impl ClustersCentroids {
@@ -15,61 +24,25 @@ impl ClustersCentroids {
         &self,
         data: &ArrayView2<f64>,
         clusters: &ArrayView1<i32>,
-        counts: &ArrayView1<usize>,
+        counts: &ArrayView1<f64>,
+        weights: Option<&ArrayView1<f64>>,
     ) -> Result<ArcArray2<f64>, CalcError> {
         let q = counts.len();
         let mut centroids: Array2<f64> = Array2::default((q, data.ncols()));
-        for (x, y) in zip(data.rows(), clusters.iter()) {
+        for (i, (x, y)) in zip(data.rows(), clusters.iter()).enumerate() {
             let mut r = centroids.row_mut(*y as usize);
-            r += &(&x / counts[*y as usize] as f64);
+            r += &(&x * (weight(weights, i) / counts[*y as usize]));
         }
         let res = centroids.into_shared();
         Ok(res)
     }
 }
-pub struct ClustersCentroidsNode<'a> {
-    index: ClustersCentroids,
-    raw_data: Option<Result<(ArrayView2<'a, f64>, ArrayView1<'a, i32>), CalcError>>,
-    counts: Option<Result<ArcArray1<usize>, CalcError>>,
-    sender: Sender<'a, ClustersCentroidsValue>,
-}
-impl<'a> ClustersCentroidsNode<'a> {
-    pub fn new(sender: Sender<'a, ClustersCentroidsValue>) -> Self {
-        Self {
-            index: ClustersCentroids,
-            raw_data: None,
-            counts: None,
-            sender,
-        }
-    }
-    fn process_when_ready(&mut self) {
-        if let (Some(raw_data), Some(counts)) = (self.raw_data.as_ref(), self.counts.as_ref()) {
-            let res = match raw_data.combine(counts) {
-                Ok(((ref x, ref y), cnts)) => self
-                    .index
-                    .compute(x, y, &cnts.view())
-                    .map(|val| ClustersCentroidsValue { val }),
-                Err(err) => Err(err),
-            };
-            self.sender.send_to_subscribers(res);
-            self.raw_data = None;
-            self.counts = None;
-        }
-    }
-}
-
-impl<'a> Subscriber<(ArrayView2<'a, f64>, ArrayView1<'a, i32>)> for ClustersCentroidsNode<'a> {
-    fn recieve_data(
-        &mut self,
-        data: Result<(ArrayView2<'a, f64>, ArrayView1<'a, i32>), CalcError>,
-    ) {
-        self.raw_data = Some(data);
-        self.process_when_ready();
-    }
-}
-impl<'a> Subscriber<ArcArray1<usize>> for ClustersCentroidsNode<'a> {
-    fn recieve_data(&mut self, data: Result<ArcArray1<usize>, CalcError>) {
-        self.counts = Some(data);
-        self.process_when_ready();
-    }
+pub fn node() -> Node<ClustersCentroidsValue> {
+    Node::new("clusters_centroids", |scope, (counts,): (CountsValue,)| {
+        let (x, y) = scope.raw_data()?;
+        let weights = scope.weights()?;
+        ClustersCentroids
+            .compute(&x, &y, &counts.val.view(), weights.as_ref())
+            .map(|val| ClustersCentroidsValue { val })
+    })
 }
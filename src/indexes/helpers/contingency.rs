@@ -0,0 +1,96 @@
+use crate::{
+    calc_error::CalcError,
+    dataflow::{Node, Product},
+    profile::array_memory,
+};
+use itertools::Itertools;
+use ndarray::{ArcArray2, Array1, Array2, ArrayView1, ArrayView2, Axis};
+use std::iter::zip;
+
+// rows are the reference classes, columns the clusters of the evaluated partition,
+// both ordered by label
+#[derive(Clone, Debug)]
+pub struct ContingencyValue {
+    pub val: ArcArray2<usize>,
+}
+impl Product for ContingencyValue {
+    fn memory(&self) -> usize {
+        array_memory(&self.val)
+    }
+}
+
+#[derive(Default)]
+pub struct Contingency;
+impl Contingency {
+    pub fn compute(
+        &self,
+        y_true: &ArrayView1<i32>,
+        y_pred: &ArrayView1<i32>,
+    ) -> Result<ArcArray2<usize>, CalcError> {
+        if y_true.len() != y_pred.len() {
+            return Err(CalcError::shape(
+                "Length of reference partition doesnt match number of observations",
+            ));
+        }
+        let classes = y_true.iter().unique().sorted().collect::<Vec<&i32>>();
+        let clusters = y_pred.iter().unique().sorted().collect::<Vec<&i32>>();
+        let mut table: Array2<usize> = Array2::zeros((classes.len(), clusters.len()));
+        for (t, p) in zip(y_true, y_pred) {
+            let i = classes.binary_search(&t).map_err(|_| "Unknown class")?;
+            let j = clusters.binary_search(&p).map_err(|_| "Unknown cluster")?;
+            table[[i, j]] += 1;
+        }
+        Ok(table.into_shared())
+    }
+}
+
+fn comb2(n: usize) -> f64 {
+    (n * n.saturating_sub(1)) as f64 / 2.
+}
+
+// pairs of observations grouped together in both partitions, in the reference only,
+// in the evaluated partition only, and in neither
+pub fn pair_counts(contingency: &ArrayView2<usize>) -> (f64, f64, f64, f64) {
+    let n = contingency.sum();
+    let both = contingency.iter().map(|v| comb2(*v)).sum::<f64>();
+    let same_class = contingency.sum_axis(Axis(1)).map(|v| comb2(*v)).sum();
+    let same_cluster = contingency.sum_axis(Axis(0)).map(|v| comb2(*v)).sum();
+    let only_class = same_class - both;
+    let only_cluster = same_cluster - both;
+    let neither = comb2(n) - both - only_class - only_cluster;
+    (both, only_class, only_cluster, neither)
+}
+
+pub fn entropy(counts: &Array1<usize>) -> f64 {
+    let n = counts.sum() as f64;
+    -counts
+        .iter()
+        .filter(|v| **v > 0)
+        .map(|v| *v as f64 / n)
+        .map(|p| p * p.ln())
+        .sum::<f64>()
+}
+
+pub fn mutual_information(contingency: &ArrayView2<usize>) -> f64 {
+    let n = contingency.sum() as f64;
+    let rows = contingency.sum_axis(Axis(1));
+    let cols = contingency.sum_axis(Axis(0));
+    contingency
+        .indexed_iter()
+        .filter(|(_, v)| **v > 0)
+        .map(|((i, j), v)| {
+            let v = *v as f64;
+            v / n * (n * v / (rows[i] * cols[j]) as f64).ln()
+        })
+        .sum()
+}
+
+pub fn node() -> Node<ContingencyValue> {
+    Node::new("contingency", |scope, ()| {
+        let y_pred = scope.labels()?;
+        let y_true = scope.reference()?;
+        Contingency
+            .compute(&y_true, &y_pred)
+            .map(|val| ContingencyValue { val })
+    })
+}
@@ -1,45 +1,53 @@
 use crate::{
     calc_error::CalcError,
-    sender::{Sender, Subscriber},
+    dataflow::{Node, Product},
+    profile::array_memory,
 };
 use itertools::Itertools;
-use ndarray::{ArcArray1, Array1, ArrayView1, ArrayView2};
+use ndarray::{ArcArray1, Array1, ArrayView1};
+
+use super::weights::weight;
+
+#[derive(Clone, Debug)]
+pub struct CountsValue {
+    pub val: ArcArray1<f64>,
+}
+impl Product for CountsValue {
+    fn memory(&self) -> usize {
+        array_memory(&self.val)
+    }
+}
+
+// size of every cluster, the sum of the weights of its observations when weighted
 #[derive(Default)]
 pub struct Counts;
 impl Counts {
-    pub fn compute(&self, clusters: &ArrayView1<i32>) -> Result<ArcArray1<usize>, CalcError> {
-        let counts = clusters.iter().counts();
+    pub fn compute(
+        &self,
+        clusters: &ArrayView1<i32>,
+        weights: Option<&ArrayView1<f64>>,
+    ) -> Result<ArcArray1<f64>, CalcError> {
+        let counts = clusters
+            .iter()
+            .enumerate()
+            .map(|(i, c)| (*c, weight(weights, i)))
+            .into_grouping_map()
+            .sum();
         let vec = counts
             .into_iter()
             .sorted_by(|(a, _), (b, _)| a.cmp(b))
             .map(|(_, v)| v)
-            .collect::<Vec<usize>>();
+            .collect::<Vec<f64>>();
         let res = Array1::from_vec(vec);
         Ok(res.to_shared())
     }
 }
-pub struct CountsNode<'a> {
-    index: Counts,
-    sender: Sender<'a, ArcArray1<usize>>,
-}
-impl<'a> CountsNode<'a> {
-    pub fn new(sender: Sender<'a, ArcArray1<usize>>) -> Self {
-        Self {
-            index: Counts,
-            sender,
-        }
-    }
-}
-
-impl<'a> Subscriber<(ArrayView2<'a, f64>, ArrayView1<'a, i32>)> for CountsNode<'a> {
-    fn recieve_data(
-        &mut self,
-        data: Result<(ArrayView2<'a, f64>, ArrayView1<'a, i32>), CalcError>,
-    ) {
-        let res = match data {
-            Ok((_, ref y)) => self.index.compute(y),
-            Err(err) => Err(err),
-        };
-        self.sender.send_to_subscribers(res);
-    }
+pub fn node() -> Node<CountsValue> {
+    Node::new("counts", |scope, ()| {
+        let y = scope.labels()?;
+        let weights = scope.weights()?;
+        Counts
+            .compute(&y, weights.as_ref())
+            .map(|val| CountsValue { val })
+    })
 }
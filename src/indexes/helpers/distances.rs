@@ -0,0 +1,222 @@
+use crate::calc_error::CalcError;
+use crate::dataflow::{Node, Product};
+use crate::metric::Metric;
+use crate::profile::array_memory;
+use ndarray::{s, ArcArray1, Array2, ArrayView1, ArrayView2, Axis};
+use rayon::prelude::*;
+
+// dissimilarities given instead of the features
+#[derive(Clone, Debug)]
+pub enum DistanceMatrix<'a> {
+    // upper triangle in row-major order, as returned by scipy's pdist
+    Condensed(ArrayView1<'a, f64>),
+    // only the upper triangle is read
+    Square(ArrayView2<'a, f64>),
+}
+
+// condensed pairwise distances between observations, pair (i, j) with i < j is
+// stored at `condensed_index(n, i, j)`
+#[derive(Clone, Debug)]
+pub struct DistancesValue {
+    pub val: ArcArray1<f64>,
+}
+impl Product for DistancesValue {
+    fn memory(&self) -> usize {
+        array_memory(&self.val)
+    }
+}
+
+pub fn condensed_index(n: usize, i: usize, j: usize) -> usize {
+    let (i, j) = if i < j { (i, j) } else { (j, i) };
+    n * i - i * (i + 1) / 2 + (j - i - 1)
+}
+
+pub enum DistanceSource<'s, 'a> {
+    Features(ArrayView2<'a, f64>, &'s Metric),
+    Precomputed(&'s DistanceMatrix<'a>),
+}
+
+// picks the precomputed distances when given, the features otherwise
+pub fn distance_source<'s, 'a>(
+    raw_data: &'s Result<(ArrayView2<'a, f64>, ArrayView1<'a, i32>), CalcError>,
+    metric: &'s Metric,
+    precomputed: &'s Result<(DistanceMatrix<'a>, usize), CalcError>,
+) -> Result<(DistanceSource<'s, 'a>, usize), CalcError> {
+    match (precomputed, raw_data) {
+        (Ok((matrix, n)), _) => Ok((DistanceSource::Precomputed(matrix), *n)),
+        (Err(_), Ok((x, _))) => Ok((DistanceSource::Features(*x, metric), x.nrows())),
+        (Err(_), Err(err)) => Err(err.clone()),
+    }
+}
+
+pub const DEFAULT_MEMORY_BUDGET: usize = 256 << 20;
+
+// computes pairwise distances block by block, so that the blocks processed at the same time
+// take at most `memory_budget` bytes
+#[derive(Clone, Copy, Debug)]
+pub struct PairwiseEngine {
+    pub memory_budget: usize,
+}
+impl Default for PairwiseEngine {
+    fn default() -> Self {
+        Self {
+            memory_budget: DEFAULT_MEMORY_BUDGET,
+        }
+    }
+}
+impl PairwiseEngine {
+    fn block_rows(&self, n: usize) -> usize {
+        let row_bytes = n.max(1) * std::mem::size_of::<f64>() * rayon::current_num_threads();
+        (self.memory_budget / row_bytes).clamp(1, n.max(1))
+    }
+
+    // folds the distances from every observation i to the observations i + 1..n,
+    // blocks are folded in parallel and reduced in row order
+    pub fn fold_rows<T, I, F, R>(
+        &self,
+        source: &DistanceSource,
+        n: usize,
+        identity: I,
+        fold: F,
+        reduce: R,
+    ) -> Result<T, CalcError>
+    where
+        T: Send,
+        I: Fn() -> T + Sync + Send,
+        F: Fn(T, usize, ArrayView1<f64>) -> T + Sync,
+        R: Fn(T, T) -> T + Sync + Send,
+    {
+        let shape_err = "Shape of distance matrix doesnt match number of observations";
+        match source {
+            DistanceSource::Features(x, _) if x.nrows() != n => {
+                return Err(CalcError::shape(
+                    "Number of rows doesnt match number of observations",
+                ))
+            }
+            DistanceSource::Precomputed(DistanceMatrix::Condensed(d))
+                if d.len() != n * n.saturating_sub(1) / 2 =>
+            {
+                return Err(CalcError::shape(shape_err))
+            }
+            DistanceSource::Precomputed(DistanceMatrix::Square(d)) if d.dim() != (n, n) => {
+                return Err(CalcError::shape(shape_err))
+            }
+            _ => {}
+        }
+        let rows = self.block_rows(n);
+        (0..n)
+            .step_by(rows)
+            .collect::<Vec<usize>>()
+            .into_par_iter()
+            .map(|start| {
+                let end = (start + rows).min(n);
+                fold_block(source, n, start, end, identity(), &fold)
+            })
+            .try_reduce(&identity, |a, b| Ok(reduce(a, b)))
+    }
+}
+
+fn fold_block<T>(
+    source: &DistanceSource,
+    n: usize,
+    start: usize,
+    end: usize,
+    init: T,
+    fold: &impl Fn(T, usize, ArrayView1<f64>) -> T,
+) -> Result<T, CalcError> {
+    let res = match source {
+        DistanceSource::Features(x, metric) => {
+            let block = block_distances(x, metric, start, end)?;
+            (start..end).fold(init, |acc, i| {
+                let a = i - start;
+                fold(acc, i, block.slice(s![a, a + 1..]))
+            })
+        }
+        DistanceSource::Precomputed(DistanceMatrix::Condensed(d)) => {
+            (start..end).fold(init, |acc, i| {
+                let first = condensed_index(n, i, i + 1);
+                fold(acc, i, d.slice(s![first..first + (n - i - 1)]))
+            })
+        }
+        DistanceSource::Precomputed(DistanceMatrix::Square(d)) => {
+            (start..end).fold(init, |acc, i| fold(acc, i, d.slice(s![i, i + 1..])))
+        }
+    };
+    Ok(res)
+}
+
+// distances from the rows start..end to the rows start..n, entry (a, c) holds the distance
+// between observations start + a and start + c, only c > a is filled
+fn block_distances(
+    x: &ArrayView2<f64>,
+    metric: &Metric,
+    start: usize,
+    end: usize,
+) -> Result<Array2<f64>, CalcError> {
+    let block = x.slice(s![start..end, ..]);
+    let rest = x.slice(s![start.., ..]);
+    match metric {
+        // |a - b|^2 = |a|^2 + |b|^2 - 2ab turns the block into a single matrix product
+        Metric::Euclidean | Metric::SqEuclidean => {
+            let norms = rest.map_axis(Axis(1), |r| r.dot(&r));
+            let mut d = block.dot(&rest.t());
+            for ((a, c), v) in d.indexed_iter_mut() {
+                *v = (norms[a] + norms[c] - 2. * *v).max(0.);
+            }
+            if let Metric::Euclidean = metric {
+                d.mapv_inplace(f64::sqrt);
+            }
+            Ok(d)
+        }
+        _ => {
+            let mut d = Array2::zeros((block.nrows(), rest.nrows()));
+            for (a, mut row) in d.rows_mut().into_iter().enumerate() {
+                for c in a + 1..rest.nrows() {
+                    row[c] = metric.distance(block.row(a), rest.row(c))?;
+                }
+            }
+            Ok(d)
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct Distances {
+    engine: PairwiseEngine,
+}
+impl Distances {
+    pub fn new(engine: PairwiseEngine) -> Self {
+        Self { engine }
+    }
+    pub fn compute(&self, source: &DistanceSource, n: usize) -> Result<ArcArray1<f64>, CalcError> {
+        if let DistanceSource::Precomputed(DistanceMatrix::Condensed(d)) = source {
+            if d.len() == n * n.saturating_sub(1) / 2 {
+                return Ok(d.to_owned().into_shared());
+            }
+        }
+        let distances = self.engine.fold_rows(
+            source,
+            n,
+            Vec::new,
+            |mut acc, _, row| {
+                acc.extend(row.iter());
+                acc
+            },
+            |mut a, b| {
+                a.extend(b);
+                a
+            },
+        )?;
+        Ok(ArcArray1::from_vec(distances))
+    }
+}
+
+pub fn node(engine: PairwiseEngine) -> Node<DistancesValue> {
+    let index = Distances::new(engine);
+    Node::new("distances", move |scope, (metric,): (Metric,)| {
+        let (raw_data, precomputed) = (scope.raw_data(), scope.precomputed());
+        let (source, n) = distance_source(&raw_data, &metric, &precomputed)?;
+        index.compute(&source, n).map(|val| DistancesValue { val })
+    })
+    .label_independent()
+}
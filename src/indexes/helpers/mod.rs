@@ -1,10 +1,14 @@
+pub mod adjacent;
 pub mod between_group_dispercion;
-// pub mod clusters;
 pub mod clusters_centroids;
+pub mod contingency;
 pub mod counts;
+pub mod distances;
+pub mod pair_stats;
 pub mod pairs_and_distances;
-pub mod raw_data;
 pub mod s_plus_and_minus;
 pub mod scat;
+pub mod split;
 pub mod total_dispercion;
+pub mod weights;
 pub mod within_group_dispercion;
@@ -0,0 +1,112 @@
+use crate::calc_error::CalcError;
+use crate::dataflow::{Node, Product};
+use crate::metric::Metric;
+use crate::profile::array_memory;
+use ndarray::{Array1, Array2, ArrayView1};
+
+use super::distances::{distance_source, DistanceSource, PairwiseEngine};
+
+// aggregates of the within- and between-cluster pairwise distances, computed without
+// keeping all distances in memory
+#[derive(Clone, Debug)]
+pub struct PairStatsValue {
+    pub within_count: usize,
+    pub between_count: usize,
+    pub within_sum: f64,
+    pub between_sum: f64,
+    pub max_within: f64,
+    pub min_between: f64,
+    // largest distance inside every cluster
+    pub diameters: Array1<f64>,
+    // smallest distance between every two clusters, only the upper triangle is filled
+    pub separations: Array2<f64>,
+}
+impl Product for PairStatsValue {
+    fn memory(&self) -> usize {
+        array_memory(&self.diameters) + array_memory(&self.separations)
+    }
+}
+impl PairStatsValue {
+    fn new(q: usize) -> Self {
+        Self {
+            within_count: 0,
+            between_count: 0,
+            within_sum: 0.,
+            between_sum: 0.,
+            max_within: f64::NEG_INFINITY,
+            min_between: f64::INFINITY,
+            diameters: Array1::zeros(q),
+            separations: Array2::from_elem((q, q), f64::INFINITY),
+        }
+    }
+    fn merge(mut self, other: Self) -> Self {
+        self.within_count += other.within_count;
+        self.between_count += other.between_count;
+        self.within_sum += other.within_sum;
+        self.between_sum += other.between_sum;
+        self.max_within = self.max_within.max(other.max_within);
+        self.min_between = self.min_between.min(other.min_between);
+        self.diameters
+            .zip_mut_with(&other.diameters, |a, b| *a = a.max(*b));
+        self.separations
+            .zip_mut_with(&other.separations, |a, b| *a = a.min(*b));
+        self
+    }
+}
+
+#[derive(Default)]
+pub struct PairStats {
+    engine: PairwiseEngine,
+}
+impl PairStats {
+    pub fn compute(
+        &self,
+        source: &DistanceSource,
+        y: &ArrayView1<i32>,
+    ) -> Result<PairStatsValue, CalcError> {
+        let q = *y.iter().max().ok_or("Cant get numb of clusters")? as usize + 1;
+        let mut stats = self.engine.fold_rows(
+            source,
+            y.len(),
+            || PairStatsValue::new(q),
+            |mut acc, i, row| {
+                let c1 = y[i] as usize;
+                for (c2, d) in y.iter().skip(i + 1).zip(row) {
+                    let c2 = *c2 as usize;
+                    if c1 == c2 {
+                        acc.within_count += 1;
+                        acc.within_sum += d;
+                        acc.max_within = acc.max_within.max(*d);
+                        acc.diameters[c1] = acc.diameters[c1].max(*d);
+                    } else {
+                        acc.between_count += 1;
+                        acc.between_sum += d;
+                        acc.min_between = acc.min_between.min(*d);
+                        let pair = &mut acc.separations[[c1.min(c2), c1.max(c2)]];
+                        *pair = pair.min(*d);
+                    }
+                }
+                acc
+            },
+            PairStatsValue::merge,
+        )?;
+        // mirrors the upper triangle, a cluster has no separation from itself
+        for i in 0..q {
+            stats.separations[[i, i]] = f64::NAN;
+            for j in i + 1..q {
+                stats.separations[[j, i]] = stats.separations[[i, j]];
+            }
+        }
+        Ok(stats)
+    }
+}
+
+pub fn node(engine: PairwiseEngine) -> Node<PairStatsValue> {
+    let index = PairStats { engine };
+    Node::new("pair_stats", move |scope, (metric,): (Metric,)| {
+        let y = scope.labels()?;
+        let (raw_data, precomputed) = (scope.raw_data(), scope.precomputed());
+        let (source, _) = distance_source(&raw_data, &metric, &precomputed)?;
+        index.compute(&source, &y)
+    })
+}
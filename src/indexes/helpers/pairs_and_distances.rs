@@ -1,54 +1,57 @@
 use crate::calc_error::CalcError;
-use crate::sender::{Sender, Subscriber};
-use ndarray::{ArcArray1, ArrayView1, ArrayView2, Axis};
+use crate::dataflow::{Node, Product};
+use crate::profile::array_memory;
+use ndarray::{ArcArray1, ArrayView1};
+
+use super::distances::DistancesValue;
+
+// whether every pair of observations lies in the same cluster, with its distance
+#[derive(Clone, Debug)]
+pub struct PairsAndDistancesValue {
+    pub pairs: ArcArray1<i8>,
+    pub distances: ArcArray1<f64>,
+}
+impl Product for PairsAndDistancesValue {
+    fn memory(&self) -> usize {
+        // the distances are shared with `DistancesValue`
+        array_memory(&self.pairs)
+    }
+}
 #[derive(Default)]
 pub struct PairsAndDistances;
 impl PairsAndDistances {
     fn compute(
         &self,
-        x: &ArrayView2<f64>,
         y: &ArrayView1<i32>,
+        distances: &ArcArray1<f64>,
     ) -> Result<(ArcArray1<i8>, ArcArray1<f64>), CalcError> {
-        let n = y.len() * (y.len() - 1) / 2;
-        let mut distances: Vec<f64> = Vec::with_capacity(n);
+        let n = y.len() * y.len().saturating_sub(1) / 2;
+        if distances.len() != n {
+            return Err(CalcError::shape(
+                "Number of distances doesnt match number of observations",
+            ));
+        }
         let mut pairs_in_the_same_cluster: Vec<i8> = Vec::with_capacity(n);
 
-        //calculating distances beetween pair of points and does they belong to the same cluster
-        for (i, (row1, cluster1)) in x.axis_iter(Axis(0)).zip(y).enumerate() {
-            for (j, (row2, cluster2)) in x.axis_iter(Axis(0)).zip(y).enumerate() {
-                if i < j {
-                    pairs_in_the_same_cluster.push((cluster1 == cluster2) as i8); // the same cluster =producer 1, different = 0
-                    distances.push((&row2 - &row1).pow2().sum().sqrt());
-                }
+        //checking does pairs of points belong to the same cluster, in the order of the distances
+        for (i, cluster1) in y.iter().enumerate() {
+            for cluster2 in y.iter().skip(i + 1) {
+                pairs_in_the_same_cluster.push((cluster1 == cluster2) as i8); // the same cluster =producer 1, different = 0
             }
         }
         let pairs_in_the_same_cluster = ArcArray1::from_vec(pairs_in_the_same_cluster);
-        let distances = ArcArray1::from_vec(distances);
-        Ok((pairs_in_the_same_cluster, distances))
+        Ok((pairs_in_the_same_cluster, distances.clone()))
     }
 }
 
-pub struct PairsAndDistancesNode<'a> {
-    index: PairsAndDistances,
-    sender: Sender<'a, (ArcArray1<i8>, ArcArray1<f64>)>,
-}
-impl<'a> PairsAndDistancesNode<'a> {
-    pub fn new(sender: Sender<'a, (ArcArray1<i8>, ArcArray1<f64>)>) -> Self {
-        Self {
-            index: PairsAndDistances,
-            sender,
-        }
-    }
-}
-impl<'a> Subscriber<(ArrayView2<'a, f64>, ArrayView1<'a, i32>)> for PairsAndDistancesNode<'a> {
-    fn recieve_data(
-        &mut self,
-        data: Result<(ArrayView2<'a, f64>, ArrayView1<'a, i32>), CalcError>,
-    ) {
-        let res = match data.as_ref() {
-            Ok((x, y)) => self.index.compute(x, y),
-            Err(err) => Err(err.clone()),
-        };
-        self.sender.send_to_subscribers(res);
-    }
+pub fn node() -> Node<PairsAndDistancesValue> {
+    Node::new(
+        "pairs_and_distances",
+        |scope, (distances,): (DistancesValue,)| {
+            let y = scope.labels()?;
+            PairsAndDistances
+                .compute(&y, &distances.val)
+                .map(|(pairs, distances)| PairsAndDistancesValue { pairs, distances })
+        },
+    )
 }
@@ -1,62 +1,101 @@
 use crate::calc_error::CalcError;
-use crate::sender::{Sender, Subscriber};
-use ndarray::{ArcArray1, ArrayView1};
+use crate::dataflow::{Node, Product};
+use ndarray::ArrayView1;
 use std::iter::zip;
 
+use super::pairs_and_distances::PairsAndDistancesValue;
+
+#[derive(Clone, Debug)]
+pub struct SPlusAndMinusValue {
+    pub s_plus: usize,
+    pub s_minus: usize,
+    pub ties: usize,
+}
+impl Product for SPlusAndMinusValue {}
+
 #[derive(Default)]
 pub struct Index;
 
 impl Index {
-    fn compute(
+    pub fn compute(
         &self,
         pairs_in_the_same_cluster: &ArrayView1<i8>,
         distances: &ArrayView1<f64>,
     ) -> Result<(usize, usize, usize), CalcError> {
-        let (mut s_plus, mut s_minus, mut ties) = (0, 0, 0);
-
         // finding s_plus which represents the number of times a distance between two points
         // which belong to the same cluster is strictly smaller than the distance between two points not belonging to the same cluster
         // and s_minus which represents the number of times distance between two points lying in the same cluster  is strictly greater than a distance between two points not
         //belonging to the same cluster
 
-        for (i, (d1, b1)) in zip(distances, pairs_in_the_same_cluster).enumerate() {
-            for (j, (d2, b2)) in zip(distances, pairs_in_the_same_cluster).enumerate() {
-                if i < j && (*b1 == 1 && *b2 == 0) {
-                    if d1 < d2 {
-                        s_plus += 1;
-                    }
-                    if d1 > d2 {
-                        s_minus += 1;
-                    }
-                    if d1 == d2 {
-                        ties += 1;
-                    }
-                }
+        // a within-cluster distance is only compared with the between-cluster distances
+        // that come after it. Pairs are visited backwards and the between-cluster distances
+        // seen so far are counted in a Fenwick tree over their ranks, so every within-cluster
+        // distance is placed with two prefix sums instead of a scan over all pairs
+        let mut ranks = zip(distances, pairs_in_the_same_cluster)
+            .filter(|(_, b)| **b == 0)
+            .map(|(d, _)| *d)
+            .collect::<Vec<f64>>();
+        ranks.sort_unstable_by(f64::total_cmp);
+        ranks.dedup();
+        let mut seen = Fenwick::new(ranks.len());
+        let (mut s_plus, mut s_minus, mut ties) = (0, 0, 0);
+        for (d, b) in zip(distances, pairs_in_the_same_cluster).rev() {
+            let smaller = ranks.partition_point(|v| v < d);
+            if *b == 0 {
+                seen.add(smaller);
+                continue;
             }
+            let not_greater = ranks.partition_point(|v| v <= d);
+            let (below, up_to) = (seen.prefix(smaller), seen.prefix(not_greater));
+            s_plus += seen.total - up_to;
+            s_minus += below;
+            ties += up_to - below;
         }
         Ok((s_plus, s_minus, ties))
     }
 }
-#[derive(Default)]
-pub struct SPlusAndMinusNode<'a> {
-    index: Index,
-    sender: Sender<'a, (usize, usize, usize)>,
+// counts of the inserted ranks
+struct Fenwick {
+    tree: Vec<usize>,
+    total: usize,
 }
-
-impl<'a> SPlusAndMinusNode<'a> {
-    pub fn new(sender: Sender<'a, (usize, usize, usize)>) -> Self {
+impl Fenwick {
+    fn new(n: usize) -> Self {
         Self {
-            index: Index,
-            sender,
+            tree: vec![0; n + 1],
+            total: 0,
         }
     }
-}
-impl<'a> Subscriber<(ArcArray1<i8>, ArcArray1<f64>)> for SPlusAndMinusNode<'a> {
-    fn recieve_data(&mut self, data: Result<(ArcArray1<i8>, ArcArray1<f64>), CalcError>) {
-        let res = match data.as_ref() {
-            Ok((p, d)) => self.index.compute(&p.view(), &d.view()),
-            Err(err) => Err(err.clone()),
-        };
-        self.sender.send_to_subscribers(res);
+    fn add(&mut self, rank: usize) {
+        let mut i = rank + 1;
+        while i < self.tree.len() {
+            self.tree[i] += 1;
+            i += i & i.wrapping_neg();
+        }
+        self.total += 1;
     }
+    // number of inserted ranks below `rank`
+    fn prefix(&self, rank: usize) -> usize {
+        let (mut i, mut count) = (rank, 0);
+        while i > 0 {
+            count += self.tree[i];
+            i -= i & i.wrapping_neg();
+        }
+        count
+    }
+}
+
+pub fn node() -> Node<SPlusAndMinusValue> {
+    Node::new(
+        "s_plus_and_minus",
+        |_, (pairs,): (PairsAndDistancesValue,)| {
+            Index
+                .compute(&pairs.pairs.view(), &pairs.distances.view())
+                .map(|(s_plus, s_minus, ties)| SPlusAndMinusValue {
+                    s_plus,
+                    s_minus,
+                    ties,
+                })
+        },
+    )
 }
@@ -1,16 +1,22 @@
-use std::iter::zip;
-
 use crate::{
     calc_error::CalcError,
-    sender::{Sender, Subscriber},
+    dataflow::{Node, Product},
+    profile::array_memory,
 };
 use ndarray::{ArcArray1, Array2, ArrayView1, ArrayView2, Axis};
+
+use super::weights::weighted_var;
 #[derive(Debug, Clone)]
 pub struct ScatValue {
     pub val: f64,
     pub clusters_vars: ArcArray1<f64>,
     pub var: f64,
 }
+impl Product for ScatValue {
+    fn memory(&self) -> usize {
+        array_memory(&self.clusters_vars)
+    }
+}
 #[derive(Default)]
 pub struct Index;
 impl Index {
@@ -18,20 +24,21 @@ impl Index {
         &self,
         x: &ArrayView2<f64>,
         y: &ArrayView1<i32>,
+        weights: Option<&ArrayView1<f64>>,
     ) -> Result<(f64, ArcArray1<f64>, f64), CalcError> {
-        let var = x.var_axis(Axis(0), 0.);
+        let var = weighted_var(x, weights).ok_or("Cant calc variance")?;
         let q = *y.iter().max().ok_or("Cant get max cluster index")? as usize + 1;
         let mut clusters_vars: Array2<f64> = Array2::zeros((q, x.ncols()));
         for i in 0..q {
-            let vec = zip(x.rows(), y)
-                .filter(|(_, c)| **c as usize == i)
-                .map(|(v, _)| v.into_iter())
-                .flatten()
-                .map(|v| *v)
-                .collect::<Vec<f64>>();
-            let arr =
-                Array2::from_shape_vec((vec.len(), x.ncols()), vec).map_err(|e| e.to_string())?;
-            let var = arr.var_axis(Axis(0), 0.);
+            let members = (0..y.len())
+                .filter(|j| y[*j] as usize == i)
+                .collect::<Vec<usize>>();
+            let w = weights.map(|w| w.select(Axis(0), &members));
+            let var = weighted_var(
+                &x.select(Axis(0), &members).view(),
+                w.as_ref().map(|w| w.view()).as_ref(),
+            )
+            .ok_or("Cant calc cluster variance")?;
             clusters_vars.row_mut(i).assign(&var);
         }
 
@@ -42,36 +49,16 @@ impl Index {
         Ok((val, clusters_vars.to_shared(), var))
     }
 }
-pub struct Node<'a> {
-    index: Index,
-    sender: Sender<'a, ScatValue>,
-}
-impl<'a> Node<'a> {
-    pub fn new(sender: Sender<'a, ScatValue>) -> Self {
-        Self {
-            index: Index,
-            sender,
-        }
-    }
-}
-
-impl<'a> Subscriber<(ArrayView2<'a, f64>, ArrayView1<'a, i32>)> for Node<'a> {
-    fn recieve_data(
-        &mut self,
-        data: Result<(ArrayView2<'a, f64>, ArrayView1<'a, i32>), CalcError>,
-    ) {
-        let res = match data {
-            Ok((ref x, ref y)) => {
-                self.index
-                    .compute(x, y)
-                    .map(|(val, clusters_vars, var)| ScatValue {
-                        val,
-                        clusters_vars,
-                        var,
-                    })
-            }
-            Err(err) => Err(err),
-        };
-        self.sender.send_to_subscribers(res);
-    }
+pub fn node() -> Node<ScatValue> {
+    Node::new("scat", |scope, ()| {
+        let (x, y) = scope.raw_data()?;
+        let weights = scope.weights()?;
+        Index
+            .compute(&x, &y, weights.as_ref())
+            .map(|(val, clusters_vars, var)| ScatValue {
+                val,
+                clusters_vars,
+                var,
+            })
+    })
 }
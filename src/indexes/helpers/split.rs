@@ -0,0 +1,113 @@
+use crate::{
+    calc_error::CalcError,
+    dataflow::{Node, Product},
+};
+use itertools::Itertools;
+use ndarray::{Array1, ArrayView1, ArrayView2, Axis};
+use std::collections::HashMap;
+use std::iter::zip;
+
+use super::{
+    clusters_centroids::ClustersCentroids, counts::Counts, total_dispercion::TD,
+    within_group_dispercion::WGD,
+};
+
+// dispersion of the cluster the next partition splits in two, the statistics of
+// duda, pseudot2 and beale
+#[derive(Clone, Copy, Debug)]
+pub struct SplitValue {
+    // within cluster sum of squares of the parent
+    pub parent_wss: f64,
+    // within cluster sums of squares of the two children added up
+    pub children_wss: f64,
+    // size of the parent, the sum of the weights of its members when weighted
+    pub n: f64,
+    // number of features
+    pub p: usize,
+}
+impl Product for SplitValue {}
+
+#[derive(Default)]
+pub struct Split;
+impl Split {
+    // x holds the members of the parent cluster, y splits them into the two children
+    pub fn compute(
+        &self,
+        x: &ArrayView2<f64>,
+        y: &ArrayView1<i32>,
+        weights: Option<&ArrayView1<f64>>,
+    ) -> Result<SplitValue, CalcError> {
+        if x.nrows() != y.len() {
+            return Err(CalcError::shape(
+                "Labels length doesnt match number of observations",
+            ));
+        }
+        if y.iter().any(|c| *c != 0 && *c != 1) {
+            return Err(CalcError::labels("Split labels must be 0 or 1"));
+        }
+        let counts = Counts.compute(y, weights)?;
+        if counts.len() != 2 {
+            return Err("Split requires exactly 2 clusters".into());
+        }
+        let centroids = ClustersCentroids.compute(x, y, &counts.view(), weights)?;
+        let wg = WGD.compute(x, y, &centroids.view(), weights)?;
+        let td = TD.compute(x, weights)?;
+        Ok(SplitValue {
+            parent_wss: td.diag().sum(),
+            children_wss: wg.diag().sum(),
+            n: counts.sum(),
+            p: x.ncols(),
+        })
+    }
+    // the members of the cluster of y that y_next splits in two and the child (0 or 1)
+    // each of them goes to, y_next must split one cluster of y and keep the others
+    pub fn find(
+        &self,
+        y: &ArrayView1<i32>,
+        y_next: &ArrayView1<i32>,
+    ) -> Result<(Vec<usize>, Array1<i32>), CalcError> {
+        if y.len() != y_next.len() {
+            return Err(CalcError::shape(
+                "Length of next partition doesnt match number of observations",
+            ));
+        }
+        let mut parents: HashMap<i32, i32> = HashMap::new();
+        for (c, c_next) in zip(y, y_next) {
+            if *parents.entry(*c_next).or_insert(*c) != *c {
+                return Err("Next partition doesnt refine the evaluated one".into());
+            }
+        }
+        let children = parents.values().copied().counts();
+        let parent = match children.iter().filter(|(_, n)| **n > 1).collect_vec()[..] {
+            [(parent, 2)] => *parent,
+            _ => return Err("Next partition must split exactly one cluster in two".into()),
+        };
+        let first = parents
+            .iter()
+            .filter(|(_, c)| **c == parent)
+            .map(|(c_next, _)| *c_next)
+            .min()
+            .ok_or("Cant find the children of the split cluster")?;
+        let rows = (0..y.len()).filter(|i| y[*i] == parent).collect_vec();
+        let children = rows
+            .iter()
+            .map(|i| i32::from(y_next[*i] != first))
+            .collect();
+        Ok((rows, children))
+    }
+}
+pub fn node() -> Node<SplitValue> {
+    Node::new("split", |scope, ()| {
+        let (x, y) = scope.raw_data()?;
+        let y_next = scope.next_labels()?;
+        let weights = scope.weights()?;
+        let (rows, children) = Split.find(&y, &y_next)?;
+        let x = x.select(Axis(0), &rows);
+        let weights = weights.map(|w| w.select(Axis(0), &rows));
+        Split.compute(
+            &x.view(),
+            &children.view(),
+            weights.as_ref().map(|w| w.view()).as_ref(),
+        )
+    })
+}
@@ -1,43 +1,44 @@
 use crate::{
     calc_error::CalcError,
-    sender::{Sender, Subscriber},
+    dataflow::{Node, Product},
+    profile::array_memory,
 };
 use ndarray::{ArcArray2, ArrayView1, ArrayView2, Axis};
 
+use super::weights::weighted_mean;
+
 #[derive(Clone, Debug)]
 pub struct TDValue {
     pub val: ArcArray2<f64>,
 }
+impl Product for TDValue {
+    fn memory(&self) -> usize {
+        array_memory(&self.val)
+    }
+}
 
 #[derive(Default)]
 pub struct TD;
 impl TD {
-    pub fn compute(&self, x: &ArrayView2<f64>) -> Result<ArcArray2<f64>, CalcError> {
-        let data_center = x.mean_axis(Axis(0)).ok_or("Cant calc data centroid")?;
-        let t = x - &data_center;
+    pub fn compute(
+        &self,
+        x: &ArrayView2<f64>,
+        weights: Option<&ArrayView1<f64>>,
+    ) -> Result<ArcArray2<f64>, CalcError> {
+        let data_center = weighted_mean(x, weights).ok_or("Cant calc data centroid")?;
+        let mut t = x - &data_center;
+        if let Some(w) = weights {
+            t *= &w.sqrt().insert_axis(Axis(1));
+        }
         let td = t.t().dot(&t);
         Ok(td.into_shared())
     }
 }
-pub struct TDNode<'a> {
-    index: TD,
-    sender: Sender<'a, TDValue>,
-}
-impl<'a> TDNode<'a> {
-    pub fn new(sender: Sender<'a, TDValue>) -> Self {
-        Self { index: TD, sender }
-    }
-}
-
-impl<'a> Subscriber<(ArrayView2<'a, f64>, ArrayView1<'a, i32>)> for TDNode<'a> {
-    fn recieve_data(
-        &mut self,
-        data: Result<(ArrayView2<'a, f64>, ArrayView1<'a, i32>), CalcError>,
-    ) {
-        let res = match data {
-            Ok((x, _)) => self.index.compute(&x).map(|val| TDValue { val }),
-            Err(err) => Err(err),
-        };
-        self.sender.send_to_subscribers(res);
-    }
+pub fn node() -> Node<TDValue> {
+    Node::new("total_dispercion", |scope, ()| {
+        let x = scope.features()?;
+        let weights = scope.weights()?;
+        TD.compute(&x, weights.as_ref()).map(|val| TDValue { val })
+    })
+    .label_independent()
 }
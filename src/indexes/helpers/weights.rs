@@ -0,0 +1,28 @@
+use ndarray::{Array1, ArrayView1, ArrayView2, Axis};
+
+// weight of observation i, every observation weighs 1 when no weights are given
+pub fn weight(weights: Option<&ArrayView1<f64>>, i: usize) -> f64 {
+    weights.map_or(1., |w| w[i])
+}
+
+// weighted mean of the rows of x
+pub fn weighted_mean(
+    x: &ArrayView2<f64>,
+    weights: Option<&ArrayView1<f64>>,
+) -> Option<Array1<f64>> {
+    match weights {
+        Some(w) if w.sum() > 0. => Some(w.dot(x) / w.sum()),
+        Some(_) => None,
+        None => x.mean_axis(Axis(0)),
+    }
+}
+
+// weighted population variance of every column of x
+pub fn weighted_var(x: &ArrayView2<f64>, weights: Option<&ArrayView1<f64>>) -> Option<Array1<f64>> {
+    let mean = weighted_mean(x, weights)?;
+    let sq = (x - &mean).pow2();
+    match weights {
+        Some(w) => Some(w.dot(&sq) / w.sum()),
+        None => sq.mean_axis(Axis(0)),
+    }
+}
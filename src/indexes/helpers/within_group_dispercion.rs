@@ -1,16 +1,24 @@
 use crate::{
-    calc_error::{CalcError, CombineErrors},
+    calc_error::CalcError,
+    dataflow::{Node, Product},
     indexes::helpers::clusters_centroids::ClustersCentroidsValue,
-    sender::{Sender, Subscriber},
+    profile::array_memory,
 };
 
 use ndarray::{ArcArray2, Array2, ArrayView1, ArrayView2};
 use std::iter::zip;
 
+use super::weights::weight;
+
 #[derive(Clone, Debug)]
 pub struct WGDValue {
     pub val: ArcArray2<f64>,
 }
+impl Product for WGDValue {
+    fn memory(&self) -> usize {
+        array_memory(&self.val)
+    }
+}
 #[derive(Default)]
 pub struct WGD;
 impl WGD {
@@ -19,62 +27,27 @@ impl WGD {
         x: &ArrayView2<f64>,
         y: &ArrayView1<i32>,
         clusters_centroids: &ArrayView2<f64>,
+        weights: Option<&ArrayView1<f64>>,
     ) -> Result<ArcArray2<f64>, CalcError> {
         let (n, d) = x.dim();
         let mut dif: Array2<f64> = Array2::zeros((n, d));
         for (i, (x, y)) in zip(x.rows(), y).enumerate() {
-            let temp = &clusters_centroids.row(*y as usize) - &x;
+            // scaling by the square root weighs every outer product by the observation weight
+            let temp = (&clusters_centroids.row(*y as usize) - &x) * weight(weights, i).sqrt();
             dif.row_mut(i).assign(&temp);
         }
         let wg = dif.t().dot(&dif);
         Ok(wg.into_shared())
     }
 }
-pub struct WGDNode<'a> {
-    index: WGD,
-    clusters_centroids: Option<Result<ArcArray2<f64>, CalcError>>,
-    raw_data: Option<Result<(ArrayView2<'a, f64>, ArrayView1<'a, i32>), CalcError>>,
-    sender: Sender<'a, WGDValue>,
-}
-impl<'a> WGDNode<'a> {
-    pub fn new(sender: Sender<'a, WGDValue>) -> Self {
-        Self {
-            index: WGD,
-            clusters_centroids: None,
-            raw_data: None,
-            sender,
-        }
-    }
-    fn process_when_ready(&mut self) {
-        if let (Some(clusters_centroids), Some(raw_data)) =
-            (self.clusters_centroids.as_ref(), self.raw_data.as_ref())
-        {
-            let res = match clusters_centroids.combine(raw_data) {
-                Ok((cls_ctrs, (x, y))) => self
-                    .index
-                    .compute(x, y, &cls_ctrs.view())
-                    .map(|val| WGDValue { val }),
-                Err(err) => Err(err),
-            };
-            self.sender.send_to_subscribers(res);
-            self.raw_data = None;
-            self.clusters_centroids = None;
-        }
-    }
-}
-impl<'a> Subscriber<ClustersCentroidsValue> for WGDNode<'a> {
-    fn recieve_data(&mut self, data: Result<ClustersCentroidsValue, CalcError>) {
-        self.clusters_centroids = Some(data.map(|v| v.val));
-        self.process_when_ready();
-    }
-}
-
-impl<'a> Subscriber<(ArrayView2<'a, f64>, ArrayView1<'a, i32>)> for WGDNode<'a> {
-    fn recieve_data(
-        &mut self,
-        data: Result<(ArrayView2<'a, f64>, ArrayView1<'a, i32>), CalcError>,
-    ) {
-        self.raw_data = Some(data);
-        self.process_when_ready();
-    }
+pub fn node() -> Node<WGDValue> {
+    Node::new(
+        "within_group_dispercion",
+        |scope, (centroids,): (ClustersCentroidsValue,)| {
+            let (x, y) = scope.raw_data()?;
+            let weights = scope.weights()?;
+            WGD.compute(&x, &y, &centroids.val.view(), weights.as_ref())
+                .map(|val| WGDValue { val })
+        },
+    )
 }
@@ -0,0 +1,26 @@
+use crate::calc_error::CalcError;
+use crate::dataflow::{Node, Product};
+use ndarray::ArrayView2;
+
+use super::helpers::contingency::{pair_counts, ContingencyValue};
+
+#[derive(Clone, Copy, Debug)]
+pub struct JaccardIndexValue {
+    pub val: f64,
+}
+impl Product for JaccardIndexValue {}
+#[derive(Default)]
+pub struct Index;
+impl Index {
+    fn compute(&self, contingency: &ArrayView2<usize>) -> Result<f64, CalcError> {
+        let (both, only_class, only_cluster, _) = pair_counts(contingency);
+        Ok(both / (both + only_class + only_cluster))
+    }
+}
+pub fn node() -> Node<JaccardIndexValue> {
+    Node::new("jaccard", |_, (contingency,): (ContingencyValue,)| {
+        Index
+            .compute(&contingency.val.view())
+            .map(|val| JaccardIndexValue { val })
+    })
+}
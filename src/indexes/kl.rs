@@ -0,0 +1,56 @@
+use crate::calc_error::CalcError;
+use crate::dataflow::{Node, Product};
+use ndarray::{ArrayView1, ArrayView2};
+
+use super::helpers::{
+    adjacent::{NextPartition, PreviousPartition},
+    counts::CountsValue,
+    within_group_dispercion::WGDValue,
+};
+
+#[derive(Clone, Copy, Debug)]
+pub struct KLIndexValue {
+    pub val: f64,
+}
+impl Product for KLIndexValue {}
+#[derive(Default)]
+pub struct Index;
+impl Index {
+    // adjacent partitions are expected to have k - 1 and k + 1 clusters
+    fn compute(
+        &self,
+        wg_prev: &ArrayView2<f64>,
+        wg: &ArrayView2<f64>,
+        wg_next: &ArrayView2<f64>,
+        counts: &ArrayView1<f64>,
+    ) -> Result<f64, CalcError> {
+        let k = counts.len() as f64;
+        let p = wg.ncols() as f64;
+        let weighted_trace = |q: f64, wg: &ArrayView2<f64>| q.powf(2. / p) * wg.diag().sum();
+        let diff = weighted_trace(k - 1., wg_prev) - weighted_trace(k, wg);
+        let diff_next = weighted_trace(k, wg) - weighted_trace(k + 1., wg_next);
+        Ok((diff / diff_next).abs())
+    }
+}
+
+pub fn node() -> Node<KLIndexValue> {
+    Node::new(
+        "kl",
+        |_,
+         (PreviousPartition(wg_prev), wg, NextPartition(wg_next), counts): (
+            PreviousPartition<WGDValue>,
+            WGDValue,
+            NextPartition<WGDValue>,
+            CountsValue,
+        )| {
+            Index
+                .compute(
+                    &wg_prev.val.view(),
+                    &wg.val.view(),
+                    &wg_next.val.view(),
+                    &counts.val.view(),
+                )
+                .map(|val| KLIndexValue { val })
+        },
+    )
+}
@@ -1,21 +1,21 @@
-use crate::calc_error::{CalcError, CombineErrors};
-use ndarray::{ArcArray1, ArcArray2, ArrayView1, ArrayView2};
+use crate::calc_error::CalcError;
+use crate::dataflow::{Node, Product};
+use ndarray::{ArrayView1, ArrayView2};
 use ndarray_linalg::Determinant;
 
-use crate::sender::{Sender, Subscriber};
-
-use super::helpers::within_group_dispercion::WGDValue;
+use super::helpers::{counts::CountsValue, within_group_dispercion::WGDValue};
 #[derive(Clone, Copy, Debug)]
 pub struct MariottIndexValue {
     pub val: f64,
 }
+impl Product for MariottIndexValue {}
 
 #[derive(Default)]
 pub struct Index;
 impl Index {
     pub fn compute(
         &self,
-        counts: &ArrayView1<usize>,
+        counts: &ArrayView1<f64>,
         wg: &ArrayView2<f64>,
     ) -> Result<f64, CalcError> {
         let q = counts.len();
@@ -35,47 +35,10 @@ impl Index {
     }
 }
 
-pub struct Node<'a> {
-    index: Index,
-    counts: Option<Result<ArcArray1<usize>, CalcError>>,
-    wg: Option<Result<ArcArray2<f64>, CalcError>>,
-    sender: Sender<'a, MariottIndexValue>,
-}
-
-impl<'a> Node<'a> {
-    pub fn new(sender: Sender<'a, MariottIndexValue>) -> Self {
-        Self {
-            index: Index,
-            counts: None,
-            wg: None,
-            sender,
-        }
-    }
-    fn process_when_ready(&mut self) {
-        if let (Some(counts), Some(wg)) = (self.counts.as_ref(), self.wg.as_ref()) {
-            let res = match wg.combine(counts) {
-                Ok((wg, cnts)) => self
-                    .index
-                    .compute(&cnts.view(), &wg.view())
-                    .map(|val| MariottIndexValue { val }),
-                Err(err) => Err(err),
-            };
-            self.sender.send_to_subscribers(res);
-            self.wg = None;
-            self.counts = None;
-        }
-    }
-}
-
-impl<'a> Subscriber<ArcArray1<usize>> for Node<'a> {
-    fn recieve_data(&mut self, data: Result<ArcArray1<usize>, CalcError>) {
-        self.counts = Some(data);
-        self.process_when_ready();
-    }
-}
-impl<'a> Subscriber<WGDValue> for Node<'a> {
-    fn recieve_data(&mut self, data: Result<WGDValue, CalcError>) {
-        self.wg = Some(data.map(|v| v.val));
-        self.process_when_ready();
-    }
+pub fn node() -> Node<MariottIndexValue> {
+    Node::new("mariott", |_, (wg, counts): (WGDValue, CountsValue)| {
+        Index
+            .compute(&counts.val.view(), &wg.val.view())
+            .map(|val| MariottIndexValue { val })
+    })
 }
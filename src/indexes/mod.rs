@@ -24,12 +24,20 @@ pub mod sdbw;
 pub mod trcovw;
 //
 //
-// pub mod dindex;
-// pub mod beale;
-// pub mod duda;
-// pub mod pseudot2;
+pub mod beale;
+pub mod dindex;
+pub mod duda;
+pub mod pseudot2;
 //
-// pub mod gap;
-// pub mod kl;
-// pub mod frey;
-// pub mod hardigan;
+pub mod frey;
+pub mod gap;
+pub mod hartigan;
+pub mod kl;
+//
+pub mod adjusted_rand;
+pub mod fowlkes_mallows;
+pub mod jaccard;
+pub mod mutual_information;
+pub mod purity;
+pub mod rand_index;
+pub mod v_measure;
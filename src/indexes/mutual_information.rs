@@ -0,0 +1,93 @@
+use crate::calc_error::CalcError;
+use crate::dataflow::{Node, Product};
+use ndarray::{Array1, ArrayView2, Axis};
+
+use super::helpers::contingency::{entropy, mutual_information, ContingencyValue};
+
+#[derive(Clone, Copy, Debug)]
+pub struct NMIIndexValue {
+    pub val: f64,
+}
+impl Product for NMIIndexValue {}
+#[derive(Clone, Copy, Debug)]
+pub struct AMIIndexValue {
+    pub val: f64,
+}
+impl Product for AMIIndexValue {}
+#[derive(Default)]
+pub struct Index;
+impl Index {
+    // mutual information normalized by the arithmetic mean of the entropies
+    fn compute_normalized(&self, contingency: &ArrayView2<usize>) -> Result<f64, CalcError> {
+        let h_true = entropy(&contingency.sum_axis(Axis(1)));
+        let h_pred = entropy(&contingency.sum_axis(Axis(0)));
+        // both partitions have a single cluster
+        if h_true == 0. && h_pred == 0. {
+            return Ok(1.);
+        }
+        Ok(mutual_information(contingency) / ((h_true + h_pred) / 2.))
+    }
+    // mutual information adjusted for chance under the hypergeometric model
+    fn compute_adjusted(&self, contingency: &ArrayView2<usize>) -> Result<f64, CalcError> {
+        let rows = contingency.sum_axis(Axis(1));
+        let cols = contingency.sum_axis(Axis(0));
+        let h_true = entropy(&rows);
+        let h_pred = entropy(&cols);
+        if h_true == 0. && h_pred == 0. {
+            return Ok(1.);
+        }
+        let emi = expected_mutual_information(&rows, &cols);
+        let denominator = (h_true + h_pred) / 2. - emi;
+        if denominator == 0. {
+            return Err("Expected mutual information equals the mean entropy".into());
+        }
+        Ok((mutual_information(contingency) - emi) / denominator)
+    }
+}
+
+fn expected_mutual_information(rows: &Array1<usize>, cols: &Array1<usize>) -> f64 {
+    let n = rows.sum();
+    // ln(k!) for k in 0..=n
+    let ln_factorial = (0..=n)
+        .scan(0., |acc, k| {
+            if k > 0 {
+                *acc += (k as f64).ln();
+            }
+            Some(*acc)
+        })
+        .collect::<Vec<f64>>();
+    let n_f = n as f64;
+    let mut emi = 0.;
+    for a in rows.iter().copied() {
+        for b in cols.iter().copied() {
+            let start = (a + b).saturating_sub(n).max(1);
+            for nij in start..=a.min(b) {
+                let term = nij as f64 / n_f * (n_f * nij as f64 / (a * b) as f64).ln();
+                let ln_probability =
+                    ln_factorial[a] + ln_factorial[b] + ln_factorial[n - a] + ln_factorial[n - b]
+                        - ln_factorial[n]
+                        - ln_factorial[nij]
+                        - ln_factorial[a - nij]
+                        - ln_factorial[b - nij]
+                        - ln_factorial[n + nij - a - b];
+                emi += term * ln_probability.exp();
+            }
+        }
+    }
+    emi
+}
+
+pub fn nmi_node() -> Node<NMIIndexValue> {
+    Node::new("nmi", |_, (contingency,): (ContingencyValue,)| {
+        Index
+            .compute_normalized(&contingency.val.view())
+            .map(|val| NMIIndexValue { val })
+    })
+}
+pub fn ami_node() -> Node<AMIIndexValue> {
+    Node::new("ami", |_, (contingency,): (ContingencyValue,)| {
+        Index
+            .compute_adjusted(&contingency.val.view())
+            .map(|val| AMIIndexValue { val })
+    })
+}
@@ -1,36 +1,49 @@
-use super::*;
-use itertools::Itertools;
+use crate::calc_error::CalcError;
+use crate::dataflow::{Node, Product};
+use ndarray::{ArrayView1, ArrayView2};
 
-pub struct Index {}
-impl Computable for Index {
-    fn compute(&self, x: ArrayView2<f64>, y: ArrayView1<i32>) -> Result<f64, CalcError> {
-        if y.iter().counts().keys().len() != 2 {
-            return Err(CalcError::from("There is more than 2 clusters"));
-        }
-        let dataset_mean = x.mean_axis(Axis(0)).ok_or("Cant calc mean")?;
-        let clusters = calc_clusters(&y);
-        let cluster_centers = calc_clusters_centers(&clusters, &x);
-
-        let within_group_dispersion_parent = {
-            let diff = &x - &dataset_mean;
-            diff.dot(&diff.t()).diag().sum()
-        };
+use super::duda;
+use super::helpers::split::{Split, SplitValue};
 
-        let mut within_group_dispersion_children: HashMap<i32, f64> = HashMap::default();
-        for (cl_idx, idxs) in clusters.iter() {
-            for idx in idxs {
-                let diff = &x.row(*idx) - &cluster_centers[cl_idx];
+#[derive(Clone, Copy, Debug)]
+pub struct PseudoT2IndexValue {
+    pub val: f64,
+    // value above which the split is significant, derived from the one of duda
+    pub critical: f64,
+}
+impl Product for PseudoT2IndexValue {}
+impl PseudoT2IndexValue {
+    // the parent is kept as a single cluster
+    pub fn accepted(&self) -> bool {
+        self.val <= self.critical
+    }
+}
 
-                within_group_dispersion_children
-                    .entry(*cl_idx)
-                    .and_modify(|v| *v += diff.dot(&diff))
-                    .or_insert(0.);
-            }
+#[derive(Default)]
+pub struct Index;
+impl Index {
+    // members of the parent cluster and the child each of them goes to
+    pub fn compute(
+        &self,
+        x: &ArrayView2<f64>,
+        y: &ArrayView1<i32>,
+        weights: Option<&ArrayView1<f64>>,
+    ) -> Result<PseudoT2IndexValue, CalcError> {
+        Split.compute(x, y, weights).map(|split| self.value(&split))
+    }
+    fn value(&self, split: &SplitValue) -> PseudoT2IndexValue {
+        let je1 = split.parent_wss;
+        let je2 = split.children_wss;
+        let critical = duda::critical_value(split);
+        PseudoT2IndexValue {
+            val: (je1 - je2) / (je2 / (split.n - 2.)),
+            critical: (1. - critical) / critical * (split.n - 2.),
         }
-        let w_children_sum = within_group_dispersion_children.values().sum::<f64>();
-        let value = (within_group_dispersion_parent - w_children_sum)
-            / w_children_sum
-            / (x.nrows() - 2) as f64;
-        Ok(value)
     }
 }
+
+pub fn node() -> Node<PseudoT2IndexValue> {
+    Node::new("pseudot2", |_, (split,): (SplitValue,)| {
+        Ok(Index.value(&split))
+    })
+}
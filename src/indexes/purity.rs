@@ -0,0 +1,30 @@
+use crate::calc_error::CalcError;
+use crate::dataflow::{Node, Product};
+use ndarray::ArrayView2;
+
+use super::helpers::contingency::ContingencyValue;
+
+#[derive(Clone, Copy, Debug)]
+pub struct PurityIndexValue {
+    pub val: f64,
+}
+impl Product for PurityIndexValue {}
+#[derive(Default)]
+pub struct Index;
+impl Index {
+    fn compute(&self, contingency: &ArrayView2<usize>) -> Result<f64, CalcError> {
+        let majority = contingency
+            .columns()
+            .into_iter()
+            .map(|c| c.iter().copied().max().unwrap_or_default())
+            .sum::<usize>();
+        Ok(majority as f64 / contingency.sum() as f64)
+    }
+}
+pub fn node() -> Node<PurityIndexValue> {
+    Node::new("purity", |_, (contingency,): (ContingencyValue,)| {
+        Index
+            .compute(&contingency.val.view())
+            .map(|val| PurityIndexValue { val })
+    })
+}
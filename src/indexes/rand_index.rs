@@ -0,0 +1,26 @@
+use crate::calc_error::CalcError;
+use crate::dataflow::{Node, Product};
+use ndarray::ArrayView2;
+
+use super::helpers::contingency::{pair_counts, ContingencyValue};
+
+#[derive(Clone, Copy, Debug)]
+pub struct RandIndexValue {
+    pub val: f64,
+}
+impl Product for RandIndexValue {}
+#[derive(Default)]
+pub struct Index;
+impl Index {
+    fn compute(&self, contingency: &ArrayView2<usize>) -> Result<f64, CalcError> {
+        let (both, only_class, only_cluster, neither) = pair_counts(contingency);
+        Ok((both + neither) / (both + only_class + only_cluster + neither))
+    }
+}
+pub fn node() -> Node<RandIndexValue> {
+    Node::new("rand", |_, (contingency,): (ContingencyValue,)| {
+        Index
+            .compute(&contingency.val.view())
+            .map(|val| RandIndexValue { val })
+    })
+}
@@ -1,22 +1,23 @@
-use crate::calc_error::{CalcError, CombineErrors};
-use ndarray::{ArcArray1, ArcArray2, Array1, ArrayView1, ArrayView2, Axis};
-use std::{collections::HashMap, sync::Arc};
+use crate::calc_error::CalcError;
+use crate::dataflow::{Node, Product};
+use ndarray::{ArrayView1, ArrayView2};
 
-use crate::sender::{Sender, Subscriber};
-
-use super::helpers::{between_group_dispercion::BGDValue, counts, total_dispercion::TDValue};
+use super::helpers::{
+    between_group_dispercion::BGDValue, counts::CountsValue, total_dispercion::TDValue,
+};
 
 #[derive(Clone, Copy, Debug)]
 pub struct RatkowskyIndexValue {
     pub val: f64,
 }
+impl Product for RatkowskyIndexValue {}
 #[derive(Default)]
 pub struct Index;
 
 impl Index {
     fn compute(
         &self,
-        counts: &ArrayView1<usize>,
+        counts: &ArrayView1<f64>,
         tg: &ArrayView2<f64>,
         bg: &ArrayView2<f64>,
     ) -> Result<f64, CalcError> {
@@ -46,58 +47,13 @@ impl Index {
     }
 }
 
-pub struct Node<'a> {
-    index: Index,
-    bg: Option<Result<ArcArray2<f64>, CalcError>>,
-    tg: Option<Result<ArcArray2<f64>, CalcError>>,
-    counts: Option<Result<ArcArray1<usize>, CalcError>>,
-    sender: Sender<'a, RatkowskyIndexValue>,
-}
-
-impl<'a> Node<'a> {
-    fn process_when_ready(&mut self) {
-        if let (Some(counts), Some(tg), Some(bg)) =
-            (self.counts.as_ref(), self.tg.as_ref(), self.bg.as_ref())
-        {
-            let res = match counts.combine(tg).combine(bg) {
-                Ok(((cnts, tg), bg)) => self
-                    .index
-                    .compute(&cnts.view(), &tg.view(), &bg.view())
-                    .map(|val| RatkowskyIndexValue { val }),
-                Err(err) => Err(err),
-            };
-            self.sender.send_to_subscribers(res);
-            self.counts = None;
-            self.tg = None;
-            self.bg = None;
-        }
-    }
-    pub fn new(sender: Sender<'a, RatkowskyIndexValue>) -> Self {
-        Self {
-            index: Index,
-            counts: None,
-            tg: None,
-            bg: None,
-            sender,
-        }
-    }
-}
-
-impl<'a> Subscriber<ArcArray1<usize>> for Node<'a> {
-    fn recieve_data(&mut self, data: Result<ArcArray1<usize>, CalcError>) {
-        self.counts = Some(data);
-        self.process_when_ready();
-    }
-}
-impl<'a> Subscriber<TDValue> for Node<'a> {
-    fn recieve_data(&mut self, data: Result<TDValue, CalcError>) {
-        self.tg = Some(data.map(|v| v.val));
-        self.process_when_ready();
-    }
-}
-impl<'a> Subscriber<BGDValue> for Node<'a> {
-    fn recieve_data(&mut self, data: Result<BGDValue, CalcError>) {
-        self.bg = Some(data.map(|v| v.val));
-        self.process_when_ready();
-    }
+pub fn node() -> Node<RatkowskyIndexValue> {
+    Node::new(
+        "ratkowsky",
+        |_, (counts, td, bg): (CountsValue, TDValue, BGDValue)| {
+            Index
+                .compute(&counts.val.view(), &td.val.view(), &bg.val.view())
+                .map(|val| RatkowskyIndexValue { val })
+        },
+    )
 }
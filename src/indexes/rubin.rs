@@ -1,8 +1,8 @@
-use crate::calc_error::{CalcError, CombineErrors};
-use ndarray::{ArcArray2, ArrayView2};
+use crate::calc_error::CalcError;
+use ndarray::ArrayView2;
 use ndarray_linalg::Determinant;
 
-use crate::sender::{Sender, Subscriber};
+use crate::dataflow::{Node, Product};
 
 use super::helpers::{total_dispercion::TDValue, within_group_dispercion::WGDValue};
 
@@ -10,6 +10,7 @@ use super::helpers::{total_dispercion::TDValue, within_group_dispercion::WGDValu
 pub struct RubinIndexValue {
     pub val: f64,
 }
+impl Product for RubinIndexValue {}
 #[derive(Default)]
 pub struct Index;
 impl Index {
@@ -34,48 +35,10 @@ impl Index {
     }
 }
 
-pub struct Node<'a> {
-    index: Index,
-    wg: Option<Result<ArcArray2<f64>, CalcError>>,
-    td: Option<Result<ArcArray2<f64>, CalcError>>,
-    sender: Sender<'a, RubinIndexValue>,
-}
-
-impl<'a> Node<'a> {
-    pub fn new(sender: Sender<'a, RubinIndexValue>) -> Self {
-        Self {
-            index: Index,
-            wg: None,
-            td: None,
-            sender,
-        }
-    }
-    fn process_when_ready(&mut self) {
-        if let (Some(wg), Some(td)) = (self.wg.as_ref(), self.td.as_ref()) {
-            let res = match wg.combine(td) {
-                Ok((wg, td)) => self
-                    .index
-                    .compute(&wg.view(), &td.view())
-                    .map(|val| RubinIndexValue { val }),
-                Err(err) => Err(err),
-            };
-            self.sender.send_to_subscribers(res);
-            self.wg = None;
-            self.td = None;
-        }
-    }
-}
-
-impl<'a> Subscriber<WGDValue> for Node<'a> {
-    fn recieve_data(&mut self, data: Result<WGDValue, CalcError>) {
-        self.wg = Some(data.map(|v| v.val));
-        self.process_when_ready();
-    }
-}
-
-impl<'a> Subscriber<TDValue> for Node<'a> {
-    fn recieve_data(&mut self, data: Result<TDValue, CalcError>) {
-        self.td = Some(data.map(|v| v.val));
-        self.process_when_ready();
-    }
+pub fn node() -> Node<RubinIndexValue> {
+    Node::new("rubin", |_, (wg, td): (WGDValue, TDValue)| {
+        Index
+            .compute(&wg.val.view(), &td.val.view())
+            .map(|val| RubinIndexValue { val })
+    })
 }
@@ -1,16 +1,17 @@
-use crate::calc_error::{CalcError, CombineErrors};
-use ndarray::{ArcArray1, ArcArray2, Array1, Array2, ArrayView1, ArrayView2, Axis};
+use crate::calc_error::CalcError;
+use crate::dataflow::{Node, Product};
+use ndarray::{ArrayView1, ArrayView2};
 use ndarray_linalg::Determinant;
-use std::{collections::HashMap, sync::Arc};
 
-use crate::sender::{Sender, Subscriber};
-
-use super::helpers::{total_dispercion::TDValue, within_group_dispercion::WGDValue};
+use super::helpers::{
+    counts::CountsValue, total_dispercion::TDValue, within_group_dispercion::WGDValue,
+};
 
 #[derive(Clone, Copy, Debug)]
 pub struct ScottIndexValue {
     pub val: f64,
 }
+impl Product for ScottIndexValue {}
 #[derive(Default)]
 pub struct Index;
 impl Index {
@@ -18,9 +19,9 @@ impl Index {
         &self,
         wg: &ArrayView2<f64>,
         td: &ArrayView2<f64>,
-        counts: &ArrayView1<usize>,
+        counts: &ArrayView1<f64>,
     ) -> Result<f64, CalcError> {
-        let n = counts.sum() as f64;
+        let n = counts.sum();
         let det_t = td.det().map_err(|e| CalcError::from(format!("{e:?}")))?;
         let det_wg = wg.det().map_err(|e| CalcError::from(format!("{e:?}")))?;
         let val = (det_t / det_wg).ln();
@@ -44,58 +45,13 @@ impl Index {
     }
 }
 
-pub struct Node<'a> {
-    index: Index,
-    counts: Option<Result<ArcArray1<usize>, CalcError>>,
-    wg: Option<Result<ArcArray2<f64>, CalcError>>,
-    td: Option<Result<ArcArray2<f64>, CalcError>>,
-    sender: Sender<'a, ScottIndexValue>,
-}
-
-impl<'a> Node<'a> {
-    pub fn new(sender: Sender<'a, ScottIndexValue>) -> Self {
-        Self {
-            index: Index,
-            wg: None,
-            td: None,
-            counts: None,
-            sender,
-        }
-    }
-    fn process_when_ready(&mut self) {
-        if let (Some(wg), Some(td), Some(counts)) =
-            (self.wg.as_ref(), self.td.as_ref(), self.counts.as_ref())
-        {
-            let res = match wg.combine(td).combine(counts) {
-                Ok(((wg, td), cnts)) => self
-                    .index
-                    .compute(&wg.view(), &td.view(), &cnts.view())
-                    .map(|val| ScottIndexValue { val }),
-                Err(err) => Err(err),
-            };
-            self.sender.send_to_subscribers(res);
-            self.wg = None;
-            self.td = None;
-            self.counts = None;
-        }
-    }
-}
-impl<'a> Subscriber<ArcArray1<usize>> for Node<'a> {
-    fn recieve_data(&mut self, data: Result<ArcArray1<usize>, CalcError>) {
-        self.counts = Some(data);
-        self.process_when_ready();
-    }
-}
-impl<'a> Subscriber<WGDValue> for Node<'a> {
-    fn recieve_data(&mut self, data: Result<WGDValue, CalcError>) {
-        self.wg = Some(data.map(|v| v.val));
-        self.process_when_ready();
-    }
-}
-
-impl<'a> Subscriber<TDValue> for Node<'a> {
-    fn recieve_data(&mut self, data: Result<TDValue, CalcError>) {
-        self.td = Some(data.map(|v| v.val));
-        self.process_when_ready();
-    }
+pub fn node() -> Node<ScottIndexValue> {
+    Node::new(
+        "scott",
+        |_, (wg, td, counts): (WGDValue, TDValue, CountsValue)| {
+            Index
+                .compute(&wg.val.view(), &td.val.view(), &counts.val.view())
+                .map(|val| ScottIndexValue { val })
+        },
+    )
 }
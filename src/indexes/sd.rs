@@ -1,14 +1,21 @@
-use crate::calc_error::{CalcError, CombineErrors};
-use ndarray::{ArcArray1, ArcArray2, ArrayView1, ArrayView2};
+use crate::calc_error::CalcError;
+use ndarray::{ArcArray1, ArcArray2, Array1, Array2, ArrayView1, ArrayView2};
 
-use crate::sender::{Sender, Subscriber};
+use crate::dataflow::{Node, Product};
+use crate::metric::Metric;
 
 use super::helpers::{clusters_centroids::ClustersCentroidsValue, scat::ScatValue};
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Debug)]
 pub struct SDIndexValue {
     pub val: f64,
+    // variance norm of every cluster relative to the one of the whole dataset
+    pub scat: ArcArray1<f64>,
+    // contribution of every cluster to Dis, the inverse of its summed centroid distances
+    pub dis: ArcArray1<f64>,
+    pub centroid_distances: ArcArray2<f64>,
 }
+impl Product for SDIndexValue {}
 #[derive(Default)]
 pub struct Index;
 
@@ -16,8 +23,14 @@ impl Index {
     pub fn compute(
         &self,
         scat: &f64,
+        clusters_vars: &ArrayView1<f64>,
+        var: f64,
         clusters_centroids: &ArrayView2<f64>,
-    ) -> Result<f64, CalcError> {
+        metric: &Metric,
+    ) -> Result<SDIndexValue, CalcError> {
+        let q = clusters_centroids.nrows();
+        let mut centroid_distances = Array2::zeros((q, q));
+        let mut dis = Array1::zeros(q);
         let mut d = 0.0;
         let mut d_max = f64::MIN;
         let mut d_min = f64::MAX;
@@ -25,7 +38,8 @@ impl Index {
             let mut dist_acum = 0.0;
             for (j, row2) in clusters_centroids.rows().into_iter().enumerate() {
                 if i != j {
-                    let dist = (&row2 - &row1).pow2().sum().sqrt();
+                    let dist = metric.distance(row1, row2)?;
+                    centroid_distances[[i, j]] = dist;
                     dist_acum += dist;
                     if i < j {
                         if dist > d_max {
@@ -38,60 +52,34 @@ impl Index {
                 }
             }
             if dist_acum != 0.0 {
+                dis[i] = 1. / dist_acum;
                 d += 1. / dist_acum;
             }
         }
-        let value = d * d_max / d_min;
+        let value = scat + d * d_max / d_min;
 
-        Ok(value)
+        Ok(SDIndexValue {
+            val: value,
+            scat: (clusters_vars / var).into_shared(),
+            dis: dis.into_shared(),
+            centroid_distances: centroid_distances.into_shared(),
+        })
     }
 }
 
-pub struct Node<'a> {
-    index: Index,
-    scat: Option<Result<(f64, ArcArray1<f64>, f64), CalcError>>,
-    clusters_centroids: Option<Result<ArcArray2<f64>, CalcError>>,
-    sender: Sender<'a, SDIndexValue>,
-}
-
-impl<'a> Node<'a> {
-    fn process_when_ready(&mut self) {
-        if let (Some(scat), Some(clusters_centroids)) =
-            (self.scat.as_ref(), self.clusters_centroids.as_ref())
-        {
-            let res = match scat.combine(clusters_centroids) {
-                Ok(((val, _, _), cls_ctrds)) => self
-                    .index
-                    .compute(val, &cls_ctrds.view())
-                    .map(|val| SDIndexValue { val }),
-                Err(err) => Err(err),
-            };
-            self.sender.send_to_subscribers(res);
-            self.scat = None;
-            self.clusters_centroids = None;
-        }
-    }
-    pub fn new(sender: Sender<'a, SDIndexValue>) -> Self {
-        Self {
-            index: Index,
-            scat: None,
-            clusters_centroids: None,
-            sender,
-        }
-    }
-}
-
-impl<'a> Subscriber<ScatValue> for Node<'a> {
-    fn recieve_data(&mut self, data: Result<ScatValue, CalcError>) {
-        self.scat = Some(data.map(|v| (v.val, v.clusters_vars, v.var)));
-        self.process_when_ready();
-    }
-}
-impl<'a> Subscriber<ClustersCentroidsValue> for Node<'a> {
-    fn recieve_data(&mut self, data: Result<ClustersCentroidsValue, CalcError>) {
-        self.clusters_centroids = Some(data.map(|v| v.val));
-        self.process_when_ready();
-    }
+pub fn node() -> Node<SDIndexValue> {
+    Node::new(
+        "sd",
+        |_, (scat, centroids, metric): (ScatValue, ClustersCentroidsValue, Metric)| {
+            Index.compute(
+                &scat.val,
+                &scat.clusters_vars.view(),
+                scat.var,
+                &centroids.val.view(),
+                &metric,
+            )
+        },
+    )
 }
 // impl Computable for IndexDis {
 //     fn compute(&self, x: ArrayView2<f64>, y: ArrayView1<i32>) -> Result<f64, CalcError> {
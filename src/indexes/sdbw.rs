@@ -1,16 +1,21 @@
 use std::iter::zip;
 
-use crate::calc_error::{CalcError, CombineErrors};
-use ndarray::{ArcArray1, ArcArray2, ArrayView1, ArrayView2};
+use crate::calc_error::CalcError;
+use ndarray::{ArcArray2, Array2, ArrayView1, ArrayView2};
 
-use crate::sender::{Sender, Subscriber};
+use crate::dataflow::{Node, Product};
+use crate::metric::Metric;
 
 use super::helpers::{clusters_centroids::ClustersCentroidsValue, scat::ScatValue};
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Debug)]
 pub struct SDBWIndexValue {
     pub val: f64,
+    // density at the midpoint of every two clusters relative to the denser of their centroids,
+    // NaN on the diagonal
+    pub density_ratios: ArcArray2<f64>,
 }
+impl Product for SDBWIndexValue {}
 #[derive(Default)]
 pub struct Index;
 
@@ -22,10 +27,11 @@ impl Index {
         x: &ArrayView2<f64>,
         y: &ArrayView1<i32>,
         clusters_centroids: &ArrayView2<f64>,
-    ) -> Result<f64, CalcError> {
+        metric: &Metric,
+    ) -> Result<SDBWIndexValue, CalcError> {
         let q = clusters_centroids.nrows();
         let stdev = centroid_vars.sum().sqrt() / q as f64;
-        let mut accum = 0;
+        let mut density_ratios = Array2::from_elem((q, q), f64::NAN);
         for i in 0..q {
             for j in 0..q {
                 if i != j {
@@ -35,17 +41,28 @@ impl Index {
                         y,
                         |v| v == i || v == j,
                         ((&clusters_centroids.row(i) + &clusters_centroids.row(j)) / 2.).view(),
-                    );
+                        metric,
+                    )?;
                     let density2 =
-                        Self::density(stdev, x, y, |v| v == i, clusters_centroids.row(i));
+                        Self::density(stdev, x, y, |v| v == i, clusters_centroids.row(i), metric)?;
                     let density3 =
-                        Self::density(stdev, x, y, |v| v == j, clusters_centroids.row(j));
-                    accum += density1 / density2.max(density3);
+                        Self::density(stdev, x, y, |v| v == j, clusters_centroids.row(j), metric)?;
+                    // no observation around either centroid leaves nothing to compare with
+                    let denominator = density2.max(density3);
+                    density_ratios[[i, j]] = if denominator == 0 {
+                        0.
+                    } else {
+                        density1 as f64 / denominator as f64
+                    };
                 }
             }
         }
-        let value = (accum / (q * (q - 1))) as f64;
-        Ok(value)
+        let accum = density_ratios.iter().filter(|v| !v.is_nan()).sum::<f64>();
+        let value = scat + accum / (q * (q - 1)) as f64;
+        Ok(SDBWIndexValue {
+            val: value,
+            density_ratios: density_ratios.into_shared(),
+        })
     }
     fn density<F>(
         stdev: f64,
@@ -53,79 +70,37 @@ impl Index {
         y: &ArrayView1<i32>,
         predicat: F,
         center: ArrayView1<f64>,
-    ) -> usize
+        metric: &Metric,
+    ) -> Result<usize, CalcError>
     where
         F: Fn(usize) -> bool,
     {
         let mut retval: usize = 0;
         for (row, c) in zip(x.rows(), y) {
             if predicat(*c as usize) {
-                let dist = (&row - &center).pow2().sum().sqrt();
+                let dist = metric.distance(row, center)?;
                 retval += (dist <= stdev) as usize;
             }
         }
-        retval
+        Ok(retval)
     }
 }
 
-pub struct Node<'a> {
-    index: Index,
-    raw_data: Option<Result<(ArrayView2<'a, f64>, ArrayView1<'a, i32>), CalcError>>,
-    scat: Option<Result<(f64, ArcArray1<f64>, f64), CalcError>>,
-    clusters_centroids: Option<Result<ArcArray2<f64>, CalcError>>,
-    sender: Sender<'a, SDBWIndexValue>,
-}
-
-impl<'a> Node<'a> {
-    fn process_when_ready(&mut self) {
-        if let (Some(scat), Some(clusters_centroids), Some(raw_data)) = (
-            self.scat.as_ref(),
-            self.clusters_centroids.as_ref(),
-            self.raw_data.as_ref(),
-        ) {
-            let res = match scat.combine(clusters_centroids).combine(raw_data) {
-                Ok((((val, centroid_vars, _), cls_ctrds), (x, y))) => self
-                    .index
-                    .compute(val, &centroid_vars.view(), x, y, &cls_ctrds.view())
-                    .map(|val| SDBWIndexValue { val }),
-                Err(err) => Err(err),
-            };
-            self.sender.send_to_subscribers(res);
-            self.scat = None;
-            self.clusters_centroids = None;
-        }
-    }
-    pub fn new(sender: Sender<'a, SDBWIndexValue>) -> Self {
-        Self {
-            index: Index,
-            raw_data: None,
-            scat: None,
-            clusters_centroids: None,
-            sender,
-        }
-    }
-}
-
-impl<'a> Subscriber<(ArrayView2<'a, f64>, ArrayView1<'a, i32>)> for Node<'a> {
-    fn recieve_data(
-        &mut self,
-        data: Result<(ArrayView2<'a, f64>, ArrayView1<'a, i32>), CalcError>,
-    ) {
-        self.raw_data = Some(data);
-        self.process_when_ready();
-    }
-}
-impl<'a> Subscriber<ScatValue> for Node<'a> {
-    fn recieve_data(&mut self, data: Result<ScatValue, CalcError>) {
-        self.scat = Some(data.map(|v| (v.val, v.clusters_vars, v.var)));
-        self.process_when_ready();
-    }
-}
-impl<'a> Subscriber<ClustersCentroidsValue> for Node<'a> {
-    fn recieve_data(&mut self, data: Result<ClustersCentroidsValue, CalcError>) {
-        self.clusters_centroids = Some(data.map(|v| v.val));
-        self.process_when_ready();
-    }
+pub fn node() -> Node<SDBWIndexValue> {
+    Node::new(
+        "sdbw",
+        |scope, (scat, centroids, metric): (ScatValue, ClustersCentroidsValue, Metric)| {
+            let (x, y) = scope.raw_data()?;
+            Index.compute(
+                &scat.val,
+                &scat.clusters_vars.view(),
+                &x,
+                &y,
+                &centroids.val.view(),
+                &metric,
+            )
+        },
+    )
 }
 // use super::*;
 // use std::ops::AddAssign;
@@ -1,57 +1,76 @@
 use crate::calc_error::CalcError;
-use crate::sender::{Sender, Subscriber};
-use ndarray::{Array1, ArrayView1, ArrayView2};
+use crate::dataflow::{Node, Product};
+use crate::indexes::helpers::distances::{condensed_index, DistancesValue};
+use ndarray::{ArcArray1, Array1, ArrayView1};
+use rayon::prelude::*;
 use std::iter::zip;
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Debug)]
 pub struct SilhouetteIndexValue {
     pub val: f64,
+    // s(i) of every observation
+    pub samples: ArcArray1<f64>,
+    // mean s(i) of every cluster, NaN for labels without observations
+    pub clusters: ArcArray1<f64>,
+    // the other cluster with the smallest mean distance to every observation
+    pub nearest: ArcArray1<i32>,
 }
+impl Product for SilhouetteIndexValue {}
 #[derive(Default)]
 pub struct Index;
 
 impl Index {
-    pub fn compute(&self, x: &ArrayView2<f64>, y: &ArrayView1<i32>) -> Result<f64, CalcError> {
+    pub fn compute(
+        &self,
+        y: &ArrayView1<i32>,
+        distances: &ArrayView1<f64>,
+    ) -> Result<SilhouetteIndexValue, CalcError> {
+        let n = y.len();
         let q = *y.iter().max().ok_or("Cant get numb of clusters")? as usize + 1;
-        let mut s: Vec<Vec<f64>> = Vec::new();
-        s.resize(q, Vec::default());
-        for (row1, c1) in zip(x.rows(), y) {
-            let mut d: Vec<Vec<f64>> = Vec::new();
-            d.resize(q, Vec::default());
-            for (row2, c2) in zip(x.rows(), y) {
-                let dist = (&row2 - &row1).pow2().sum().sqrt();
-                if row1 != row2 {
-                    d.get_mut(*c2 as usize).ok_or("Cant get val")?.push(dist);
-                }
-            }
-            let mut d = d
-                .into_iter()
-                .enumerate()
-                .map(|(i, v)| {
-                    if i == *c1 as usize {
-                        Array1::from_vec(v).mean()
-                    } else {
-                        Some(v.iter().sum::<f64>() / (v.len() - 1) as f64)
+        let (samples, nearest): (Vec<f64>, Vec<i32>) = (0..n)
+            .into_par_iter()
+            .map(|i| {
+                let c1 = y[i] as usize;
+                let mut sums = vec![0.; q];
+                let mut counts = vec![0usize; q];
+                for (j, c2) in y.iter().enumerate() {
+                    if i != j {
+                        sums[*c2 as usize] += distances[condensed_index(n, i, j)];
+                        counts[*c2 as usize] += 1;
                     }
-                })
-                .collect::<Option<Vec<f64>>>()
-                .ok_or("Cant calc mean")?;
-            let a = d.remove(*c1 as usize);
-            let b = d
-                .into_iter()
-                .min_by(|a, b| a.total_cmp(b))
-                .ok_or("Cant calc min")?;
-            s.get_mut(*c1 as usize)
-                .ok_or("Cant get val")?
-                .push((b - a) / b.max(a));
-        }
-        let s = s
+                }
+                let (nearest, b) = (0..q)
+                    .filter(|c| *c != c1 && counts[*c] > 0)
+                    .map(|c| (c, sums[c] / counts[c] as f64))
+                    .min_by(|a, b| a.1.total_cmp(&b.1))
+                    .ok_or("Cant calc min")?;
+                // observations alone in their cluster get 0
+                if counts[c1] == 0 {
+                    return Ok((0., nearest as i32));
+                }
+                let a = sums[c1] / counts[c1] as f64;
+                Ok(((b - a) / b.max(a), nearest as i32))
+            })
+            .collect::<Result<Vec<(f64, i32)>, CalcError>>()?
             .into_iter()
-            .map(|v| Array1::from_vec(v).mean())
-            .collect::<Option<Vec<f64>>>()
-            .ok_or("Cant calc mean")?;
-        Array1::from_vec(s)
-            .mean()
-            .ok_or("Cant calc res mean".into())
+            .unzip();
+
+        let mut sums = vec![0.; q];
+        let mut counts = vec![0usize; q];
+        for (s, c) in zip(&samples, y) {
+            sums[*c as usize] += s;
+            counts[*c as usize] += 1;
+        }
+        let clusters = zip(sums, counts)
+            .map(|(sum, count)| sum / count as f64)
+            .collect::<Array1<f64>>();
+        let samples = Array1::from_vec(samples);
+        let val = samples.mean().ok_or("Cant calc res mean")?;
+        Ok(SilhouetteIndexValue {
+            val,
+            samples: samples.into_shared(),
+            clusters: clusters.into_shared(),
+            nearest: Array1::from_vec(nearest).into_shared(),
+        })
 
         // let mut temp: Vec<f64> = Vec::with_capacity(clusters.keys().len() - 1);
         // let mut stor: Vec<f64> = Vec::with_capacity(x.nrows());
@@ -93,32 +112,9 @@ impl Index {
     }
 }
 
-pub struct Node<'a> {
-    index: Index,
-    sender: Sender<'a, SilhouetteIndexValue>,
-}
-
-impl<'a> Node<'a> {
-    pub fn new(sender: Sender<'a, SilhouetteIndexValue>) -> Self {
-        Self {
-            index: Index::default(),
-            sender,
-        }
-    }
-}
-impl<'a> Subscriber<(ArrayView2<'a, f64>, ArrayView1<'a, i32>)> for Node<'a> {
-    fn recieve_data(
-        &mut self,
-        data: Result<(ArrayView2<'a, f64>, ArrayView1<'a, i32>), CalcError>,
-    ) {
-        let res = match data.as_ref() {
-            Ok((x, y)) => self
-                .index
-                .compute(x, y)
-                .map(|val| SilhouetteIndexValue { val }),
-
-            Err(err) => Err(err.clone()),
-        };
-        self.sender.send_to_subscribers(res);
-    }
+pub fn node() -> Node<SilhouetteIndexValue> {
+    Node::new("silhouette", |scope, (distances,): (DistancesValue,)| {
+        let y = scope.labels()?;
+        Index.compute(&y, &distances.val.view())
+    })
 }
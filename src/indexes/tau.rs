@@ -1,12 +1,16 @@
-use crate::calc_error::{CalcError, CombineErrors};
-use crate::sender::{Sender, Subscriber};
+use super::helpers::{
+    pairs_and_distances::PairsAndDistancesValue, s_plus_and_minus::SPlusAndMinusValue,
+};
+use crate::calc_error::CalcError;
+use crate::dataflow::{Node, Product};
 use core::f64;
-use ndarray::{ArcArray1, ArrayView1};
+use ndarray::ArrayView1;
 
 #[derive(Clone, Copy, Debug)]
 pub struct TauIndexValue {
     pub val: f64,
 }
+impl Product for TauIndexValue {}
 #[derive(Default)]
 pub struct Index;
 
@@ -20,7 +24,11 @@ impl Index {
     ) -> Result<f64, CalcError> {
         let nt = pairs_in_the_same_cluster.len() as f64;
         let temp = nt * (nt - 1.) / 2.;
-        let value = (s_plus - s_minus) as f64 / (temp * (temp - ties as f64)).sqrt();
+        let denominator = (temp * (temp - ties as f64)).sqrt();
+        if denominator.is_nan() || denominator == 0. {
+            return Err("Tau index is undefined when all distances are tied".into());
+        }
+        let value = (s_plus as f64 - s_minus as f64) / denominator;
         Ok(value)
 
         // let nw = pairs_in_the_same_cluster
@@ -63,50 +71,13 @@ impl Index {
     }
 }
 
-pub struct Node<'a> {
-    index: Index,
-    s_plus_and_minus: Option<Result<(usize, usize, usize), CalcError>>,
-    pairs_and_distances: Option<Result<(ArcArray1<i8>, ArcArray1<f64>), CalcError>>,
-    sender: Sender<'a, TauIndexValue>,
-}
-
-impl<'a> Node<'a> {
-    fn process_when_ready(&mut self) {
-        if let (Some(s_plus_and_minus), Some(pairs_and_distances)) = (
-            self.s_plus_and_minus.as_ref(),
-            self.pairs_and_distances.as_ref(),
-        ) {
-            let res = match s_plus_and_minus.combine(pairs_and_distances) {
-                Ok(((s_plus, s_minus, ties), (pairs, _))) => self
-                    .index
-                    .compute(&pairs.view(), *s_plus, *s_minus, *ties)
-                    .map(|val| TauIndexValue { val }),
-                Err(err) => Err(err),
-            };
-            self.sender.send_to_subscribers(res);
-            self.s_plus_and_minus = None;
-            self.pairs_and_distances = None;
-        }
-    }
-    pub fn new(sender: Sender<'a, TauIndexValue>) -> Self {
-        Self {
-            index: Index,
-            s_plus_and_minus: None,
-            pairs_and_distances: None,
-            sender,
-        }
-    }
-}
-
-impl<'a> Subscriber<(usize, usize, usize)> for Node<'a> {
-    fn recieve_data(&mut self, data: Result<(usize, usize, usize), CalcError>) {
-        self.s_plus_and_minus = Some(data);
-        self.process_when_ready();
-    }
-}
-impl<'a> Subscriber<(ArcArray1<i8>, ArcArray1<f64>)> for Node<'a> {
-    fn recieve_data(&mut self, data: Result<(ArcArray1<i8>, ArcArray1<f64>), CalcError>) {
-        self.pairs_and_distances = Some(data);
-        self.process_when_ready();
-    }
+pub fn node() -> Node<TauIndexValue> {
+    Node::new(
+        "tau",
+        |_, (s, pairs): (SPlusAndMinusValue, PairsAndDistancesValue)| {
+            Index
+                .compute(&pairs.pairs.view(), s.s_plus, s.s_minus, s.ties)
+                .map(|val| TauIndexValue { val })
+        },
+    )
 }
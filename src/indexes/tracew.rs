@@ -1,12 +1,13 @@
 use super::helpers::within_group_dispercion::WGDValue;
 use crate::calc_error::CalcError;
-use crate::sender::{Sender, Subscriber};
+use crate::dataflow::{Node, Product};
 use ndarray::ArrayView2;
 
 #[derive(Clone, Copy, Debug)]
 pub struct TracewIndexValue {
     pub val: f64,
 }
+impl Product for TracewIndexValue {}
 #[derive(Default)]
 pub struct Index;
 impl Index {
@@ -14,29 +15,10 @@ impl Index {
         Ok(wg.diag().sum())
     }
 }
-pub struct Node<'a> {
-    index: Index,
-    sender: Sender<'a, TracewIndexValue>,
-}
-
-impl<'a> Node<'a> {
-    pub fn new(sender: Sender<'a, TracewIndexValue>) -> Self {
-        Self {
-            index: Index,
-            sender,
-        }
-    }
-}
-
-impl<'a> Subscriber<WGDValue> for Node<'a> {
-    fn recieve_data(&mut self, data: Result<WGDValue, CalcError>) {
-        let res = match data.map(|v| v.val) {
-            Ok(wg) => self
-                .index
-                .compute(&wg.view())
-                .map(|val| TracewIndexValue { val }),
-            Err(err) => Err(err),
-        };
-        self.sender.send_to_subscribers(res);
-    }
+pub fn node() -> Node<TracewIndexValue> {
+    Node::new("tracew", |_, (wg,): (WGDValue,)| {
+        Index
+            .compute(&wg.val.view())
+            .map(|val| TracewIndexValue { val })
+    })
 }
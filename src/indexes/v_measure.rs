@@ -0,0 +1,44 @@
+use crate::calc_error::CalcError;
+use crate::dataflow::{Node, Product};
+use ndarray::{ArrayView2, Axis};
+
+use super::helpers::contingency::{entropy, mutual_information, ContingencyValue};
+
+#[derive(Clone, Copy, Debug)]
+pub struct VMeasureIndexValue {
+    // harmonic mean of homogeneity and completeness
+    pub val: f64,
+    // every cluster contains members of a single class
+    pub homogeneity: f64,
+    // every class is assigned to a single cluster
+    pub completeness: f64,
+}
+impl Product for VMeasureIndexValue {}
+#[derive(Default)]
+pub struct Index;
+impl Index {
+    fn compute(&self, contingency: &ArrayView2<usize>) -> Result<(f64, f64, f64), CalcError> {
+        let h_true = entropy(&contingency.sum_axis(Axis(1)));
+        let h_pred = entropy(&contingency.sum_axis(Axis(0)));
+        let mi = mutual_information(contingency);
+        let homogeneity = if h_true == 0. { 1. } else { mi / h_true };
+        let completeness = if h_pred == 0. { 1. } else { mi / h_pred };
+        let v_measure = if homogeneity + completeness == 0. {
+            0.
+        } else {
+            2. * homogeneity * completeness / (homogeneity + completeness)
+        };
+        Ok((v_measure, homogeneity, completeness))
+    }
+}
+pub fn node() -> Node<VMeasureIndexValue> {
+    Node::new("v_measure", |_, (contingency,): (ContingencyValue,)| {
+        Index
+            .compute(&contingency.val.view())
+            .map(|(val, homogeneity, completeness)| VMeasureIndexValue {
+                val,
+                homogeneity,
+                completeness,
+            })
+    })
+}
@@ -0,0 +1,272 @@
+use crate::calc_error::CalcError;
+use crate::index_tree::IndexTreeReturnValue;
+use crate::indexes::helpers::distances::{condensed_index, DistanceMatrix};
+use ndarray::{Array1, ArrayView1, ArrayView2, Axis, CowArray, Ix2};
+
+// how observations with a negative label (noise, as returned by DBSCAN) are evaluated
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum NoisePolicy {
+    // noise is left out of every index
+    #[default]
+    Exclude,
+    // every noise observation forms a cluster of its own
+    Singletons,
+    // all noise observations form one cluster
+    Cluster,
+}
+
+// a partition relabelled to 0..q-1, as the indexes expect, together with the features or
+// distances of the observations it keeps. Clusters keep the order of their original ids,
+// clusters made of noise come last
+pub struct NormalizedPartition<'a> {
+    x: Option<CowArray<'a, f64, Ix2>>,
+    distances: Option<DistanceMatrix<'a>>,
+    // distances between the kept observations, when noise was left out
+    selected_distances: Option<Array1<f64>>,
+    labels: Array1<i32>,
+    previous: Option<Array1<i32>>,
+    next: Option<Array1<i32>>,
+    reference: Option<Array1<i32>>,
+    weights: Option<Array1<f64>>,
+    // original id of every cluster, -1 for clusters made of noise
+    cluster_ids: Array1<i32>,
+    // observations kept, None when none was left out
+    kept: Option<Vec<usize>>,
+    // number of observations before noise was left out
+    observations: usize,
+    policy: NoisePolicy,
+}
+impl<'a> NormalizedPartition<'a> {
+    pub fn new(
+        x: ArrayView2<'a, f64>,
+        y: ArrayView1<i32>,
+        policy: NoisePolicy,
+    ) -> Result<Self, CalcError> {
+        if x.nrows() != y.len() {
+            return Err(CalcError::shape(
+                "Number of labels doesnt match number of observations",
+            ));
+        }
+        let mut partition = Self::from_labels(y, policy);
+        partition.x = Some(match &partition.kept {
+            Some(kept) => CowArray::from(x.select(Axis(0), kept)),
+            None => CowArray::from(x),
+        });
+        Ok(partition)
+    }
+
+    pub fn from_distances(
+        distances: DistanceMatrix<'a>,
+        y: ArrayView1<i32>,
+        policy: NoisePolicy,
+    ) -> Result<Self, CalcError> {
+        let n = y.len();
+        let valid = match &distances {
+            DistanceMatrix::Condensed(d) => d.len() == n * n.saturating_sub(1) / 2,
+            DistanceMatrix::Square(d) => d.dim() == (n, n),
+        };
+        if !valid {
+            return Err(CalcError::shape(
+                "Shape of distance matrix doesnt match number of observations",
+            ));
+        }
+        let mut partition = Self::from_labels(y, policy);
+        partition.selected_distances = partition.kept.as_ref().map(|kept| {
+            Array1::from_iter(kept.iter().enumerate().flat_map(|(a, i)| {
+                kept[a + 1..].iter().map(|j| match &distances {
+                    DistanceMatrix::Condensed(d) => d[condensed_index(n, *i, *j)],
+                    DistanceMatrix::Square(d) => d[[*i, *j]],
+                })
+            }))
+        });
+        partition.distances = Some(distances);
+        Ok(partition)
+    }
+
+    fn from_labels(y: ArrayView1<i32>, policy: NoisePolicy) -> Self {
+        let ids = cluster_ids(y);
+        let noise = y.iter().filter(|c| **c < 0).count();
+
+        let (labels, kept, noise_clusters) = match policy {
+            NoisePolicy::Exclude => {
+                let kept = (noise > 0)
+                    .then(|| (0..y.len()).filter(|i| y[*i] >= 0).collect::<Vec<usize>>());
+                let labels = match &kept {
+                    Some(kept) => relabel(y.select(Axis(0), kept).view(), &ids, policy),
+                    None => relabel(y, &ids, policy),
+                };
+                (labels, kept, 0)
+            }
+            NoisePolicy::Singletons => (relabel(y, &ids, policy), None, noise),
+            NoisePolicy::Cluster => (relabel(y, &ids, policy), None, noise.min(1)),
+        };
+        let mut cluster_ids = ids;
+        cluster_ids.resize(cluster_ids.len() + noise_clusters, -1);
+        Self {
+            x: None,
+            distances: None,
+            selected_distances: None,
+            labels,
+            previous: None,
+            next: None,
+            reference: None,
+            weights: None,
+            cluster_ids: Array1::from_vec(cluster_ids),
+            kept,
+            observations: y.len(),
+            policy,
+        }
+    }
+
+    // neighbouring partitions are restricted to the kept observations and relabelled with
+    // the same noise policy. Their noise can only be left out along with the observations
+    // of the current partition, so when it is excluded there every noise observation the
+    // current partition keeps forms a cluster of its own
+    pub fn with_adjacent(
+        mut self,
+        previous: Option<ArrayView1<i32>>,
+        next: Option<ArrayView1<i32>>,
+    ) -> Result<Self, CalcError> {
+        self.previous = previous
+            .map(|y| self.select(y, "previous").map(|y| self.adjacent(y)))
+            .transpose()?;
+        self.next = next
+            .map(|y| self.select(y, "next").map(|y| self.adjacent(y)))
+            .transpose()?;
+        Ok(self)
+    }
+
+    fn adjacent(&self, y: Array1<i32>) -> Array1<i32> {
+        let policy = match self.policy {
+            NoisePolicy::Exclude => NoisePolicy::Singletons,
+            policy => policy,
+        };
+        relabel(y.view(), &cluster_ids(y.view()), policy)
+    }
+
+    // the reference partition is only restricted to the kept observations,
+    // external indexes dont depend on the ids
+    pub fn with_reference(mut self, reference: ArrayView1<i32>) -> Result<Self, CalcError> {
+        self.reference = Some(self.select(reference, "reference")?);
+        Ok(self)
+    }
+
+    // observation weights, noise left out takes its weight along
+    pub fn with_weights(mut self, weights: ArrayView1<f64>) -> Result<Self, CalcError> {
+        if weights.len() != self.observations {
+            return Err(CalcError::shape(
+                "Number of weights doesnt match number of observations",
+            ));
+        }
+        self.weights = Some(match &self.kept {
+            Some(kept) => weights.select(Axis(0), kept),
+            None => weights.to_owned(),
+        });
+        Ok(self)
+    }
+
+    fn select(&self, y: ArrayView1<i32>, name: &str) -> Result<Array1<i32>, CalcError> {
+        if y.len() != self.observations {
+            return Err(CalcError::shape(format!(
+                "Length of {name} partition doesnt match number of observations"
+            )));
+        }
+        Ok(match &self.kept {
+            Some(kept) => y.select(Axis(0), kept),
+            None => y.to_owned(),
+        })
+    }
+
+    pub fn features(&self) -> Option<ArrayView2<'_, f64>> {
+        self.x.as_ref().map(|x| x.view())
+    }
+    pub fn distances(&self) -> Option<DistanceMatrix<'_>> {
+        match &self.selected_distances {
+            Some(d) => Some(DistanceMatrix::Condensed(d.view())),
+            None => self.distances.as_ref().map(|d| match d {
+                DistanceMatrix::Condensed(d) => DistanceMatrix::Condensed(d.view()),
+                DistanceMatrix::Square(d) => DistanceMatrix::Square(d.view()),
+            }),
+        }
+    }
+    pub fn labels(&self) -> ArrayView1<'_, i32> {
+        self.labels.view()
+    }
+    pub fn previous(&self) -> Option<ArrayView1<'_, i32>> {
+        self.previous.as_ref().map(|y| y.view())
+    }
+    pub fn next(&self) -> Option<ArrayView1<'_, i32>> {
+        self.next.as_ref().map(|y| y.view())
+    }
+    pub fn reference(&self) -> Option<ArrayView1<'_, i32>> {
+        self.reference.as_ref().map(|y| y.view())
+    }
+    pub fn weights(&self) -> Option<ArrayView1<'_, f64>> {
+        self.weights.as_ref().map(|w| w.view())
+    }
+    pub fn cluster_ids(&self) -> ArrayView1<'_, i32> {
+        self.cluster_ids.view()
+    }
+    // no noise observation was left out
+    pub fn keeps_all(&self) -> bool {
+        self.kept.is_none()
+    }
+
+    // maps the cluster indexes in per-cluster outputs back to the original ids, per-observation
+    // outputs are spread back over all observations, NaN (or -1) for the ones left out.
+    // Arrays with one entry (or row and column) per cluster, like the silhouette of every
+    // cluster or the Dunn, SD and SDbw matrices, keep their order, entry i belongs to the
+    // cluster with id `cluster_ids[i]`
+    pub fn restore(&self, mut res: IndexTreeReturnValue) -> IndexTreeReturnValue {
+        let original = |c: &i32| self.cluster_ids.get(*c as usize).copied().unwrap_or(-1);
+        if let Some(Ok(silhouette)) = res.silhouette.as_mut() {
+            let mut nearest = silhouette.nearest.map(original);
+            // the sampled estimate carries no per-observation values
+            if let Some(kept) = self.kept.as_ref().filter(|k| k.len() == nearest.len()) {
+                let mut samples = Array1::from_elem(self.observations, f64::NAN);
+                let mut spread = Array1::from_elem(self.observations, -1);
+                for (k, i) in kept.iter().enumerate() {
+                    samples[*i] = silhouette.samples[k];
+                    spread[*i] = nearest[k];
+                }
+                silhouette.samples = samples.into_shared();
+                nearest = spread;
+            }
+            silhouette.nearest = nearest.into_shared();
+        }
+        if let Some(Ok(davies_bouldin)) = res.davies_bouldin.as_mut() {
+            davies_bouldin.worst = davies_bouldin.worst.map(original).into_shared();
+        }
+        res.cluster_ids = Some(self.cluster_ids.to_shared());
+        res
+    }
+}
+
+// sorted ids of the clusters of a partition, noise left aside
+fn cluster_ids(y: ArrayView1<i32>) -> Vec<i32> {
+    let mut ids = y.iter().copied().filter(|c| *c >= 0).collect::<Vec<i32>>();
+    ids.sort_unstable();
+    ids.dedup();
+    ids
+}
+
+// maps the ids to 0..q-1 in their order, the clusters made of noise get the labels after
+// them. Noise is expected to be filtered out already when it is excluded
+fn relabel(y: ArrayView1<i32>, ids: &[i32], policy: NoisePolicy) -> Array1<i32> {
+    let q = ids.len() as i32;
+    let cluster = |c: &i32| ids.binary_search(c).unwrap_or_default() as i32;
+    match policy {
+        NoisePolicy::Exclude => y.map(cluster),
+        NoisePolicy::Singletons => {
+            let mut next = q;
+            y.map(|c| {
+                if *c >= 0 {
+                    return cluster(c);
+                }
+                next += 1;
+                next - 1
+            })
+        }
+        NoisePolicy::Cluster => y.map(|c| if *c < 0 { q } else { cluster(c) }),
+    }
+}
@@ -0,0 +1,90 @@
+use crate::calc_error::CalcError;
+use crate::dataflow::{Node, Product};
+use ndarray::{ArcArray2, ArrayView1, ArrayView2, Axis};
+use ndarray_linalg::Inverse;
+use std::{fmt, sync::Arc};
+
+pub type DistanceFn =
+    dyn Fn(ArrayView1<f64>, ArrayView1<f64>) -> Result<f64, CalcError> + Send + Sync;
+
+// distance used by every node that measures distances between observations or centroids
+#[derive(Clone, Default)]
+pub enum Metric {
+    #[default]
+    Euclidean,
+    SqEuclidean,
+    Manhattan,
+    Chebyshev,
+    Minkowski(f64),
+    Cosine,
+    Correlation,
+    // inverse covariance matrix, estimated from the features when not given
+    Mahalanobis(Option<ArcArray2<f64>>),
+    Custom(Arc<DistanceFn>),
+}
+
+impl fmt::Debug for Metric {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Euclidean => write!(f, "Euclidean"),
+            Self::SqEuclidean => write!(f, "SqEuclidean"),
+            Self::Manhattan => write!(f, "Manhattan"),
+            Self::Chebyshev => write!(f, "Chebyshev"),
+            Self::Minkowski(p) => write!(f, "Minkowski({p})"),
+            Self::Cosine => write!(f, "Cosine"),
+            Self::Correlation => write!(f, "Correlation"),
+            Self::Mahalanobis(vi) => write!(f, "Mahalanobis({vi:?})"),
+            Self::Custom(_) => write!(f, "Custom"),
+        }
+    }
+}
+
+impl Metric {
+    pub fn distance(&self, a: ArrayView1<f64>, b: ArrayView1<f64>) -> Result<f64, CalcError> {
+        let diff = &a - &b;
+        let val = match self {
+            Self::Euclidean => diff.pow2().sum().sqrt(),
+            Self::SqEuclidean => diff.pow2().sum(),
+            Self::Manhattan => diff.abs().sum(),
+            Self::Chebyshev => diff.fold(0., |acc, v| v.abs().max(acc)),
+            Self::Minkowski(p) => diff.abs().powf(*p).sum().powf(1. / p),
+            Self::Cosine => 1. - a.dot(&b) / (a.dot(&a) * b.dot(&b)).sqrt(),
+            Self::Correlation => {
+                let a = &a - a.mean().ok_or("Cant calc mean")?;
+                let b = &b - b.mean().ok_or("Cant calc mean")?;
+                1. - a.dot(&b) / (a.dot(&a) * b.dot(&b)).sqrt()
+            }
+            Self::Mahalanobis(Some(vi)) => diff.dot(&vi.dot(&diff)).sqrt(),
+            Self::Mahalanobis(None) => {
+                return Err("Mahalanobis metric requires inverse covariance matrix".into())
+            }
+            Self::Custom(f) => return f(a, b),
+        };
+        Ok(val)
+    }
+
+    // fills in everything the metric estimates from the features
+    pub fn fit(&self, x: &ArrayView2<f64>) -> Result<Self, CalcError> {
+        match self {
+            Self::Mahalanobis(None) => {
+                let mean = x.mean_axis(Axis(0)).ok_or("Cant calc mean")?;
+                let centered = x - &mean;
+                let cov = centered.t().dot(&centered) / (x.nrows() as f64 - 1.);
+                let vi = cov.inv().map_err(|e| CalcError::from(format!("{e:?}")))?;
+                Ok(Self::Mahalanobis(Some(vi.into_shared())))
+            }
+            Self::Minkowski(p) if *p < 1. => Err("Minkowski metric requires p >= 1".into()),
+            _ => Ok(self.clone()),
+        }
+    }
+}
+impl Product for Metric {}
+
+// the metric fitted to the features, shared by the neighbouring partitions
+pub fn node() -> Node<Metric> {
+    Node::new("metric", |scope, ()| match scope.features() {
+        Ok(x) => scope.metric().fit(&x),
+        Err(_) => Ok(scope.metric().clone()),
+    })
+    .label_independent()
+}
@@ -0,0 +1,284 @@
+use crate::calc_error::CalcError;
+use crate::index_tree::{IndexTreeBuilder, IndexTreeReturnValue};
+use crate::labels::{NoisePolicy, NormalizedPartition};
+use itertools::Itertools;
+use ndarray::{Array1, ArrayView2};
+use std::iter::zip;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DecisionRule {
+    Max,
+    Min,
+    // max |v(k) - v(k-1)|
+    MaxDifference,
+    // max/min of (v(k+1) - v(k)) - (v(k) - v(k-1))
+    MaxSecondDifference,
+    MinSecondDifference,
+    // k - 1 for the first k where the index drops below 1
+    BeforeFirstBelowOne,
+    // smallest k with gap(k) >= gap(k+1) - s(k+1), needs the standard errors of the
+    // gap values and is applied by `select_gap`
+    Gap,
+    // smallest k for which the test of the split made by partition k + 1 keeps the parent
+    // as a single cluster, needs the outcome of the tests and is applied by `select_first_accepted`
+    FirstAccepted,
+}
+
+impl DecisionRule {
+    // rules used by NbClust for each index
+    pub fn for_index(name: &str) -> Option<Self> {
+        let rule = match name {
+            "calinski_harabasz" | "dunn" | "silhouette" | "tau" | "gamma" | "ptbiserial"
+            | "ratkowsky" | "kl" | "ccc" => Self::Max,
+            "davies_bouldin" | "c_index" | "gplus" | "mcclain" | "sd" | "sdbw" => Self::Min,
+            "ball_hall" | "scott" | "friedman" | "trcovw" | "hartigan" => Self::MaxDifference,
+            "mariott" | "tracew" | "hubert" | "dindex" => Self::MaxSecondDifference,
+            "rubin" => Self::MinSecondDifference,
+            "frey" => Self::BeforeFirstBelowOne,
+            "gap" => Self::Gap,
+            "duda" | "pseudot2" | "beale" => Self::FirstAccepted,
+            _ => return None,
+        };
+        Some(rule)
+    }
+
+    // `ks` must be sorted, missing values are passed as NaN and never selected
+    pub fn select(&self, ks: &[usize], values: &[f64]) -> Result<usize, CalcError> {
+        if ks.len() != values.len() {
+            return Err(CalcError::shape(
+                "Number of values doesnt match number of partitions",
+            ));
+        }
+        let n = values.len();
+        let candidates: Vec<(usize, f64)> = match self {
+            Self::Gap => {
+                return Err(CalcError::missing_input(
+                    "Gap rule requires the standard errors of the gap values",
+                ))
+            }
+            Self::FirstAccepted => {
+                return Err(CalcError::missing_input(
+                    "Rule requires the outcome of the split tests",
+                ))
+            }
+            Self::Max | Self::Min | Self::BeforeFirstBelowOne => {
+                ks.iter().copied().zip(values.iter().copied()).collect()
+            }
+            Self::MaxDifference => (1..n)
+                .map(|i| (ks[i], (values[i] - values[i - 1]).abs()))
+                .collect(),
+            Self::MaxSecondDifference | Self::MinSecondDifference => {
+                ks.iter().copied().zip(second_differences(values)).collect()
+            }
+        };
+        let mut candidates = candidates.into_iter().filter(|(_, v)| v.is_finite());
+        let best = match self {
+            Self::Max | Self::MaxDifference | Self::MaxSecondDifference => {
+                candidates.max_by(|(_, a), (_, b)| a.total_cmp(b))
+            }
+            Self::Min | Self::MinSecondDifference => {
+                candidates.min_by(|(_, a), (_, b)| a.total_cmp(b))
+            }
+            Self::BeforeFirstBelowOne => candidates
+                .find(|(_, v)| *v < 1.)
+                .map(|(k, v)| (k.saturating_sub(1), v)),
+            Self::Gap | Self::FirstAccepted => None,
+        };
+        best.map(|(k, _)| k)
+            .ok_or("Not enough partitions to apply decision rule".into())
+    }
+    // Tibshirani's rule, `s` holds the standard error s_k of every gap value
+    pub fn select_gap(ks: &[usize], values: &[f64], s: &[f64]) -> Result<usize, CalcError> {
+        if ks.len() != values.len() || ks.len() != s.len() {
+            return Err(CalcError::shape(
+                "Number of values doesnt match number of partitions",
+            ));
+        }
+        (1..ks.len())
+            .filter(|i| ks[*i] == ks[i - 1] + 1)
+            .find(|i| values[i - 1] >= values[*i] - s[*i])
+            .map(|i| ks[i - 1])
+            .ok_or("Not enough partitions to apply decision rule".into())
+    }
+    // NbClust's rule for duda, pseudot2 and beale, `accepted` is false where the test failed
+    pub fn select_first_accepted(ks: &[usize], accepted: &[bool]) -> Result<usize, CalcError> {
+        if ks.len() != accepted.len() {
+            return Err(CalcError::shape(
+                "Number of values doesnt match number of partitions",
+            ));
+        }
+        zip(ks, accepted)
+            .find(|(_, accepted)| **accepted)
+            .map(|(k, _)| *k)
+            .ok_or("No partition is accepted by the split test".into())
+    }
+}
+
+// (v(k+1) - v(k)) - (v(k) - v(k-1)), NaN at both ends of the curve
+pub fn second_differences(values: &[f64]) -> Vec<f64> {
+    let n = values.len();
+    (0..n)
+        .map(|i| match (i.checked_sub(1), values.get(i + 1)) {
+            (Some(prev), Some(next)) => (next - values[i]) - (values[i] - values[prev]),
+            _ => f64::NAN,
+        })
+        .collect()
+}
+
+// values of a graphical index (Hubert, D-index) over the evaluated partitions
+#[cfg_attr(feature = "python", pyo3::pyclass(get_all))]
+#[derive(Clone, Debug)]
+pub struct IndexCurve {
+    pub ks: Vec<usize>,
+    pub values: Vec<f64>,
+    pub second_differences: Vec<f64>,
+}
+
+#[derive(Clone, Debug)]
+pub struct IndexChoice {
+    pub index: &'static str,
+    pub rule: DecisionRule,
+    pub best_k: Result<usize, CalcError>,
+}
+
+#[cfg_attr(feature = "python", pyo3::pyclass)]
+#[derive(Clone, Debug)]
+pub struct NumberOfClustersValue {
+    pub ks: Vec<usize>,
+    pub results: Vec<IndexTreeReturnValue>,
+    pub choices: Vec<IndexChoice>,
+    // (k, number of indexes voting for k), sorted by k
+    pub votes: Vec<(usize, usize)>,
+    pub best_k: Option<usize>,
+}
+
+impl NumberOfClustersValue {
+    pub fn from_results(ks: Vec<usize>, results: Vec<IndexTreeReturnValue>) -> Self {
+        let mut choices = Vec::new();
+        if let Some(first) = results.first() {
+            for (i, (index, value)) in first.scalars().into_iter().enumerate() {
+                let (Some(_), Some(rule)) = (value, DecisionRule::for_index(index)) else {
+                    continue;
+                };
+                let values = values(&results, i);
+                let best_k = match rule {
+                    DecisionRule::Gap => {
+                        DecisionRule::select_gap(&ks, &values, &gap_errors(&results))
+                    }
+                    DecisionRule::FirstAccepted => {
+                        DecisionRule::select_first_accepted(&ks, &accepted(&results, index))
+                    }
+                    _ => rule.select(&ks, &values),
+                };
+                choices.push(IndexChoice {
+                    index,
+                    rule,
+                    best_k,
+                });
+            }
+        }
+        let votes = choices
+            .iter()
+            .filter_map(|c| c.best_k.as_ref().ok().copied())
+            .counts()
+            .into_iter()
+            .sorted()
+            .collect::<Vec<(usize, usize)>>();
+        // ties are resolved in favour of the smaller k
+        let best_k = votes.iter().rev().max_by_key(|(_, n)| *n).map(|(k, _)| *k);
+        Self {
+            ks,
+            results,
+            choices,
+            votes,
+            best_k,
+        }
+    }
+    // missing or failed values are NaN
+    pub fn curve(&self, index: &str) -> Option<IndexCurve> {
+        let i = self
+            .results
+            .first()?
+            .scalars()
+            .into_iter()
+            .position(|(name, _)| name == index)?;
+        let values = values(&self.results, i);
+        Some(IndexCurve {
+            ks: self.ks.clone(),
+            second_differences: second_differences(&values),
+            values,
+        })
+    }
+}
+
+fn values(results: &[IndexTreeReturnValue], i: usize) -> Vec<f64> {
+    results
+        .iter()
+        .map(|r| match r.scalars().swap_remove(i).1 {
+            Some(Ok(v)) => v,
+            _ => f64::NAN,
+        })
+        .collect()
+}
+
+// s_k of every gap value, NaN where gap failed
+fn gap_errors(results: &[IndexTreeReturnValue]) -> Vec<f64> {
+    results
+        .iter()
+        .map(|r| match &r.gap {
+            Some(Ok(gap)) => gap.s_k,
+            _ => f64::NAN,
+        })
+        .collect()
+}
+
+// outcome of the split test of `index` for every partition
+fn accepted(results: &[IndexTreeReturnValue], index: &str) -> Vec<bool> {
+    results
+        .iter()
+        .map(|r| match index {
+            "duda" => matches!(&r.duda, Some(Ok(v)) if v.accepted()),
+            "pseudot2" => matches!(&r.pseudot2, Some(Ok(v)) if v.accepted()),
+            "beale" => matches!(&r.beale, Some(Ok(v)) if v.accepted()),
+            _ => false,
+        })
+        .collect()
+}
+
+// every labeling is normalized with the `noise` policy, see `IndexTree::compute_normalized`
+pub fn select_number_of_clusters<I, F>(
+    x: ArrayView2<f64>,
+    labelings: I,
+    noise: NoisePolicy,
+    configure: F,
+) -> Result<NumberOfClustersValue, CalcError>
+where
+    I: IntoIterator<Item = (usize, Array1<i32>)>,
+    F: for<'b> Fn(IndexTreeBuilder<'b>) -> IndexTreeBuilder<'b>,
+{
+    let labelings = labelings
+        .into_iter()
+        .sorted_by_key(|(k, _)| *k)
+        .collect::<Vec<(usize, Array1<i32>)>>();
+    if labelings.iter().any(|(_, y)| y.len() != x.nrows()) {
+        return Err(CalcError::shape(
+            "Labeling length doesnt match number of observations",
+        ));
+    }
+    let mut ks = Vec::with_capacity(labelings.len());
+    let mut results = Vec::with_capacity(labelings.len());
+    for (i, (k, y)) in labelings.iter().enumerate() {
+        let adjacent = |j: Option<usize>, adjacent_k: usize| {
+            j.and_then(|j| labelings.get(j))
+                .filter(|(k, _)| *k == adjacent_k)
+                .map(|(_, y)| y.view())
+        };
+        let previous = adjacent(i.checked_sub(1), k.wrapping_sub(1));
+        let next = adjacent(Some(i + 1), k + 1);
+        let data = NormalizedPartition::new(x, y.view(), noise)?.with_adjacent(previous, next)?;
+        let tree = configure(IndexTreeBuilder::default()).finish();
+        results.push(tree.compute_normalized(&data));
+        ks.push(*k);
+    }
+    Ok(NumberOfClustersValue::from_results(ks, results))
+}
@@ -0,0 +1,161 @@
+use crate::calc_error::CalcError;
+use crate::dataflow::{Key, Node, Product, Products, Side};
+use crate::indexes::helpers::{
+    between_group_dispercion::BGDValue, clusters_centroids::ClustersCentroidsValue,
+    counts::CountsValue, pairs_and_distances::PairsAndDistancesValue, scat::ScatValue,
+    total_dispercion::TDValue, within_group_dispercion::WGDValue,
+};
+use ndarray::{ArcArray1, ArcArray2, ArrayView1, ArrayView2};
+use rayon::prelude::*;
+use std::sync::Arc;
+
+// products of the helper nodes, custom indexes read them and callers can keep them
+// as outputs
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Helper {
+    Counts,
+    Centroids,
+    WithinGroupDispersion,
+    BetweenGroupDispersion,
+    TotalDispersion,
+    PairsAndDistances,
+    Scat,
+}
+impl Helper {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Helper::Counts => "counts",
+            Helper::Centroids => "centroids",
+            Helper::WithinGroupDispersion => "wgd",
+            Helper::BetweenGroupDispersion => "bgd",
+            Helper::TotalDispersion => "td",
+            Helper::PairsAndDistances => "pairs_and_distances",
+            Helper::Scat => "scat",
+        }
+    }
+    pub fn from_name(name: &str) -> Option<Self> {
+        let helper = match name {
+            "counts" => Helper::Counts,
+            "centroids" => Helper::Centroids,
+            "wgd" => Helper::WithinGroupDispersion,
+            "bgd" => Helper::BetweenGroupDispersion,
+            "td" => Helper::TotalDispersion,
+            "pairs_and_distances" => Helper::PairsAndDistances,
+            "scat" => Helper::Scat,
+            _ => return None,
+        };
+        Some(helper)
+    }
+    fn key(&self) -> Key {
+        match self {
+            Helper::Counts => Key::of::<CountsValue>(Side::Current),
+            Helper::Centroids => Key::of::<ClustersCentroidsValue>(Side::Current),
+            Helper::WithinGroupDispersion => Key::of::<WGDValue>(Side::Current),
+            Helper::BetweenGroupDispersion => Key::of::<BGDValue>(Side::Current),
+            Helper::TotalDispersion => Key::of::<TDValue>(Side::Current),
+            Helper::PairsAndDistances => Key::of::<PairsAndDistancesValue>(Side::Current),
+            Helper::Scat => Key::of::<ScatValue>(Side::Current),
+        }
+    }
+}
+
+// input of a custom index, the products it didnt ask for are `None`. Features are
+// `None` when the partition is evaluated from distances
+#[derive(Clone, Debug)]
+pub struct PluginInput<'a> {
+    pub features: Option<ArrayView2<'a, f64>>,
+    pub labels: ArrayView1<'a, i32>,
+    pub weights: Option<ArrayView1<'a, f64>>,
+    pub counts: Option<ArcArray1<f64>>,
+    pub centroids: Option<ArcArray2<f64>>,
+    pub wgd: Option<ArcArray2<f64>>,
+    pub bgd: Option<ArcArray2<f64>>,
+    pub td: Option<ArcArray2<f64>>,
+    // 1 for pairs of observations in the same cluster, aligned with `distances`
+    pub pairs: Option<ArcArray1<i8>>,
+    pub distances: Option<ArcArray1<f64>>,
+    pub scat: Option<f64>,
+}
+impl<'a> PluginInput<'a> {
+    // fills in the products of `helpers`, the first one that failed is returned instead
+    fn fetch(
+        mut self,
+        helpers: &[Helper],
+        products: &Products,
+        side: Side,
+    ) -> Result<Self, CalcError> {
+        for helper in helpers {
+            match helper {
+                Helper::Counts => self.counts = Some(products.get::<CountsValue>(side)?.val),
+                Helper::Centroids => {
+                    self.centroids = Some(products.get::<ClustersCentroidsValue>(side)?.val)
+                }
+                Helper::WithinGroupDispersion => {
+                    self.wgd = Some(products.get::<WGDValue>(side)?.val)
+                }
+                Helper::BetweenGroupDispersion => {
+                    self.bgd = Some(products.get::<BGDValue>(side)?.val)
+                }
+                Helper::TotalDispersion => self.td = Some(products.get::<TDValue>(side)?.val),
+                Helper::PairsAndDistances => {
+                    let value = products.get::<PairsAndDistancesValue>(side)?;
+                    self.pairs = Some(value.pairs);
+                    self.distances = Some(value.distances);
+                }
+                Helper::Scat => self.scat = Some(products.get::<ScatValue>(side)?.val),
+            }
+        }
+        Ok(self)
+    }
+}
+
+// an index defined outside the crate, it reuses the products of the helper nodes
+// shared with the built-in indexes
+pub trait IndexPlugin: Send + Sync {
+    fn name(&self) -> &str;
+    fn requires(&self) -> Vec<Helper>;
+    fn compute(&self, input: &PluginInput) -> Result<f64, CalcError>;
+}
+
+#[derive(Clone, Debug)]
+pub struct PluginsValue {
+    pub values: Vec<(String, Result<f64, CalcError>)>,
+}
+impl Product for PluginsValue {}
+
+// every custom index runs in the same node, the products one of them asks for are
+// computed once for all of them
+pub fn node(plugins: Vec<Arc<dyn IndexPlugin>>) -> Node<PluginsValue> {
+    let mut inputs = Vec::new();
+    for helper in plugins.iter().flat_map(|plugin| plugin.requires()) {
+        if !inputs.contains(&helper.key()) {
+            inputs.push(helper.key());
+        }
+    }
+    Node::dynamic("plugins", inputs, move |scope, products| {
+        let input = PluginInput {
+            features: scope.features().ok(),
+            labels: scope.labels()?,
+            weights: scope.weights()?,
+            counts: None,
+            centroids: None,
+            wgd: None,
+            bgd: None,
+            td: None,
+            pairs: None,
+            distances: None,
+            scat: None,
+        };
+        let values = plugins
+            .par_iter()
+            .map(|plugin| {
+                let value = input
+                    .clone()
+                    .fetch(&plugin.requires(), products, scope.side())
+                    .and_then(|input| plugin.compute(&input));
+                (plugin.name().to_string(), value)
+            })
+            .collect();
+        Ok(PluginsValue { values })
+    })
+}
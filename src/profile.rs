@@ -0,0 +1,68 @@
+use crate::dataflow::Side;
+use ndarray::{ArrayBase, Dimension, RawData};
+use std::fmt::Write;
+use std::time::Duration;
+
+// bytes held by the elements of an array
+pub fn array_memory<S: RawData, D: Dimension>(a: &ArrayBase<S, D>) -> usize {
+    a.len() * std::mem::size_of::<S::Elem>()
+}
+
+// one node run by a traced execution
+#[derive(Clone, Debug)]
+pub struct NodeTrace {
+    pub name: &'static str,
+    pub product: &'static str,
+    pub side: Side,
+    // index of the rayon worker, `None` when the node ran on the calling thread
+    pub thread: Option<usize>,
+    // since the execution started
+    pub start: Duration,
+    pub duration: Duration,
+    // estimate of the bytes held by the product, 0 when the node failed
+    pub memory: usize,
+    pub ok: bool,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Profile {
+    // in the order the nodes finished
+    pub nodes: Vec<NodeTrace>,
+    pub wall: Duration,
+}
+impl Profile {
+    // total time spent in the nodes with this name, a node may run for several partitions
+    pub fn time_of(&self, name: &str) -> Duration {
+        self.nodes
+            .iter()
+            .filter(|node| node.name == name)
+            .map(|node| node.duration)
+            .sum()
+    }
+    // trace event format read by chrome://tracing and Perfetto, one complete event per node
+    pub fn chrome_trace(&self) -> String {
+        let mut json = String::from("{\"traceEvents\":[");
+        for (i, node) in self.nodes.iter().enumerate() {
+            if i > 0 {
+                json.push(',');
+            }
+            // the calling thread gets its own row before the workers
+            let tid = node.thread.map_or(0, |t| t + 1);
+            let _ = write!(
+                json,
+                "{{\"name\":\"{}\",\"cat\":\"{:?}\",\"ph\":\"X\",\"ts\":{},\"dur\":{},\"pid\":1,\"tid\":{},\
+                 \"args\":{{\"product\":\"{}\",\"memory\":{},\"ok\":{}}}}}",
+                node.name,
+                node.side,
+                node.start.as_micros(),
+                node.duration.as_micros(),
+                tid,
+                node.product,
+                node.memory,
+                node.ok,
+            );
+        }
+        json.push_str("],\"displayTimeUnit\":\"ms\"}");
+        json
+    }
+}
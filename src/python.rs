@@ -0,0 +1,1246 @@
+// the macros of pyo3 0.22 check for its `gil-refs` feature and wrap returned errors in
+// a conversion clippy reports as useless
+#![allow(unexpected_cfgs, clippy::useless_conversion)]
+use crate::{
+    batch::{self, BatchValue},
+    calc_error::CalcError,
+    index_tree::IndexTreeReturnValue,
+    number_of_clusters::{self, IndexCurve, NumberOfClustersValue},
+    plugin::{Helper, IndexPlugin, PluginInput},
+};
+use numpy::{PyArray1, PyArray2, ToPyArray};
+use pyo3::{create_exception, exceptions::PyValueError, prelude::*, types::PyDict};
+use std::{collections::HashMap, sync::Arc};
+
+// every error is a ValueError, as before the kinds were introduced
+create_exception!(rust_ext, ClusteringError, PyValueError);
+create_exception!(rust_ext, ShapeError, ClusteringError);
+create_exception!(rust_ext, NonFiniteError, ClusteringError);
+create_exception!(rust_ext, LabelError, ClusteringError);
+create_exception!(rust_ext, PartitionError, ClusteringError);
+create_exception!(rust_ext, MissingInputError, ClusteringError);
+create_exception!(rust_ext, WeightError, ClusteringError);
+create_exception!(rust_ext, ComputationError, ClusteringError);
+
+impl From<CalcError> for PyErr {
+    fn from(err: CalcError) -> Self {
+        let message = err.to_string();
+        match err {
+            CalcError::Shape(_) => ShapeError::new_err(message),
+            CalcError::NonFinite { .. } => NonFiniteError::new_err(message),
+            CalcError::Labels(_) => LabelError::new_err(message),
+            CalcError::TooFewClusters { .. }
+            | CalcError::TooFewObservations { .. }
+            | CalcError::SingletonCluster { .. } => PartitionError::new_err(message),
+            CalcError::MissingInput(_) => MissingInputError::new_err(message),
+            CalcError::Weights(_) => WeightError::new_err(message),
+            CalcError::Computation(_) => ComputationError::new_err(message),
+        }
+    }
+}
+
+// custom index written in python, `func` takes a dict with the labels, the features (None
+// for distance input), the weights and the requested products, and returns a float
+#[derive(Clone, Debug)]
+struct PyIndexPlugin {
+    name: String,
+    requires: Vec<Helper>,
+    func: Arc<Py<PyAny>>,
+}
+impl IndexPlugin for PyIndexPlugin {
+    fn name(&self) -> &str {
+        &self.name
+    }
+    fn requires(&self) -> Vec<Helper> {
+        self.requires.clone()
+    }
+    fn compute(&self, input: &PluginInput) -> Result<f64, CalcError> {
+        Python::with_gil(|py| {
+            let inputs = PyDict::new_bound(py);
+            inputs.set_item("labels", input.labels.to_pyarray_bound(py))?;
+            inputs.set_item("features", input.features.map(|x| x.to_pyarray_bound(py)))?;
+            inputs.set_item("weights", input.weights.map(|w| w.to_pyarray_bound(py)))?;
+            if let Some(counts) = &input.counts {
+                inputs.set_item("counts", counts.to_pyarray_bound(py))?;
+            }
+            if let Some(centroids) = &input.centroids {
+                inputs.set_item("centroids", centroids.to_pyarray_bound(py))?;
+            }
+            if let Some(wgd) = &input.wgd {
+                inputs.set_item("wgd", wgd.to_pyarray_bound(py))?;
+            }
+            if let Some(bgd) = &input.bgd {
+                inputs.set_item("bgd", bgd.to_pyarray_bound(py))?;
+            }
+            if let Some(td) = &input.td {
+                inputs.set_item("td", td.to_pyarray_bound(py))?;
+            }
+            if let (Some(pairs), Some(distances)) = (&input.pairs, &input.distances) {
+                inputs.set_item("pairs", pairs.to_pyarray_bound(py))?;
+                inputs.set_item("distances", distances.to_pyarray_bound(py))?;
+            }
+            if let Some(scat) = input.scat {
+                inputs.set_item("scat", scat)?;
+            }
+            self.func.call1(py, (inputs,))?.extract::<f64>(py)
+        })
+        .map_err(|e| CalcError::from(e.to_string()))
+    }
+}
+
+type PairsAndDistancesArrays<'py> = (Bound<'py, PyArray1<i8>>, Bound<'py, PyArray1<f64>>);
+
+#[pymethods]
+impl IndexTreeReturnValue {
+    #[getter]
+    fn get_ball_hall(&self) -> Result<Option<f64>, CalcError> {
+        self.ball_hall.clone().map(|f| f.map(|v| v.val)).transpose()
+    }
+    #[getter]
+    fn get_davies_bouldin(&self) -> Result<Option<f64>, CalcError> {
+        self.davies_bouldin
+            .clone()
+            .map(|f| f.map(|v| v.val))
+            .transpose()
+    }
+    #[getter]
+    fn get_c_index(&self) -> Result<Option<f64>, CalcError> {
+        self.c_index.clone().map(|f| f.map(|v| v.val)).transpose()
+    }
+    #[getter]
+    fn get_calinski_harabasz(&self) -> Result<Option<f64>, CalcError> {
+        self.calinski_harabasz
+            .clone()
+            .map(|f| f.map(|v| v.val))
+            .transpose()
+    }
+    #[getter]
+    fn get_dunn(&self) -> Result<Option<f64>, CalcError> {
+        self.dunn.clone().map(|f| f.map(|v| v.val)).transpose()
+    }
+    #[getter]
+    fn get_silhouette(&self) -> Result<Option<f64>, CalcError> {
+        self.silhouette
+            .clone()
+            .map(|f| f.map(|v| v.val))
+            .transpose()
+    }
+    #[getter]
+    fn get_silhouette_samples<'py>(
+        &self,
+        py: Python<'py>,
+    ) -> Result<Option<Bound<'py, PyArray1<f64>>>, CalcError> {
+        self.silhouette
+            .clone()
+            .map(|f| f.map(|v| v.samples.to_pyarray_bound(py)))
+            .transpose()
+    }
+    #[getter]
+    fn get_silhouette_clusters<'py>(
+        &self,
+        py: Python<'py>,
+    ) -> Result<Option<Bound<'py, PyArray1<f64>>>, CalcError> {
+        self.silhouette
+            .clone()
+            .map(|f| f.map(|v| v.clusters.to_pyarray_bound(py)))
+            .transpose()
+    }
+    #[getter]
+    fn get_silhouette_nearest<'py>(
+        &self,
+        py: Python<'py>,
+    ) -> Result<Option<Bound<'py, PyArray1<i32>>>, CalcError> {
+        self.silhouette
+            .clone()
+            .map(|f| f.map(|v| v.nearest.to_pyarray_bound(py)))
+            .transpose()
+    }
+    #[getter]
+    fn get_rubin(&self) -> Result<Option<f64>, CalcError> {
+        self.rubin.clone().map(|f| f.map(|v| v.val)).transpose()
+    }
+    #[getter]
+    fn get_mariott(&self) -> Result<Option<f64>, CalcError> {
+        self.mariott.clone().map(|f| f.map(|v| v.val)).transpose()
+    }
+    #[getter]
+    fn get_scott(&self) -> Result<Option<f64>, CalcError> {
+        self.scott.clone().map(|f| f.map(|v| v.val)).transpose()
+    }
+    #[getter]
+    fn get_friedman(&self) -> Result<Option<f64>, CalcError> {
+        self.friedman.clone().map(|f| f.map(|v| v.val)).transpose()
+    }
+    #[getter]
+    fn get_tau(&self) -> Result<Option<f64>, CalcError> {
+        self.tau.clone().map(|f| f.map(|v| v.val)).transpose()
+    }
+    #[getter]
+    fn get_gamma(&self) -> Result<Option<f64>, CalcError> {
+        self.gamma.clone().map(|f| f.map(|v| v.val)).transpose()
+    }
+    #[getter]
+    fn get_gplus(&self) -> Result<Option<f64>, CalcError> {
+        self.gplus.clone().map(|f| f.map(|v| v.val)).transpose()
+    }
+    #[getter]
+    fn get_tracew(&self) -> Result<Option<f64>, CalcError> {
+        self.tracew.clone().map(|f| f.map(|v| v.val)).transpose()
+    }
+    #[getter]
+    fn get_mcclain(&self) -> Result<Option<f64>, CalcError> {
+        self.mcclain.clone().map(|f| f.map(|v| v.val)).transpose()
+    }
+    #[getter]
+    fn get_ptbiserial(&self) -> Result<Option<f64>, CalcError> {
+        self.ptbiserial
+            .clone()
+            .map(|f| f.map(|v| v.val))
+            .transpose()
+    }
+    #[getter]
+    fn get_ratkowsky(&self) -> Result<Option<f64>, CalcError> {
+        self.ratkowsky.clone().map(|f| f.map(|v| v.val)).transpose()
+    }
+    #[getter]
+    fn get_trcovw(&self) -> Result<Option<f64>, CalcError> {
+        self.trcovw.clone().map(|f| f.map(|v| v.val)).transpose()
+    }
+    #[getter]
+    fn get_hubert(&self) -> Result<Option<f64>, CalcError> {
+        self.hubert.clone().map(|f| f.map(|v| v.val)).transpose()
+    }
+    #[getter]
+    fn get_sd(&self) -> Result<Option<f64>, CalcError> {
+        self.sd.clone().map(|f| f.map(|v| v.val)).transpose()
+    }
+    #[getter]
+    fn get_sdbw(&self) -> Result<Option<f64>, CalcError> {
+        self.sdbw.clone().map(|f| f.map(|v| v.val)).transpose()
+    }
+    #[getter]
+    fn get_kl(&self) -> Result<Option<f64>, CalcError> {
+        self.kl.clone().map(|f| f.map(|v| v.val)).transpose()
+    }
+    #[getter]
+    fn get_hartigan(&self) -> Result<Option<f64>, CalcError> {
+        self.hartigan.clone().map(|f| f.map(|v| v.val)).transpose()
+    }
+    #[getter]
+    fn get_frey(&self) -> Result<Option<f64>, CalcError> {
+        self.frey.clone().map(|f| f.map(|v| v.val)).transpose()
+    }
+    #[getter]
+    fn get_duda(&self) -> Result<Option<f64>, CalcError> {
+        self.duda.clone().map(|f| f.map(|v| v.val)).transpose()
+    }
+    #[getter]
+    fn get_duda_critical(&self) -> Result<Option<f64>, CalcError> {
+        self.duda.clone().map(|f| f.map(|v| v.critical)).transpose()
+    }
+    #[getter]
+    fn get_pseudot2(&self) -> Result<Option<f64>, CalcError> {
+        self.pseudot2.clone().map(|f| f.map(|v| v.val)).transpose()
+    }
+    #[getter]
+    fn get_pseudot2_critical(&self) -> Result<Option<f64>, CalcError> {
+        self.pseudot2
+            .clone()
+            .map(|f| f.map(|v| v.critical))
+            .transpose()
+    }
+    #[getter]
+    fn get_beale(&self) -> Result<Option<f64>, CalcError> {
+        self.beale.clone().map(|f| f.map(|v| v.val)).transpose()
+    }
+    #[getter]
+    fn get_beale_p_value(&self) -> Result<Option<f64>, CalcError> {
+        self.beale.clone().map(|f| f.map(|v| v.p_value)).transpose()
+    }
+    #[getter]
+    fn get_gap(&self) -> Result<Option<f64>, CalcError> {
+        self.gap.clone().map(|f| f.map(|v| v.val)).transpose()
+    }
+    #[getter]
+    fn get_gap_s_k(&self) -> Result<Option<f64>, CalcError> {
+        self.gap.clone().map(|f| f.map(|v| v.s_k)).transpose()
+    }
+    #[getter]
+    fn get_ccc(&self) -> Result<Option<f64>, CalcError> {
+        self.ccc.clone().map(|f| f.map(|v| v.val)).transpose()
+    }
+    #[getter]
+    fn get_dindex(&self) -> Result<Option<f64>, CalcError> {
+        self.dindex.clone().map(|f| f.map(|v| v.val)).transpose()
+    }
+    #[getter]
+    fn get_adjusted_rand(&self) -> Result<Option<f64>, CalcError> {
+        self.adjusted_rand
+            .clone()
+            .map(|f| f.map(|v| v.val))
+            .transpose()
+    }
+    #[getter]
+    fn get_rand(&self) -> Result<Option<f64>, CalcError> {
+        self.rand.clone().map(|f| f.map(|v| v.val)).transpose()
+    }
+    #[getter]
+    fn get_jaccard(&self) -> Result<Option<f64>, CalcError> {
+        self.jaccard.clone().map(|f| f.map(|v| v.val)).transpose()
+    }
+    #[getter]
+    fn get_fowlkes_mallows(&self) -> Result<Option<f64>, CalcError> {
+        self.fowlkes_mallows
+            .clone()
+            .map(|f| f.map(|v| v.val))
+            .transpose()
+    }
+    #[getter]
+    fn get_nmi(&self) -> Result<Option<f64>, CalcError> {
+        self.nmi.clone().map(|f| f.map(|v| v.val)).transpose()
+    }
+    #[getter]
+    fn get_ami(&self) -> Result<Option<f64>, CalcError> {
+        self.ami.clone().map(|f| f.map(|v| v.val)).transpose()
+    }
+    #[getter]
+    fn get_v_measure(&self) -> Result<Option<f64>, CalcError> {
+        self.v_measure.clone().map(|f| f.map(|v| v.val)).transpose()
+    }
+    #[getter]
+    fn get_homogeneity(&self) -> Result<Option<f64>, CalcError> {
+        self.v_measure
+            .clone()
+            .map(|f| f.map(|v| v.homogeneity))
+            .transpose()
+    }
+    #[getter]
+    fn get_completeness(&self) -> Result<Option<f64>, CalcError> {
+        self.v_measure
+            .clone()
+            .map(|f| f.map(|v| v.completeness))
+            .transpose()
+    }
+    #[getter]
+    fn get_purity(&self) -> Result<Option<f64>, CalcError> {
+        self.purity.clone().map(|f| f.map(|v| v.val)).transpose()
+    }
+    #[getter]
+    fn get_contingency(&self) -> Result<Option<Vec<Vec<usize>>>, CalcError> {
+        self.contingency
+            .clone()
+            .map(|f| f.map(|v| v.val.rows().into_iter().map(|r| r.to_vec()).collect()))
+            .transpose()
+    }
+    #[getter]
+    fn get_counts<'py>(
+        &self,
+        py: Python<'py>,
+    ) -> Result<Option<Bound<'py, PyArray1<f64>>>, CalcError> {
+        self.counts
+            .clone()
+            .map(|f| f.map(|v| v.val.to_pyarray_bound(py)))
+            .transpose()
+    }
+    #[getter]
+    fn get_centroids<'py>(
+        &self,
+        py: Python<'py>,
+    ) -> Result<Option<Bound<'py, PyArray2<f64>>>, CalcError> {
+        self.centroids
+            .clone()
+            .map(|f| f.map(|v| v.val.to_pyarray_bound(py)))
+            .transpose()
+    }
+    #[getter]
+    fn get_wgd<'py>(
+        &self,
+        py: Python<'py>,
+    ) -> Result<Option<Bound<'py, PyArray2<f64>>>, CalcError> {
+        self.wgd
+            .clone()
+            .map(|f| f.map(|v| v.val.to_pyarray_bound(py)))
+            .transpose()
+    }
+    #[getter]
+    fn get_bgd<'py>(
+        &self,
+        py: Python<'py>,
+    ) -> Result<Option<Bound<'py, PyArray2<f64>>>, CalcError> {
+        self.bgd
+            .clone()
+            .map(|f| f.map(|v| v.val.to_pyarray_bound(py)))
+            .transpose()
+    }
+    #[getter]
+    fn get_td<'py>(&self, py: Python<'py>) -> Result<Option<Bound<'py, PyArray2<f64>>>, CalcError> {
+        self.td
+            .clone()
+            .map(|f| f.map(|v| v.val.to_pyarray_bound(py)))
+            .transpose()
+    }
+    // same-cluster flag and distance of every pair of observations, in condensed order
+    #[getter]
+    fn get_pairs_and_distances<'py>(
+        &self,
+        py: Python<'py>,
+    ) -> Result<Option<PairsAndDistancesArrays<'py>>, CalcError> {
+        self.pairs_and_distances
+            .clone()
+            .map(|f| {
+                f.map(|v| {
+                    (
+                        v.pairs.to_pyarray_bound(py),
+                        v.distances.to_pyarray_bound(py),
+                    )
+                })
+            })
+            .transpose()
+    }
+    #[getter]
+    fn get_scat(&self) -> Result<Option<f64>, CalcError> {
+        self.scat.clone().map(|f| f.map(|v| v.val)).transpose()
+    }
+    // original id of every row of the per-cluster outputs
+    #[getter]
+    fn get_cluster_ids<'py>(&self, py: Python<'py>) -> Option<Bound<'py, PyArray1<i32>>> {
+        self.cluster_ids
+            .as_ref()
+            .map(|ids| ids.to_pyarray_bound(py))
+    }
+    // intermediate per-cluster arrays of davies_bouldin, dunn, sd and sdbw
+    fn details(
+        &self,
+        py: Python<'_>,
+        index: &str,
+    ) -> Result<Option<HashMap<&'static str, PyObject>>, CalcError> {
+        let details = match index {
+            "davies_bouldin" => self.davies_bouldin.clone().map(|f| {
+                f.map(|v| {
+                    HashMap::from([
+                        (
+                            "scatter",
+                            v.scatter.to_pyarray_bound(py).into_any().unbind(),
+                        ),
+                        ("ratios", v.ratios.to_pyarray_bound(py).into_any().unbind()),
+                        ("worst", v.worst.to_pyarray_bound(py).into_any().unbind()),
+                    ])
+                })
+            }),
+            "dunn" => self.dunn.clone().map(|f| {
+                f.map(|v| {
+                    HashMap::from([
+                        (
+                            "diameters",
+                            v.diameters.to_pyarray_bound(py).into_any().unbind(),
+                        ),
+                        (
+                            "separations",
+                            v.separations.to_pyarray_bound(py).into_any().unbind(),
+                        ),
+                    ])
+                })
+            }),
+            "sd" => self.sd.clone().map(|f| {
+                f.map(|v| {
+                    HashMap::from([
+                        ("scat", v.scat.to_pyarray_bound(py).into_any().unbind()),
+                        ("dis", v.dis.to_pyarray_bound(py).into_any().unbind()),
+                        (
+                            "centroid_distances",
+                            v.centroid_distances
+                                .to_pyarray_bound(py)
+                                .into_any()
+                                .unbind(),
+                        ),
+                    ])
+                })
+            }),
+            "sdbw" => self.sdbw.clone().map(|f| {
+                f.map(|v| {
+                    HashMap::from([(
+                        "density_ratios",
+                        v.density_ratios.to_pyarray_bound(py).into_any().unbind(),
+                    )])
+                })
+            }),
+            _ => return Err(format!("No details for index {index}").into()),
+        };
+        details.transpose()
+    }
+    // value of the custom index added with `Config.with_plugin`
+    fn plugin(&self, name: &str) -> Result<Option<f64>, CalcError> {
+        self.plugins
+            .iter()
+            .find(|(plugin, _)| plugin == name)
+            .map(|(_, value)| value.clone())
+            .transpose()
+    }
+    // one dict per node run, times in seconds, set when the config enables `trace`
+    #[getter]
+    fn get_profile(&self, py: Python<'_>) -> Option<Vec<HashMap<&'static str, PyObject>>> {
+        let profile = self.profile.as_ref()?;
+        let nodes = profile
+            .nodes
+            .iter()
+            .map(|node| {
+                HashMap::from([
+                    ("name", node.name.into_py(py)),
+                    ("product", node.product.into_py(py)),
+                    (
+                        "side",
+                        format!("{:?}", node.side).to_lowercase().into_py(py),
+                    ),
+                    ("thread", node.thread.into_py(py)),
+                    ("start", node.start.as_secs_f64().into_py(py)),
+                    ("duration", node.duration.as_secs_f64().into_py(py)),
+                    ("memory", node.memory.into_py(py)),
+                    ("ok", node.ok.into_py(py)),
+                ])
+            })
+            .collect();
+        Some(nodes)
+    }
+    // the profile as chrome trace-event JSON, for chrome://tracing or Perfetto
+    fn chrome_trace(&self) -> Option<String> {
+        self.profile.as_ref().map(|profile| profile.chrome_trace())
+    }
+    // 95% confidence interval of an index estimated on subsamples
+    fn confidence_interval(&self, index: &str) -> Option<(f64, f64)> {
+        self.estimates
+            .iter()
+            .find(|(name, _)| *name == index)
+            .map(|(_, e)| (e.low, e.high))
+    }
+}
+
+#[pymethods]
+impl NumberOfClustersValue {
+    #[getter]
+    fn get_ks(&self) -> Vec<usize> {
+        self.ks.clone()
+    }
+    #[getter]
+    fn get_results(&self) -> Vec<IndexTreeReturnValue> {
+        self.results.clone()
+    }
+    #[getter]
+    fn get_choices(&self) -> HashMap<&'static str, Option<usize>> {
+        self.choices
+            .iter()
+            .map(|c| (c.index, c.best_k.as_ref().ok().copied()))
+            .collect()
+    }
+    #[getter]
+    fn get_votes(&self) -> Vec<(usize, usize)> {
+        self.votes.clone()
+    }
+    #[getter]
+    fn get_best_k(&self) -> Option<usize> {
+        self.best_k
+    }
+    #[pyo3(name = "curve")]
+    fn py_curve(&self, index: &str) -> Option<IndexCurve> {
+        self.curve(index)
+    }
+}
+
+#[pymethods]
+impl BatchValue {
+    #[getter]
+    fn get_results(&self) -> Vec<IndexTreeReturnValue> {
+        self.results.clone()
+    }
+    // names of the selected indexes and a labelings x indexes array of their values
+    #[pyo3(name = "table")]
+    fn py_table<'py>(&self, py: Python<'py>) -> (Vec<&'static str>, Bound<'py, PyArray2<f64>>) {
+        let (names, table) = self.table();
+        (names, table.to_pyarray_bound(py))
+    }
+}
+
+#[pymodule]
+mod rust_ext {
+    use super::*;
+    use crate::index_tree::IndexTreeBuilder;
+    use crate::indexes::gap::{GapConfig, GapReference};
+    use crate::indexes::helpers::distances::{DistanceMatrix, PairwiseEngine};
+    use crate::labels::{NoisePolicy, NormalizedPartition};
+    use crate::metric::Metric;
+    use crate::sampling::{SamplingConfig, SamplingStrategy};
+    use crate::validation::ValidationConfig;
+    use ndarray::ArrayView1;
+    use numpy::{npyffi::npy_int32, PyReadonlyArray1, PyReadonlyArray2};
+    use pyo3::exceptions::PyValueError;
+    use std::sync::Arc;
+
+    #[pyclass(frozen)]
+    #[derive(Default, Debug, Clone)]
+    struct IndexTreeConfig {
+        #[pyo3(get)]
+        pub ball_hall: bool,
+        #[pyo3(get)]
+        pub davies_bouldin: bool,
+        #[pyo3(get)]
+        pub c_index: bool,
+        #[pyo3(get)]
+        pub calinski_harabasz: bool,
+        #[pyo3(get)]
+        pub dunn: bool,
+        #[pyo3(get)]
+        pub silhouette: bool,
+        #[pyo3(get)]
+        pub rubin: bool,
+        #[pyo3(get)]
+        pub mariott: bool,
+        #[pyo3(get)]
+        pub scott: bool,
+        #[pyo3(get)]
+        pub friedman: bool,
+        #[pyo3(get)]
+        pub tau: bool,
+        #[pyo3(get)]
+        pub gamma: bool,
+        #[pyo3(get)]
+        pub gplus: bool,
+        #[pyo3(get)]
+        pub tracew: bool,
+        #[pyo3(get)]
+        pub mcclain: bool,
+        #[pyo3(get)]
+        pub ptbiserial: bool,
+        #[pyo3(get)]
+        pub ratkowsky: bool,
+        #[pyo3(get)]
+        pub trcovw: bool,
+        #[pyo3(get)]
+        pub hubert: bool,
+        #[pyo3(get)]
+        pub sd: bool,
+        #[pyo3(get)]
+        pub sdbw: bool,
+        #[pyo3(get)]
+        pub kl: bool,
+        #[pyo3(get)]
+        pub hartigan: bool,
+        #[pyo3(get)]
+        pub frey: bool,
+        #[pyo3(get)]
+        pub duda: bool,
+        #[pyo3(get)]
+        pub pseudot2: bool,
+        #[pyo3(get)]
+        pub beale: bool,
+        #[pyo3(get)]
+        pub gap: bool,
+        #[pyo3(get)]
+        pub ccc: bool,
+        #[pyo3(get)]
+        pub dindex: bool,
+        #[pyo3(get)]
+        pub adjusted_rand: bool,
+        #[pyo3(get)]
+        pub rand: bool,
+        #[pyo3(get)]
+        pub jaccard: bool,
+        #[pyo3(get)]
+        pub fowlkes_mallows: bool,
+        #[pyo3(get)]
+        pub nmi: bool,
+        #[pyo3(get)]
+        pub ami: bool,
+        #[pyo3(get)]
+        pub v_measure: bool,
+        #[pyo3(get)]
+        pub purity: bool,
+        #[pyo3(get)]
+        pub contingency: bool,
+        pub gap_config: GapConfig,
+        pub metric: Metric,
+        pub engine: PairwiseEngine,
+        pub sampling: Option<SamplingConfig>,
+        pub noise: NoisePolicy,
+        pub validation: ValidationConfig,
+        pub helpers: Vec<Helper>,
+        pub plugins: Vec<PyIndexPlugin>,
+        pub tracing: bool,
+    }
+    impl IndexTreeConfig {
+        fn enable(&mut self, name: &str) -> PyResult<()> {
+            match name {
+                "ball_hall" => self.ball_hall = true,
+                "davies_bouldin" => self.davies_bouldin = true,
+                "c_index" => self.c_index = true,
+                "calinski_harabasz" => self.calinski_harabasz = true,
+                "dunn" => self.dunn = true,
+                "silhouette" => self.silhouette = true,
+                "rubin" => self.rubin = true,
+                "mariott" => self.mariott = true,
+                "scott" => self.scott = true,
+                "friedman" => self.friedman = true,
+                "tau" => self.tau = true,
+                "gamma" => self.gamma = true,
+                "gplus" => self.gplus = true,
+                "tracew" => self.tracew = true,
+                "mcclain" => self.mcclain = true,
+                "ptbiserial" => self.ptbiserial = true,
+                "ratkowsky" => self.ratkowsky = true,
+                "trcovw" => self.trcovw = true,
+                "hubert" => self.hubert = true,
+                "sd" => self.sd = true,
+                "sdbw" => self.sdbw = true,
+                "kl" => self.kl = true,
+                "hartigan" => self.hartigan = true,
+                "frey" => self.frey = true,
+                "duda" => self.duda = true,
+                "pseudot2" => self.pseudot2 = true,
+                "beale" => self.beale = true,
+                "gap" => self.gap = true,
+                "ccc" => self.ccc = true,
+                "dindex" => self.dindex = true,
+                "adjusted_rand" => self.adjusted_rand = true,
+                "rand" => self.rand = true,
+                "jaccard" => self.jaccard = true,
+                "fowlkes_mallows" => self.fowlkes_mallows = true,
+                "nmi" => self.nmi = true,
+                "ami" => self.ami = true,
+                "v_measure" => self.v_measure = true,
+                "purity" => self.purity = true,
+                "contingency" => self.contingency = true,
+                "all" => self.enable_all(),
+                _ => return Err(PyValueError::new_err(format!("Unknown index {name}"))),
+            }
+            Ok(())
+        }
+        // the preset only turns indexes on, every other setting is kept
+        fn enable_all(&mut self) {
+            self.ball_hall = true;
+            self.davies_bouldin = true;
+            self.c_index = true;
+            self.calinski_harabasz = true;
+            self.dunn = true;
+            self.silhouette = true;
+            self.rubin = true;
+            self.mariott = true;
+            self.scott = true;
+            self.friedman = true;
+            self.tau = true;
+            self.gamma = true;
+            self.gplus = true;
+            self.tracew = true;
+            self.mcclain = true;
+            self.ptbiserial = true;
+            self.ratkowsky = true;
+            self.trcovw = true;
+            self.hubert = true;
+            self.sd = true;
+            self.sdbw = true;
+            self.kl = true;
+            self.hartigan = true;
+            self.frey = true;
+            self.duda = true;
+            self.pseudot2 = true;
+            self.beale = true;
+            self.gap = true;
+            self.ccc = true;
+            self.dindex = true;
+            self.adjusted_rand = true;
+            self.rand = true;
+            self.jaccard = true;
+            self.fowlkes_mallows = true;
+            self.nmi = true;
+            self.ami = true;
+            self.v_measure = true;
+            self.purity = true;
+            self.contingency = true;
+        }
+        fn apply<'a>(&self, mut builder: IndexTreeBuilder<'a>) -> IndexTreeBuilder<'a> {
+            builder = builder
+                .metric(self.metric.clone())
+                .memory_budget(self.engine.memory_budget)
+                .validation(self.validation)
+                .tracing(self.tracing);
+            if let Some(sampling) = self.sampling {
+                builder = builder.sampling(sampling);
+            }
+            if self.ball_hall {
+                builder = builder.add_ball_hall();
+            }
+            if self.davies_bouldin {
+                builder = builder.add_davies_bouldin();
+            }
+            if self.c_index {
+                builder = builder.add_c_index();
+            }
+            if self.calinski_harabasz {
+                builder = builder.add_calinski_harabasz();
+            }
+            if self.dunn {
+                builder = builder.add_dunn();
+            }
+            if self.silhouette {
+                builder = builder.add_silhouette();
+            }
+            if self.rubin {
+                builder = builder.add_rubin();
+            }
+            if self.mariott {
+                builder = builder.add_mariott();
+            }
+            if self.scott {
+                builder = builder.add_scott();
+            }
+            if self.friedman {
+                builder = builder.add_friedman();
+            }
+            if self.tau {
+                builder = builder.add_tau();
+            }
+            if self.gamma {
+                builder = builder.add_gamma();
+            }
+            if self.gplus {
+                builder = builder.add_gplus();
+            }
+            if self.tracew {
+                builder = builder.add_tracew();
+            }
+            if self.mcclain {
+                builder = builder.add_mcclain();
+            }
+            if self.ptbiserial {
+                builder = builder.add_ptbiserial();
+            }
+            if self.ratkowsky {
+                builder = builder.add_ratkowsky();
+            }
+            if self.trcovw {
+                builder = builder.add_trcovw();
+            }
+            if self.hubert {
+                builder = builder.add_hubert();
+            }
+            if self.sd {
+                builder = builder.add_sd();
+            }
+            if self.sdbw {
+                builder = builder.add_sdbw();
+            }
+            if self.kl {
+                builder = builder.add_kl();
+            }
+            if self.hartigan {
+                builder = builder.add_hartigan();
+            }
+            if self.frey {
+                builder = builder.add_frey();
+            }
+            if self.duda {
+                builder = builder.add_duda();
+            }
+            if self.pseudot2 {
+                builder = builder.add_pseudot2();
+            }
+            if self.beale {
+                builder = builder.add_beale();
+            }
+            if self.gap {
+                builder = builder.add_gap(self.gap_config);
+            }
+            if self.ccc {
+                builder = builder.add_ccc();
+            }
+            if self.dindex {
+                builder = builder.add_dindex();
+            }
+            if self.adjusted_rand {
+                builder = builder.add_adjusted_rand();
+            }
+            if self.rand {
+                builder = builder.add_rand();
+            }
+            if self.jaccard {
+                builder = builder.add_jaccard();
+            }
+            if self.fowlkes_mallows {
+                builder = builder.add_fowlkes_mallows();
+            }
+            if self.nmi {
+                builder = builder.add_nmi();
+            }
+            if self.ami {
+                builder = builder.add_ami();
+            }
+            if self.v_measure {
+                builder = builder.add_v_measure();
+            }
+            if self.purity {
+                builder = builder.add_purity();
+            }
+            if self.contingency {
+                builder = builder.add_contingency();
+            }
+            for helper in &self.helpers {
+                builder = builder.add_helper(*helper);
+            }
+            for plugin in &self.plugins {
+                builder = builder.add_plugin(Arc::new(plugin.clone()));
+            }
+            builder
+        }
+    }
+    #[pymethods]
+    impl IndexTreeConfig {
+        // indexes are chosen by name, "all" selects every one of them
+        #[new]
+        #[pyo3(signature = (
+                indexes=None,
+                *,
+                gap_b=100,
+                gap_reference="uniform",
+                gap_seed=None,
+                metric=None,
+                metric_p=2.,
+                metric_vi=None,
+                memory_budget=None,
+                sample_size=None,
+                sample_strategy="uniform",
+                sample_repeats=10,
+                sample_seed=None,
+                noise="exclude",
+                reject_singletons=false,
+                helpers=None,
+                trace=false
+        ))]
+        #[allow(clippy::too_many_arguments)]
+        fn new(
+            indexes: Option<Vec<String>>,
+            gap_b: usize,
+            gap_reference: &str,
+            gap_seed: Option<u64>,
+            metric: Option<&Bound<'_, PyAny>>,
+            metric_p: f64,
+            metric_vi: Option<PyReadonlyArray2<'_, f64>>,
+            memory_budget: Option<usize>,
+            sample_size: Option<usize>,
+            sample_strategy: &str,
+            sample_repeats: usize,
+            sample_seed: Option<u64>,
+            noise: &str,
+            reject_singletons: bool,
+            helpers: Option<Vec<String>>,
+            trace: bool,
+        ) -> PyResult<Self> {
+            let noise = match noise {
+                "exclude" => NoisePolicy::Exclude,
+                "singletons" => NoisePolicy::Singletons,
+                "cluster" => NoisePolicy::Cluster,
+                _ => {
+                    return Err(PyValueError::new_err(format!(
+                        "Unknown noise policy {noise}, expected exclude, singletons or cluster"
+                    )))
+                }
+            };
+            let strategy = match sample_strategy {
+                "uniform" => SamplingStrategy::Uniform,
+                "stratified" => SamplingStrategy::Stratified,
+                _ => {
+                    return Err(PyValueError::new_err(format!(
+                        "Unknown sample strategy {sample_strategy}, expected uniform or stratified"
+                    )))
+                }
+            };
+            let reference = match gap_reference {
+                "uniform" => GapReference::Uniform,
+                "pca" => GapReference::Pca,
+                _ => {
+                    return Err(PyValueError::new_err(format!(
+                        "Unknown gap reference {gap_reference}, expected uniform or pca"
+                    )))
+                }
+            };
+            // helper products kept as outputs
+            let helpers = helpers
+                .unwrap_or_default()
+                .iter()
+                .map(|name| parse_helper(name))
+                .collect::<PyResult<Vec<Helper>>>()?;
+            let mut config = Self {
+                gap_config: GapConfig {
+                    reference,
+                    b: gap_b,
+                    seed: gap_seed,
+                },
+                metric: parse_metric(metric, metric_p, metric_vi)?,
+                engine: memory_budget
+                    .map(|memory_budget| PairwiseEngine { memory_budget })
+                    .unwrap_or_default(),
+                sampling: sample_size.map(|size| SamplingConfig {
+                    size,
+                    strategy,
+                    repeats: sample_repeats,
+                    seed: sample_seed,
+                }),
+                noise,
+                validation: ValidationConfig { reject_singletons },
+                helpers,
+                plugins: Vec::new(),
+                tracing: trace,
+                ..Self::default()
+            };
+            for name in indexes.unwrap_or_default() {
+                config.enable(&name)?;
+            }
+            Ok(config)
+        }
+        // copy of the config that also computes the custom index `func`, `requires` names
+        // the products it reads: counts, centroids, wgd, bgd, td, pairs_and_distances, scat
+        #[pyo3(signature = (name, func, requires=Vec::new()))]
+        fn with_plugin(
+            &self,
+            name: String,
+            func: &Bound<'_, PyAny>,
+            requires: Vec<String>,
+        ) -> PyResult<Self> {
+            if !func.is_callable() {
+                return Err(PyValueError::new_err(format!(
+                    "Plugin {name} is not callable"
+                )));
+            }
+            let requires = requires
+                .iter()
+                .map(|name| parse_helper(name))
+                .collect::<PyResult<Vec<Helper>>>()?;
+            let mut config = self.clone();
+            config.plugins.push(PyIndexPlugin {
+                name,
+                requires,
+                func: Arc::new(func.clone().unbind()),
+            });
+            Ok(config)
+        }
+        // nodes the selected indexes run, and the helpers they skip, as Graphviz DOT
+        fn dot(&self) -> String {
+            self.apply(IndexTreeBuilder::default()).finish().dot()
+        }
+    }
+
+    fn parse_helper(name: &str) -> PyResult<Helper> {
+        Helper::from_name(name)
+            .ok_or_else(|| PyValueError::new_err(format!("Unknown helper product {name}")))
+    }
+
+    // `metric` is either a name or a callable taking two 1d arrays and returning a float
+    fn parse_metric(
+        metric: Option<&Bound<'_, PyAny>>,
+        p: f64,
+        vi: Option<PyReadonlyArray2<'_, f64>>,
+    ) -> PyResult<Metric> {
+        let Some(metric) = metric else {
+            return Ok(Metric::default());
+        };
+        if metric.is_callable() {
+            let f = metric.clone().unbind();
+            let distance = move |a: ArrayView1<f64>, b: ArrayView1<f64>| {
+                Python::with_gil(|py| {
+                    let (a, b) = (a.to_pyarray_bound(py), b.to_pyarray_bound(py));
+                    f.call1(py, (a, b))?.extract::<f64>(py)
+                })
+                .map_err(|e| CalcError::from(e.to_string()))
+            };
+            return Ok(Metric::Custom(Arc::new(distance)));
+        }
+        let name = metric.extract::<String>()?;
+        let metric = match name.as_str() {
+            "euclidean" => Metric::Euclidean,
+            "sqeuclidean" => Metric::SqEuclidean,
+            "manhattan" | "cityblock" => Metric::Manhattan,
+            "chebyshev" => Metric::Chebyshev,
+            "minkowski" => Metric::Minkowski(p),
+            "cosine" => Metric::Cosine,
+            "correlation" => Metric::Correlation,
+            "mahalanobis" => {
+                Metric::Mahalanobis(vi.map(|vi| vi.as_array().to_owned().into_shared()))
+            }
+            _ => return Err(PyValueError::new_err(format!("Unknown metric {name}"))),
+        };
+        Ok(metric)
+    }
+
+    #[pymodule_init]
+    fn init(m: &Bound<'_, PyModule>) -> PyResult<()> {
+        m.add("compute_indexes", m.getattr("compute_indexes")?)?;
+        m.add(
+            "compute_indexes_from_distances",
+            m.getattr("compute_indexes_from_distances")?,
+        )?;
+        m.add("compute_indexes_batch", m.getattr("compute_indexes_batch")?)?;
+        m.add("Config", m.getattr("IndexTreeConfig")?)?;
+        let py = m.py();
+        m.add("ClusteringError", py.get_type_bound::<ClusteringError>())?;
+        m.add("ShapeError", py.get_type_bound::<ShapeError>())?;
+        m.add("NonFiniteError", py.get_type_bound::<NonFiniteError>())?;
+        m.add("LabelError", py.get_type_bound::<LabelError>())?;
+        m.add("PartitionError", py.get_type_bound::<PartitionError>())?;
+        m.add(
+            "MissingInputError",
+            py.get_type_bound::<MissingInputError>(),
+        )?;
+        m.add("WeightError", py.get_type_bound::<WeightError>())?;
+        m.add("ComputationError", py.get_type_bound::<ComputationError>())?;
+        m.add(
+            "select_number_of_clusters",
+            m.getattr("select_number_of_clusters")?,
+        )?;
+        Ok(())
+    }
+    #[pyfunction]
+    #[pyo3(signature = (x, y, config, y_prev=None, y_next=None, y_true=None, sample_weight=None))]
+    #[allow(clippy::too_many_arguments)]
+    fn compute_indexes<'py>(
+        py: Python<'py>,
+        x: PyReadonlyArray2<'py, f64>,
+        y: PyReadonlyArray1<'py, npy_int32>,
+        config: Py<IndexTreeConfig>,
+        y_prev: Option<PyReadonlyArray1<'py, npy_int32>>,
+        y_next: Option<PyReadonlyArray1<'py, npy_int32>>,
+        y_true: Option<PyReadonlyArray1<'py, npy_int32>>,
+        sample_weight: Option<PyReadonlyArray1<'py, f64>>,
+    ) -> PyResult<Py<IndexTreeReturnValue>> {
+        let x = x.as_array();
+        let y = y.as_array();
+        let y_prev = y_prev.as_ref().map(|y| y.as_array());
+        let y_next = y_next.as_ref().map(|y| y.as_array());
+        let y_true = y_true.as_ref().map(|y| y.as_array());
+        let sample_weight = sample_weight.as_ref().map(|w| w.as_array());
+
+        // noise and arbitrary cluster ids are relabelled before any index sees them
+        let mut data =
+            NormalizedPartition::new(x, y, config.get().noise)?.with_adjacent(y_prev, y_next)?;
+        if let Some(y_true) = y_true {
+            data = data.with_reference(y_true)?;
+        }
+        if let Some(sample_weight) = sample_weight {
+            data = data.with_weights(sample_weight)?;
+        }
+        let tree = config.get().apply(IndexTreeBuilder::default()).finish();
+        // custom metrics take the GIL from the worker threads
+        let res = py.allow_threads(|| tree.compute_normalized(&data));
+        Py::new(py, res)
+    }
+    // `distances` is either a condensed vector (as from scipy's pdist) or a square matrix
+    #[pyfunction]
+    #[pyo3(signature = (distances, y, config, y_true=None))]
+    fn compute_indexes_from_distances<'py>(
+        py: Python<'py>,
+        distances: &Bound<'py, PyAny>,
+        y: PyReadonlyArray1<'py, npy_int32>,
+        config: Py<IndexTreeConfig>,
+        y_true: Option<PyReadonlyArray1<'py, npy_int32>>,
+    ) -> PyResult<Py<IndexTreeReturnValue>> {
+        let condensed = distances.extract::<PyReadonlyArray1<'py, f64>>().ok();
+        let square = distances.extract::<PyReadonlyArray2<'py, f64>>().ok();
+        let distances = match (condensed.as_ref(), square.as_ref()) {
+            (Some(d), _) => DistanceMatrix::Condensed(d.as_array()),
+            (None, Some(d)) => DistanceMatrix::Square(d.as_array()),
+            (None, None) => {
+                return Err(PyValueError::new_err(
+                    "distances must be a 1d or 2d float64 array",
+                ))
+            }
+        };
+        let y = y.as_array();
+        let y_true = y_true.as_ref().map(|y| y.as_array());
+
+        let mut data = NormalizedPartition::from_distances(distances, y, config.get().noise)?;
+        if let Some(y_true) = y_true {
+            data = data.with_reference(y_true)?;
+        }
+        let tree = config.get().apply(IndexTreeBuilder::default()).finish();
+        let res = py.allow_threads(|| tree.compute_normalized(&data));
+        Py::new(py, res)
+    }
+    #[pyfunction]
+    #[pyo3(signature = (x, labelings, config, kmin=2, kmax=None))]
+    fn select_number_of_clusters<'py>(
+        py: Python<'py>,
+        x: PyReadonlyArray2<'py, f64>,
+        labelings: &Bound<'py, PyAny>,
+        config: Py<IndexTreeConfig>,
+        kmin: usize,
+        kmax: Option<usize>,
+    ) -> PyResult<Py<NumberOfClustersValue>> {
+        let x = x.as_array();
+        let labelings = if labelings.is_callable() {
+            let kmax = kmax.ok_or_else(|| {
+                PyValueError::new_err("kmax is required when labelings is a callable")
+            })?;
+            (kmin..=kmax)
+                .map(|k| {
+                    let y = labelings.call1((k,))?;
+                    let y = y.extract::<PyReadonlyArray1<npy_int32>>()?;
+                    Ok((k, y.as_array().to_owned()))
+                })
+                .collect::<PyResult<Vec<_>>>()?
+        } else {
+            let labelings = labelings
+                .iter()?
+                .enumerate()
+                .map(|(i, y)| {
+                    let y = y?.extract::<PyReadonlyArray1<npy_int32>>()?;
+                    Ok((kmin + i, y.as_array().to_owned()))
+                })
+                .collect::<PyResult<Vec<_>>>()?;
+            if kmax.is_some_and(|kmax| kmax + 1 != kmin + labelings.len()) {
+                return Err(PyValueError::new_err(
+                    "Number of labelings doesnt match kmin..=kmax",
+                ));
+            }
+            labelings
+        };
+        let config = config.get();
+        let res = py.allow_threads(|| {
+            number_of_clusters::select_number_of_clusters(x, labelings, config.noise, |builder| {
+                config.apply(builder)
+            })
+        })?;
+        Py::new(py, res)
+    }
+    // `labelings` is a 2d array with one labeling per row or a list of 1d arrays
+    #[pyfunction]
+    #[pyo3(signature = (x, labelings, config, sample_weight=None))]
+    fn compute_indexes_batch<'py>(
+        py: Python<'py>,
+        x: PyReadonlyArray2<'py, f64>,
+        labelings: &Bound<'py, PyAny>,
+        config: Py<IndexTreeConfig>,
+        sample_weight: Option<PyReadonlyArray1<'py, f64>>,
+    ) -> PyResult<Py<BatchValue>> {
+        let x = x.as_array();
+        let labelings = match labelings.extract::<PyReadonlyArray2<'py, npy_int32>>() {
+            Ok(y) => y
+                .as_array()
+                .rows()
+                .into_iter()
+                .map(|y| y.to_owned())
+                .collect(),
+            Err(_) => labelings
+                .iter()?
+                .map(|y| {
+                    Ok(y?
+                        .extract::<PyReadonlyArray1<npy_int32>>()?
+                        .as_array()
+                        .to_owned())
+                })
+                .collect::<PyResult<Vec<_>>>()?,
+        };
+        let sample_weight = sample_weight.as_ref().map(|w| w.as_array());
+        let config = config.get();
+        let res = py.allow_threads(|| {
+            batch::compute_batch(x, &labelings, sample_weight, config.noise, |builder| {
+                config.apply(builder)
+            })
+        })?;
+        Py::new(py, res)
+    }
+}
@@ -0,0 +1,246 @@
+use crate::calc_error::CalcError;
+use crate::dataflow::{Node, Product};
+use crate::index_tree::{IndexTreeBuilder, IndexTreeReturnValue};
+use crate::indexes::gap::compact;
+use crate::indexes::helpers::distances::{
+    condensed_index, distance_source, DistanceMatrix, DistanceSource, PairwiseEngine,
+};
+use crate::metric::Metric;
+use itertools::Itertools;
+use ndarray::{Array1, ArrayView1, Axis};
+use rand::{seq::index::sample, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use rayon::prelude::*;
+
+// indexes comparing pairs of observations, estimated on subsamples in sampling mode
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PairIndex {
+    Silhouette,
+    Hubert,
+    CIndex,
+    Dunn,
+    Gamma,
+    Tau,
+    Gplus,
+    Mcclain,
+    Ptbiserial,
+}
+impl PairIndex {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Silhouette => "silhouette",
+            Self::Hubert => "hubert",
+            Self::CIndex => "c_index",
+            Self::Dunn => "dunn",
+            Self::Gamma => "gamma",
+            Self::Tau => "tau",
+            Self::Gplus => "gplus",
+            Self::Mcclain => "mcclain",
+            Self::Ptbiserial => "ptbiserial",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SamplingStrategy {
+    // observations drawn without replacement from the whole dataset
+    #[default]
+    Uniform,
+    // every cluster contributes in proportion to its size, and at least one observation
+    Stratified,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct SamplingConfig {
+    // number of observations in every subsample
+    pub size: usize,
+    pub strategy: SamplingStrategy,
+    // number of subsamples the estimate is averaged over
+    pub repeats: usize,
+    // draws are reproducible when set
+    pub seed: Option<u64>,
+}
+impl Default for SamplingConfig {
+    fn default() -> Self {
+        Self {
+            size: 1000,
+            strategy: SamplingStrategy::Uniform,
+            repeats: 10,
+            seed: None,
+        }
+    }
+}
+
+// mean over the subsamples with its 95% normal confidence interval,
+// the interval is NaN for a single repeat
+#[derive(Clone, Copy, Debug)]
+pub struct SampledEstimate {
+    pub val: f64,
+    pub std: f64,
+    pub low: f64,
+    pub high: f64,
+    pub repeats: usize,
+}
+
+#[derive(Clone, Debug)]
+pub struct SampledValue {
+    pub estimates: Vec<(PairIndex, Result<SampledEstimate, CalcError>)>,
+}
+impl Product for SampledValue {}
+
+const Z_95: f64 = 1.959963984540054;
+
+pub struct Sampling {
+    config: SamplingConfig,
+    indexes: Vec<PairIndex>,
+    engine: PairwiseEngine,
+}
+impl Sampling {
+    fn draw(&self, y: &ArrayView1<i32>, rng: &mut ChaCha8Rng) -> Vec<usize> {
+        let n = y.len();
+        let size = self.config.size.min(n);
+        let mut idx = match self.config.strategy {
+            SamplingStrategy::Uniform => sample(rng, n, size).into_vec(),
+            SamplingStrategy::Stratified => y
+                .iter()
+                .enumerate()
+                .into_group_map_by(|(_, c)| **c)
+                .into_iter()
+                .sorted_by_key(|(c, _)| *c)
+                .flat_map(|(_, members)| {
+                    let quota = (members.len() * size + n / 2) / n;
+                    let quota = quota.clamp(1, members.len());
+                    sample(rng, members.len(), quota)
+                        .into_iter()
+                        .map(|i| members[i].0)
+                        .collect::<Vec<usize>>()
+                })
+                .collect(),
+        };
+        idx.sort_unstable();
+        idx
+    }
+
+    fn run(
+        &self,
+        source: &DistanceSource,
+        y: &ArrayView1<i32>,
+        rng: &mut ChaCha8Rng,
+    ) -> IndexTreeReturnValue {
+        let idx = self.draw(y, rng);
+        // clusters missing from the subsample dont leave gaps in the labels
+        let labels = compact(y.select(Axis(0), &idx));
+        let metric = match source {
+            DistanceSource::Features(_, metric) => (*metric).clone(),
+            DistanceSource::Precomputed(_) => Metric::default(),
+        };
+        let builder = self.indexes.iter().fold(
+            IndexTreeBuilder::default()
+                .metric(metric)
+                .memory_budget(self.engine.memory_budget),
+            |builder, index| builder.add_pair_index(*index),
+        );
+        match source {
+            DistanceSource::Features(x, _) => {
+                let x = x.select(Axis(0), &idx);
+                builder.finish().compute((x.view(), labels.view()))
+            }
+            DistanceSource::Precomputed(matrix) => {
+                let n = y.len();
+                let distances = Array1::from_iter(idx.iter().enumerate().flat_map(|(a, i)| {
+                    idx[a + 1..].iter().map(move |j| match matrix {
+                        DistanceMatrix::Condensed(d) => d[condensed_index(n, *i, *j)],
+                        DistanceMatrix::Square(d) => d[[*i, *j]],
+                    })
+                }));
+                builder.finish().compute_with_distances(
+                    DistanceMatrix::Condensed(distances.view()),
+                    labels.view(),
+                    None,
+                )
+            }
+        }
+    }
+
+    pub fn compute(
+        &self,
+        source: &DistanceSource,
+        n: usize,
+        y: &ArrayView1<i32>,
+    ) -> Result<SampledValue, CalcError> {
+        let repeats = self.config.repeats;
+        if repeats == 0 || self.config.size < 2 {
+            return Err("Sampling requires at least one repeat of two observations".into());
+        }
+        if y.len() != n {
+            return Err(CalcError::shape(
+                "Number of labels doesnt match number of observations",
+            ));
+        }
+        let seed = self.config.seed.unwrap_or_else(rand::random);
+        let runs = (0..repeats)
+            .into_par_iter()
+            .map(|r| {
+                // every repeat gets its own stream so results dont depend on scheduling
+                let mut rng = ChaCha8Rng::seed_from_u64(seed);
+                rng.set_stream(r as u64);
+                self.run(source, y, &mut rng).scalars()
+            })
+            .collect::<Vec<_>>();
+
+        let estimates = self
+            .indexes
+            .iter()
+            .map(|index| {
+                let values = runs
+                    .iter()
+                    .map(|scalars| {
+                        scalars
+                            .iter()
+                            .find(|(name, _)| *name == index.name())
+                            .and_then(|(_, v)| v.clone())
+                            .unwrap_or_else(|| Err("Index missing from subsample".into()))
+                    })
+                    .collect::<Result<Vec<f64>, CalcError>>();
+                (*index, values.map(|v| estimate(&v)))
+            })
+            .collect();
+        Ok(SampledValue { estimates })
+    }
+}
+
+fn estimate(values: &[f64]) -> SampledEstimate {
+    let r = values.len() as f64;
+    let val = values.iter().sum::<f64>() / r;
+    let std = if values.len() > 1 {
+        (values.iter().map(|v| (v - val).powi(2)).sum::<f64>() / (r - 1.)).sqrt()
+    } else {
+        f64::NAN
+    };
+    let half = Z_95 * std / r.sqrt();
+    SampledEstimate {
+        val,
+        std,
+        low: val - half,
+        high: val + half,
+        repeats: values.len(),
+    }
+}
+
+pub fn node(
+    config: SamplingConfig,
+    indexes: Vec<PairIndex>,
+    engine: PairwiseEngine,
+) -> Node<SampledValue> {
+    let index = Sampling {
+        config,
+        indexes,
+        engine,
+    };
+    Node::new("sampling", move |scope, (metric,): (Metric,)| {
+        let y = scope.labels()?;
+        let (raw_data, precomputed) = (scope.raw_data(), scope.precomputed());
+        let (source, n) = distance_source(&raw_data, &metric, &precomputed)?;
+        index.compute(&source, n, &y)
+    })
+}
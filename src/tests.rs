@@ -1,6 +1,24 @@
-use crate::index_tree::IndexTreeBuilder;
+use crate::batch::compute_batch;
+use crate::calc_error::CalcError;
+use crate::dataflow::{Context, Graph, Node, Product, Products, Side};
+use crate::index_tree::{IndexTreeBuilder, IndexTreeReturnValue};
+use crate::indexes::gap::{GapConfig, GapIndexValue, GapReference};
+use crate::indexes::helpers::adjacent::PreviousPartition;
+use crate::indexes::helpers::counts::{self, CountsValue};
+use crate::indexes::helpers::distances::DistanceMatrix;
+use crate::indexes::helpers::total_dispercion::{self, TDValue};
+use crate::indexes::helpers::{clusters_centroids, s_plus_and_minus, within_group_dispercion};
+use crate::indexes::{beale, duda, pseudot2};
+use crate::labels::{NoisePolicy, NormalizedPartition};
+use crate::metric::Metric;
+use crate::number_of_clusters::{select_number_of_clusters, DecisionRule, NumberOfClustersValue};
+use crate::plugin::{Helper, IndexPlugin, PluginInput};
+use crate::sampling::{SamplingConfig, SamplingStrategy};
+use crate::validation::ValidationConfig;
 use assert_float_eq::*;
+use itertools::Itertools;
 use ndarray::{arr1, arr2, prelude::*};
+use std::{iter::zip, sync::Arc};
 
 const ACCURACY: f64 = 1e-5;
 fn initialize() -> (Array2<f64>, Array1<i32>) {
@@ -446,3 +464,1318 @@ fn test_sdbw_index() {
     println!("Duration {} milisecs", (end - start).as_millis());
     assert_float_absolute_eq!(res.sdbw.unwrap().unwrap().val, 0.02584332, ACCURACY)
 }
+#[test]
+fn test_sd_and_sdbw_terms() {
+    // two clusters far apart, no observation lies within the average stdev of a centroid
+    let x = arr2(&[[0., 0.], [10., 0.], [100., 0.], [110., 0.]]);
+    let y = arr1(&[0, 0, 1, 1]);
+    let tree = IndexTreeBuilder::default().add_sd().add_sdbw().finish();
+    let res = tree.compute((x.view(), y.view()));
+    // variance norm of the clusters relative to the one of the whole dataset
+    let scat = 25. / 2525.;
+    // both centroids are 100 apart
+    assert_float_absolute_eq!(res.sd.unwrap().unwrap().val, scat + 2. / 100., ACCURACY);
+    // pairs without density around their centroids add nothing
+    assert_float_absolute_eq!(res.sdbw.unwrap().unwrap().val, scat, ACCURACY);
+}
+fn relabel(x: &ArrayView2<f64>, y: &ArrayView1<i32>, k: usize) -> Array1<i32> {
+    // k = 2 merges two blobs, k > 3 splits the first k - 3 blobs by their mean first feature
+    let mut labels = y.to_owned();
+    if k == 2 {
+        labels.mapv_inplace(|c| if c == 2 { 0 } else { c });
+    }
+    for c in 0..k.saturating_sub(3) {
+        let members = (0..y.len())
+            .filter(|i| y[*i] == c as i32)
+            .collect::<Vec<usize>>();
+        let mean = members.iter().map(|i| x[[*i, 0]]).sum::<f64>() / members.len() as f64;
+        for i in members {
+            if x[[i, 0]] < mean {
+                labels[i] = (c + 3) as i32;
+            }
+        }
+    }
+    labels
+}
+#[test]
+fn test_number_of_clusters_selection() {
+    let (x, y) = initialize();
+    let (x, y) = (x.view(), y.view());
+    let labelings = (2..=6).map(|k| (k, relabel(&x, &y, k)));
+
+    let res = select_number_of_clusters(x, labelings, NoisePolicy::Exclude, |b| {
+        b.add_calinski_harabasz()
+            .add_davies_bouldin()
+            .add_dunn()
+            .add_tracew()
+    })
+    .unwrap();
+
+    assert_eq!(res.ks, vec![2, 3, 4, 5, 6]);
+    for choice in res.choices.iter() {
+        assert_eq!(*choice.best_k.as_ref().unwrap(), 3, "{}", choice.index);
+    }
+    assert_eq!(res.best_k, Some(3));
+
+    // arbitrary ids and noise go through the noise policy
+    let labelings = (2..=6).map(|k| {
+        let mut y = relabel(&x, &y, k).mapv(|c| c + 10);
+        y[0] = -1;
+        (k, y)
+    });
+    let res = select_number_of_clusters(x, labelings, NoisePolicy::Exclude, |b| {
+        b.add_calinski_harabasz().add_silhouette()
+    })
+    .unwrap();
+    assert_eq!(res.best_k, Some(3));
+    let silhouette = res.results[1]
+        .silhouette
+        .as_ref()
+        .unwrap()
+        .as_ref()
+        .unwrap();
+    assert_eq!(silhouette.nearest[0], -1);
+    assert_eq!(
+        res.results[1].cluster_ids.as_ref().unwrap().to_vec(),
+        vec![10, 11, 12]
+    );
+}
+#[test]
+fn test_decision_rules() {
+    let ks = [2, 3, 4, 5];
+    let values = [1., 4., 5., 5.5];
+    assert_eq!(DecisionRule::Max.select(&ks, &values).unwrap(), 5);
+    assert_eq!(DecisionRule::Min.select(&ks, &values).unwrap(), 2);
+    assert_eq!(DecisionRule::MaxDifference.select(&ks, &values).unwrap(), 3);
+    assert_eq!(
+        DecisionRule::MaxSecondDifference
+            .select(&ks, &values)
+            .unwrap(),
+        4
+    );
+    assert_eq!(
+        DecisionRule::MinSecondDifference
+            .select(&ks, &values)
+            .unwrap(),
+        3
+    );
+    assert!(DecisionRule::MaxSecondDifference
+        .select(&ks[..2], &values[..2])
+        .is_err());
+}
+#[test]
+fn test_gap_rule() {
+    let ks = vec![2, 3, 4, 5];
+    // gap(3) >= gap(4) - s(4) while gap(2) < gap(3) - s(3)
+    let values = [0.5, 1.0, 1.1, 1.2];
+    let s = [0.05, 0.05, 0.2, 0.05];
+    assert_eq!(DecisionRule::select_gap(&ks, &values, &s).unwrap(), 3);
+    assert!(DecisionRule::select_gap(&ks, &values, &[0.; 4]).is_err());
+    assert!(DecisionRule::Gap.select(&ks, &values).is_err());
+
+    // the vote of gap is counted with the other indexes
+    let results = zip(values, s)
+        .map(|(val, s_k)| IndexTreeReturnValue {
+            gap: Some(Ok(GapIndexValue { val, s_k })),
+            ..Default::default()
+        })
+        .collect();
+    let res = NumberOfClustersValue::from_results(ks, results);
+    let gap = res.choices.iter().find(|c| c.index == "gap").unwrap();
+    assert_eq!(gap.rule, DecisionRule::Gap);
+    assert_eq!(*gap.best_k.as_ref().unwrap(), 3);
+    assert_eq!(res.votes, vec![(3, 1)]);
+    assert_eq!(res.best_k, Some(3));
+}
+#[test]
+fn test_kl_index() {
+    let (x, y) = initialize();
+    let (x, y) = (x.view(), y.view());
+    let (previous, next) = (relabel(&x, &y, 2), relabel(&x, &y, 4));
+
+    let tree = IndexTreeBuilder::default().add_kl().finish();
+
+    let start = std::time::Instant::now();
+    let res = tree.compute_with_adjacent((x, y), Some(previous.view()), Some(next.view()));
+    let end = std::time::Instant::now();
+    //
+    println!("Duration {} milisecs", (end - start).as_millis());
+    assert_float_absolute_eq!(res.kl.unwrap().unwrap().val, 28.754433173316926, ACCURACY)
+}
+#[test]
+fn test_hartigan_index() {
+    let (x, y) = initialize();
+    let (x, y) = (x.view(), y.view());
+    let next = relabel(&x, &y, 4);
+
+    let tree = IndexTreeBuilder::default().add_hartigan().finish();
+
+    let start = std::time::Instant::now();
+    let res = tree.compute_with_adjacent((x, y), None, Some(next.view()));
+    let end = std::time::Instant::now();
+    //
+    println!("Duration {} milisecs", (end - start).as_millis());
+    assert_float_absolute_eq!(
+        res.hartigan.unwrap().unwrap().val,
+        9.350373000166286,
+        ACCURACY
+    )
+}
+#[test]
+fn test_frey_index() {
+    let (x, y) = initialize();
+    let (x, y) = (x.view(), y.view());
+    let next = relabel(&x, &y, 4);
+
+    let tree = IndexTreeBuilder::default().add_frey().finish();
+
+    let start = std::time::Instant::now();
+    let res = tree.compute_with_adjacent((x, y), None, Some(next.view()));
+    let end = std::time::Instant::now();
+    //
+    println!("Duration {} milisecs", (end - start).as_millis());
+    assert_float_absolute_eq!(res.frey.unwrap().unwrap().val, 16.73354862480484, ACCURACY)
+}
+#[test]
+fn test_adjacent_partition_missing() {
+    let (x, y) = initialize();
+    let (x, y) = (x.view(), y.view());
+
+    let tree = IndexTreeBuilder::default().add_kl().add_dunn().finish();
+    let res = tree.compute((x, y));
+    assert!(res.kl.unwrap().is_err());
+    assert!(res.dunn.unwrap().is_ok());
+}
+#[test]
+fn test_split_indexes() {
+    let (x, y) = initialize();
+    let (x, y) = (x.view(), y.view());
+    // the whole dataset as the parent
+    let split = relabel(&x, &y, 2);
+    let (x_all, split) = (x.view(), split.view());
+
+    let start = std::time::Instant::now();
+    let duda = duda::Index.compute(&x_all, &split, None).unwrap();
+    let pseudot2 = pseudot2::Index.compute(&x_all, &split, None).unwrap();
+    let beale = beale::Index.compute(&x_all, &split, None).unwrap();
+    let end = std::time::Instant::now();
+    //
+    println!("Duration {} milisecs", (end - start).as_millis());
+    assert_float_absolute_eq!(duda.val, 0.2860476949608538, ACCURACY);
+    assert_float_absolute_eq!(pseudot2.val, 244.60020872886776, ACCURACY);
+    assert_float_absolute_eq!(beale.val, 2.446002087288678, ACCURACY);
+    assert!(!duda.accepted() && !pseudot2.accepted() && !beale.accepted());
+    assert!(duda::Index.compute(&x_all, &y, None).is_err());
+
+    // the partition with one cluster more splits cluster 0 of y
+    let next = relabel(&x, &y, 4);
+    let tree = IndexTreeBuilder::default()
+        .add_duda()
+        .add_pseudot2()
+        .add_beale()
+        .finish();
+    let res = tree.compute_with_adjacent((x, y), None, Some(next.view()));
+    let rows = (0..y.len()).filter(|i| y[*i] == 0).collect::<Vec<usize>>();
+    let parent = x.select(Axis(0), &rows);
+    let children = rows
+        .iter()
+        .map(|i| i32::from(next[*i] == 3))
+        .collect::<Array1<i32>>();
+    let (parent, children) = (parent.view(), children.view());
+    let duda = duda::Index.compute(&parent, &children, None).unwrap();
+    let pseudot2 = pseudot2::Index.compute(&parent, &children, None).unwrap();
+    let beale = beale::Index.compute(&parent, &children, None).unwrap();
+    assert_eq!(res.duda.unwrap().unwrap().val, duda.val);
+    assert_eq!(res.pseudot2.unwrap().unwrap().val, pseudot2.val);
+    assert_eq!(res.beale.unwrap().unwrap().val, beale.val);
+
+    // next must split exactly one cluster of the evaluated partition
+    let tree = || IndexTreeBuilder::default().add_duda().finish();
+    assert!(tree().compute((x, y)).duda.unwrap().is_err());
+    let two_splits = relabel(&x, &y, 5);
+    let res = tree().compute_with_adjacent((x, y), None, Some(two_splits.view()));
+    assert!(res.duda.unwrap().is_err());
+    let mut shuffled = next.clone();
+    shuffled.swap(0, 1);
+    let res = tree().compute_with_adjacent((x, y), None, Some(shuffled.view()));
+    assert!(res.duda.unwrap().is_err());
+}
+#[test]
+fn test_beale_p_value() {
+    // the F distribution with 1 and 1 degrees of freedom has cdf 2 / pi atan(sqrt(f))
+    let x = arr2(&[[0.], [1.], [3.]]);
+    let y = arr1(&[0, 0, 1]);
+    let beale = beale::Index.compute(&x.view(), &y.view(), None).unwrap();
+    let expected = 1. - 2. / std::f64::consts::PI * beale.val.sqrt().atan();
+    assert_float_absolute_eq!(beale.p_value, expected, 1e-10);
+}
+#[test]
+fn test_split_rule() {
+    let ks = [2, 3, 4, 5];
+    assert_eq!(
+        DecisionRule::select_first_accepted(&ks, &[false, true, false, true]).unwrap(),
+        3
+    );
+    assert!(DecisionRule::select_first_accepted(&ks, &[false; 4]).is_err());
+    assert!(DecisionRule::FirstAccepted.select(&ks, &[1.; 4]).is_err());
+
+    let (x, y) = initialize();
+    let (x, y) = (x.view(), y.view());
+    let labelings = (2..=6).map(|k| (k, relabel(&x, &y, k)));
+    let res = select_number_of_clusters(x, labelings, NoisePolicy::Exclude, |b| {
+        b.add_duda().add_pseudot2().add_beale()
+    })
+    .unwrap();
+    assert_eq!(res.choices.len(), 3);
+    for choice in res.choices.iter() {
+        assert_eq!(choice.rule, DecisionRule::FirstAccepted);
+        assert_eq!(*choice.best_k.as_ref().unwrap(), 3, "{}", choice.index);
+    }
+    assert_eq!(res.best_k, Some(3));
+}
+fn gap<'a>(x: ArrayView2<'a, f64>, y: ArrayView1<'a, i32>, config: GapConfig) -> f64 {
+    let tree = IndexTreeBuilder::default().add_gap(config).finish();
+    let res = tree.compute((x, y)).gap.unwrap().unwrap();
+    assert!(res.s_k > 0.);
+    res.val
+}
+#[test]
+fn test_gap_index() {
+    let (x, y) = initialize();
+    let (x, y) = (x.view(), y.view());
+    let merged = relabel(&x, &y, 2);
+
+    for reference in [GapReference::Uniform, GapReference::Pca] {
+        let config = GapConfig {
+            reference,
+            b: 20,
+            seed: Some(42),
+        };
+        let start = std::time::Instant::now();
+        let val = gap(x, y, config);
+        let end = std::time::Instant::now();
+        //
+        println!("Duration {} milisecs", (end - start).as_millis());
+        // seeded draws are reproducible
+        assert_eq!(val, gap(x, y, config));
+        // the three blobs are better separated than the merged partition
+        assert!(val > gap(x, merged.view(), config));
+    }
+}
+#[test]
+fn test_ccc_index() {
+    let (x, y) = initialize();
+    let (x, y) = (x.view(), y.view());
+
+    let tree = IndexTreeBuilder::default().add_ccc().finish();
+
+    let start = std::time::Instant::now();
+    let res = tree.compute((x, y));
+    let end = std::time::Instant::now();
+    //
+    println!("Duration {} milisecs", (end - start).as_millis());
+    // Sarle's CCC as defined by SAS, the total scatter taken about the mean. Reference value
+    // from a standalone python transcription of the ccc formula of NbClust's Indices.WBT
+    // run on this fixture, applied to the uncentered data it gives 37.7287 instead
+    assert_float_absolute_eq!(res.ccc.unwrap().unwrap().val, 34.82031057373301, ACCURACY)
+}
+#[test]
+fn test_dindex() {
+    let (x, y) = initialize();
+    let (x, y) = (x.view(), y.view());
+    let labelings = (2..=5).map(|k| (k, relabel(&x, &y, k)));
+
+    let res =
+        select_number_of_clusters(x, labelings, NoisePolicy::Exclude, |b| b.add_dindex()).unwrap();
+    let curve = res.curve("dindex").unwrap();
+
+    assert_eq!(curve.ks, vec![2, 3, 4, 5]);
+    assert_float_absolute_eq!(curve.values[1], 1.1764800970384093, ACCURACY);
+    assert!(curve.second_differences[0].is_nan());
+    assert_float_absolute_eq!(curve.second_differences[1], 2.543962132959578, ACCURACY);
+    assert_float_absolute_eq!(
+        curve.second_differences[2],
+        -0.0031368083825669135,
+        ACCURACY
+    );
+    assert!(curve.second_differences[3].is_nan());
+    assert_eq!(res.best_k, Some(3));
+}
+#[test]
+fn test_external_indexes() {
+    let (x, y) = initialize();
+    let (x, y) = (x.view(), y.view());
+    let y_pred = relabel(&x, &y, 4);
+
+    let tree = IndexTreeBuilder::default()
+        .add_adjusted_rand()
+        .add_rand()
+        .add_jaccard()
+        .add_fowlkes_mallows()
+        .add_nmi()
+        .add_ami()
+        .add_v_measure()
+        .add_purity()
+        .add_contingency()
+        .finish();
+
+    let start = std::time::Instant::now();
+    let res = tree.compute_with_reference((x, y_pred.view()), y);
+    let end = std::time::Instant::now();
+    //
+    println!("Duration {} milisecs", (end - start).as_millis());
+    assert_eq!(
+        res.contingency.unwrap().unwrap().val,
+        arr2(&[[18, 0, 0, 16], [0, 33, 0, 0], [0, 0, 33, 0]])
+    );
+    let v_measure = res.v_measure.unwrap().unwrap();
+    assert_float_absolute_eq!(
+        res.adjusted_rand.unwrap().unwrap().val,
+        0.8613865198390542,
+        ACCURACY
+    );
+    assert_float_absolute_eq!(res.rand.unwrap().unwrap().val, 0.9418181818181818, ACCURACY);
+    assert_float_absolute_eq!(
+        res.jaccard.unwrap().unwrap().val,
+        0.8218923933209648,
+        ACCURACY
+    );
+    assert_float_absolute_eq!(
+        res.fowlkes_mallows.unwrap().unwrap().val,
+        0.9065828110663496,
+        ACCURACY
+    );
+    assert_float_absolute_eq!(res.nmi.unwrap().unwrap().val, 0.9033424535435324, ACCURACY);
+    assert_float_absolute_eq!(res.ami.unwrap().unwrap().val, 0.9007910326787346, ACCURACY);
+    assert_float_absolute_eq!(v_measure.val, 0.9033424535435323, ACCURACY);
+    assert_float_absolute_eq!(v_measure.homogeneity, 1., ACCURACY);
+    assert_float_absolute_eq!(v_measure.completeness, 0.8237233733196135, ACCURACY);
+    assert_float_absolute_eq!(res.purity.unwrap().unwrap().val, 1., ACCURACY);
+}
+#[test]
+fn test_external_indexes_without_reference() {
+    let (x, y) = initialize();
+    let (x, y) = (x.view(), y.view());
+
+    let tree = IndexTreeBuilder::default().add_rand().finish();
+    let res = tree.compute((x, y));
+    assert!(res.rand.unwrap().is_err());
+}
+#[test]
+fn test_metrics() {
+    let (x, y) = initialize();
+    let (x, y) = (x.view(), y.view());
+    let dunn = |metric: Metric| {
+        let tree = IndexTreeBuilder::default()
+            .metric(metric)
+            .add_dunn()
+            .finish();
+        tree.compute((x, y)).dunn.unwrap().unwrap().val
+    };
+
+    assert_float_absolute_eq!(dunn(Metric::Manhattan), 1.3736165728594811, ACCURACY);
+    assert_float_absolute_eq!(dunn(Metric::Chebyshev), 0.9556146510995961, ACCURACY);
+    assert_float_absolute_eq!(dunn(Metric::Minkowski(3.)), 1.1984225879886234, ACCURACY);
+    // metrics coinciding with the euclidean distance
+    let identity = Array2::<f64>::eye(2).into_shared();
+    let custom = Metric::Custom(Arc::new(|a, b| Ok((&a - &b).pow2().sum().sqrt())));
+    for metric in [
+        Metric::Minkowski(2.),
+        Metric::Mahalanobis(Some(identity)),
+        custom,
+    ] {
+        assert_float_absolute_eq!(dunn(metric), 1.3200068345490268, ACCURACY);
+    }
+}
+#[test]
+fn test_metric_errors() {
+    let (x, y) = initialize();
+    let (x, y) = (x.view(), y.view());
+    let custom = Metric::Custom(Arc::new(|_, _| Err("Custom metric failed".into())));
+
+    let tree = IndexTreeBuilder::default()
+        .metric(custom)
+        .add_silhouette()
+        .add_dunn()
+        .add_calinski_harabasz()
+        .finish();
+    let res = tree.compute((x, y));
+    assert!(res.silhouette.unwrap().is_err());
+    assert!(res.dunn.unwrap().is_err());
+    assert!(res.calinski_harabasz.unwrap().is_ok());
+}
+#[test]
+fn test_precomputed_distances() {
+    let (x, y) = initialize();
+    let n = x.nrows();
+    let square =
+        Array2::from_shape_fn((n, n), |(i, j)| (&x.row(i) - &x.row(j)).pow2().sum().sqrt());
+    let condensed = Array1::from_iter(
+        (0..n)
+            .flat_map(|i| (i + 1..n).map(move |j| (i, j)))
+            .map(|(i, j)| square[[i, j]]),
+    );
+    let tree = || {
+        IndexTreeBuilder::default()
+            .add_silhouette()
+            .add_dunn()
+            .add_c_index()
+            .add_calinski_harabasz()
+            .add_adjusted_rand()
+            .finish()
+    };
+
+    let expected = tree().compute_with_reference((x.view(), y.view()), y.view());
+    for distances in [
+        DistanceMatrix::Condensed(condensed.view()),
+        DistanceMatrix::Square(square.view()),
+    ] {
+        let res = tree().compute_with_distances(distances, y.view(), Some(y.view()));
+        assert_float_absolute_eq!(
+            res.silhouette.unwrap().unwrap().val,
+            expected.silhouette.clone().unwrap().unwrap().val,
+            ACCURACY
+        );
+        assert_float_absolute_eq!(
+            res.dunn.unwrap().unwrap().val,
+            expected.dunn.clone().unwrap().unwrap().val,
+            ACCURACY
+        );
+        assert_float_absolute_eq!(
+            res.c_index.unwrap().unwrap().val,
+            expected.c_index.clone().unwrap().unwrap().val,
+            ACCURACY
+        );
+        assert_float_absolute_eq!(res.adjusted_rand.unwrap().unwrap().val, 1., ACCURACY);
+        let err = res.calinski_harabasz.unwrap().unwrap_err();
+        assert!(format!("{err:?}").contains("requires feature data"));
+    }
+
+    let res = tree().compute_with_distances(
+        DistanceMatrix::Condensed(condensed.slice(s![1..])),
+        y.view(),
+        None,
+    );
+    assert!(res.dunn.unwrap().is_err());
+}
+#[test]
+fn test_s_plus_and_minus_with_ties() {
+    // integer coordinates give many equal within and between cluster distances
+    let x = arr2(&[
+        [0., 0.],
+        [1., 0.],
+        [0., 1.],
+        [3., 3.],
+        [2., 2.],
+        [3., 2.],
+        [1., 1.],
+        [4., 4.],
+        [0., 2.],
+        [2., 0.],
+    ]);
+    let y = arr1(&[0, 0, 0, 1, 1, 1, 0, 1, 2, 2]);
+    let tree = IndexTreeBuilder::default()
+        .add_gamma()
+        .add_gplus()
+        .add_helper(Helper::PairsAndDistances)
+        .finish();
+    let res = tree.compute((x.view(), y.view()));
+
+    // the counts of the quadratic loop comparing a within-cluster pair with the
+    // between-cluster pairs after it
+    let pairs = res.pairs_and_distances.unwrap().unwrap();
+    let mut expected = (0, 0, 0);
+    for (i, (d1, b1)) in zip(&pairs.distances, &pairs.pairs).enumerate() {
+        for (d2, b2) in zip(&pairs.distances, &pairs.pairs).skip(i + 1) {
+            if *b1 == 1 && *b2 == 0 {
+                if d1 < d2 {
+                    expected.0 += 1;
+                }
+                if d1 > d2 {
+                    expected.1 += 1;
+                }
+                if d1 == d2 {
+                    expected.2 += 1;
+                }
+            }
+        }
+    }
+    let counts = s_plus_and_minus::Index
+        .compute(&pairs.pairs.view(), &pairs.distances.view())
+        .unwrap();
+    assert_eq!(counts, expected);
+    assert!(expected.2 > 0);
+
+    assert_float_absolute_eq!(
+        res.gamma.unwrap().unwrap().val,
+        0.8846153846153846,
+        ACCURACY
+    );
+    assert_float_absolute_eq!(
+        res.gplus.unwrap().unwrap().val,
+        0.012121212121212121,
+        ACCURACY
+    );
+}
+#[test]
+fn test_memory_budget() {
+    let (x, y) = initialize();
+    let (x, y) = (x.view(), y.view());
+    for metric in [Metric::Euclidean, Metric::Manhattan] {
+        let builder = || {
+            IndexTreeBuilder::default()
+                .metric(metric.clone())
+                .add_dunn()
+                .add_mcclain()
+                .add_ptbiserial()
+                .add_c_index()
+                .add_silhouette()
+        };
+        let expected = builder().finish().compute((x, y));
+        // every block holds a single row
+        let res = builder().memory_budget(1).finish().compute((x, y));
+        for ((_, val), (_, expected)) in res.scalars().into_iter().zip(expected.scalars()) {
+            if let (Some(val), Some(expected)) = (val, expected) {
+                assert_float_absolute_eq!(val.unwrap(), expected.unwrap(), ACCURACY);
+            }
+        }
+    }
+}
+#[test]
+fn test_sampling() {
+    let (x, y) = initialize();
+    let (x, y) = (x.view(), y.view());
+    let builder = || {
+        IndexTreeBuilder::default()
+            .add_silhouette()
+            .add_c_index()
+            .add_gamma()
+            .add_calinski_harabasz()
+    };
+    let expected = builder().finish().compute((x, y));
+
+    // subsamples holding every observation reproduce the exact values
+    let config = SamplingConfig {
+        size: x.nrows(),
+        repeats: 3,
+        seed: Some(0),
+        ..Default::default()
+    };
+    let res = builder().sampling(config).finish().compute((x, y));
+    assert_float_absolute_eq!(
+        res.silhouette.unwrap().unwrap().val,
+        expected.silhouette.clone().unwrap().unwrap().val,
+        ACCURACY
+    );
+    assert_eq!(res.estimates.len(), 3);
+    for (_, estimate) in res.estimates {
+        assert_float_absolute_eq!(estimate.high - estimate.low, 0., ACCURACY);
+    }
+    assert!(res.calinski_harabasz.unwrap().is_ok());
+
+    for strategy in [SamplingStrategy::Uniform, SamplingStrategy::Stratified] {
+        let config = SamplingConfig {
+            size: 30,
+            strategy,
+            repeats: 20,
+            seed: Some(42),
+        };
+        let res = builder().sampling(config).finish().compute((x, y));
+        let again = builder().sampling(config).finish().compute((x, y));
+        let (_, estimate) = res.estimates[0];
+        assert_eq!(estimate.repeats, 20);
+        assert!(estimate.low <= estimate.val && estimate.val <= estimate.high);
+        assert_float_absolute_eq!(estimate.val, again.estimates[0].1.val, ACCURACY);
+        let silhouette = res.silhouette.unwrap().unwrap().val;
+        assert_float_absolute_eq!(silhouette, estimate.val, ACCURACY);
+        assert!((silhouette - expected.silhouette.clone().unwrap().unwrap().val).abs() < 0.05);
+    }
+}
+#[test]
+fn test_silhouette_samples() {
+    let x = arr2(&[
+        [0., 0.],
+        [1., 0.],
+        [0., 1.],
+        [3., 3.],
+        [2., 2.],
+        [3., 2.],
+        [1., 1.],
+        [4., 4.],
+        [0., 2.],
+        [2., 0.],
+    ]);
+    let y = arr1(&[0, 0, 0, 1, 1, 1, 0, 1, 2, 2]);
+    let tree = IndexTreeBuilder::default().add_silhouette().finish();
+    let res = tree
+        .compute((x.view(), y.view()))
+        .silhouette
+        .unwrap()
+        .unwrap();
+
+    let samples = [
+        0.430964406271,
+        0.296633324534,
+        0.296633324534,
+        0.596448347661,
+        0.126226552147,
+        0.460655337083,
+        0.195262145876,
+        0.517105567316,
+        -0.412192603295,
+        -0.412192603295,
+    ];
+    for (s, expected) in res.samples.iter().zip(samples) {
+        assert_float_absolute_eq!(*s, expected, ACCURACY);
+    }
+    let clusters = [0.30487330030379123, 0.4251089510519329, -0.412192603294521];
+    for (s, expected) in res.clusters.iter().zip(clusters) {
+        assert_float_absolute_eq!(*s, expected, ACCURACY);
+    }
+    assert_eq!(res.nearest, arr1(&[2, 2, 2, 2, 2, 2, 2, 2, 0, 0]));
+    assert_float_absolute_eq!(res.val, 0.20955437988338543, ACCURACY);
+}
+
+#[test]
+fn test_cluster_details() {
+    let x = arr2(&[
+        [0., 0.],
+        [1., 0.],
+        [0., 1.],
+        [3., 3.],
+        [2., 2.],
+        [3., 2.],
+        [1., 1.],
+        [4., 4.],
+        [0., 2.],
+        [2., 0.],
+    ]);
+    let y = arr1(&[0, 0, 0, 1, 1, 1, 0, 1, 2, 2]);
+    let tree = IndexTreeBuilder::default()
+        .add_davies_bouldin()
+        .add_dunn()
+        .add_sd()
+        .add_sdbw()
+        .finish();
+    let res = tree.compute((x.view(), y.view()));
+
+    let db = res.davies_bouldin.unwrap().unwrap();
+    assert_float_absolute_eq!(db.ratios.mean().unwrap(), db.val, ACCURACY);
+    assert_float_absolute_eq!(db.ratios[0], 3.0, ACCURACY);
+    assert_eq!(db.worst, arr1(&[2, 2, 0]));
+
+    let dunn = res.dunn.unwrap().unwrap();
+    let min_separation = dunn
+        .separations
+        .iter()
+        .filter(|v| !v.is_nan())
+        .fold(f64::INFINITY, |a, b| a.min(*b));
+    let max_diameter = dunn.diameters.fold(0., |a: f64, b| a.max(*b));
+    assert_float_absolute_eq!(dunn.val, min_separation / max_diameter, ACCURACY);
+    assert_eq!(dunn.separations.dim(), (3, 3));
+    assert!(dunn.separations[[1, 1]].is_nan());
+    assert_eq!(dunn.separations[[0, 2]], dunn.separations[[2, 0]]);
+
+    let sd = res.sd.unwrap().unwrap();
+    let scat = sd.scat.mean().unwrap();
+    let max_distance = sd.centroid_distances.fold(0., |a: f64, b| a.max(*b));
+    let min_distance = sd
+        .centroid_distances
+        .iter()
+        .filter(|v| **v > 0.)
+        .fold(f64::INFINITY, |a, b| a.min(*b));
+    assert_float_absolute_eq!(
+        sd.val,
+        scat + sd.dis.sum() * max_distance / min_distance,
+        ACCURACY
+    );
+
+    // ratios are no longer truncated to integers
+    let sdbw = res.sdbw.unwrap().unwrap();
+    let ratios = sdbw
+        .density_ratios
+        .iter()
+        .filter(|v| !v.is_nan())
+        .sum::<f64>();
+    assert_float_absolute_eq!(sdbw.val, scat + ratios / 6., ACCURACY);
+}
+
+#[test]
+fn test_noise_labels() {
+    let x = arr2(&[
+        [0., 0.],
+        [1., 0.],
+        [0., 1.],
+        [3., 3.],
+        [2., 2.],
+        [3., 2.],
+        [1., 1.],
+        [4., 4.],
+        [0., 2.],
+        [2., 0.],
+        [9., 9.],
+        [-5., 7.],
+    ]);
+    // ids {3, 7, 42} with two noise observations
+    let y = arr1(&[3, 3, 3, 7, 7, 7, 3, 7, 42, 42, -1, -1]);
+    let clean = arr1(&[0, 0, 0, 1, 1, 1, 0, 1, 2, 2]);
+    let tree = || {
+        IndexTreeBuilder::default()
+            .add_silhouette()
+            .add_davies_bouldin()
+            .finish()
+    };
+    let expected = tree().compute((x.slice(s![..10, ..]), clean.view()));
+
+    let data = NormalizedPartition::new(x.view(), y.view(), NoisePolicy::Exclude).unwrap();
+    assert_eq!(data.labels(), clean);
+    let res = tree().compute_normalized(&data);
+    let silhouette = res.silhouette.unwrap().unwrap();
+    let db = res.davies_bouldin.unwrap().unwrap();
+    assert_eq!(res.cluster_ids.unwrap(), arr1(&[3, 7, 42]));
+    assert_float_absolute_eq!(
+        silhouette.val,
+        expected.silhouette.unwrap().unwrap().val,
+        ACCURACY
+    );
+    assert_float_absolute_eq!(
+        db.val,
+        expected.davies_bouldin.unwrap().unwrap().val,
+        ACCURACY
+    );
+    // noise gets no silhouette, cluster indexes refer to the original ids
+    assert_eq!(silhouette.samples.len(), 12);
+    assert!(silhouette.samples[10].is_nan() && silhouette.samples[11].is_nan());
+    assert_eq!(silhouette.nearest[0], 42);
+    assert_eq!(silhouette.nearest[10], -1);
+    assert_eq!(db.worst, arr1(&[42, 42, 3]));
+
+    let data = NormalizedPartition::new(x.view(), y.view(), NoisePolicy::Singletons).unwrap();
+    assert_eq!(data.labels(), arr1(&[0, 0, 0, 1, 1, 1, 0, 1, 2, 2, 3, 4]));
+    assert_eq!(data.cluster_ids(), arr1(&[3, 7, 42, -1, -1]));
+
+    let data = NormalizedPartition::new(x.view(), y.view(), NoisePolicy::Cluster).unwrap();
+    assert_eq!(data.labels(), arr1(&[0, 0, 0, 1, 1, 1, 0, 1, 2, 2, 3, 3]));
+    let res = tree().compute_normalized(&data);
+    assert_eq!(res.cluster_ids.unwrap(), arr1(&[3, 7, 42, -1]));
+    assert!(res.silhouette.unwrap().is_ok());
+
+    // noise of an adjacent partition follows the same policy instead of joining a cluster
+    let next = arr1(&[5, 5, 5, 5, 5, 5, 5, 5, 9, -1, 9, -1]);
+    let adjacent = |policy| {
+        NormalizedPartition::new(x.view(), y.view(), policy)
+            .unwrap()
+            .with_adjacent(None, Some(next.view()))
+            .unwrap()
+    };
+    assert_eq!(
+        adjacent(NoisePolicy::Cluster).next().unwrap(),
+        arr1(&[0, 0, 0, 0, 0, 0, 0, 0, 1, 2, 1, 2])
+    );
+    assert_eq!(
+        adjacent(NoisePolicy::Singletons).next().unwrap(),
+        arr1(&[0, 0, 0, 0, 0, 0, 0, 0, 1, 2, 1, 3])
+    );
+    assert_eq!(
+        adjacent(NoisePolicy::Exclude).next().unwrap(),
+        arr1(&[0, 0, 0, 0, 0, 0, 0, 0, 1, 2])
+    );
+
+    // noise is left out of the distances too
+    let n = x.nrows();
+    let square =
+        Array2::from_shape_fn((n, n), |(i, j)| (&x.row(i) - &x.row(j)).pow2().sum().sqrt());
+    let distances = DistanceMatrix::Square(square.view());
+    let data =
+        NormalizedPartition::from_distances(distances, y.view(), NoisePolicy::Exclude).unwrap();
+    let res = IndexTreeBuilder::default()
+        .add_silhouette()
+        .finish()
+        .compute_normalized(&data);
+    assert_float_absolute_eq!(
+        res.silhouette.unwrap().unwrap().val,
+        silhouette.val,
+        ACCURACY
+    );
+}
+
+#[test]
+fn test_validation() {
+    let (x, y) = initialize();
+    let tree = || {
+        IndexTreeBuilder::default()
+            .add_silhouette()
+            .add_tau()
+            .finish()
+    };
+    let err = |res: IndexTreeReturnValue| res.silhouette.unwrap().unwrap_err();
+
+    let (empty, none) = (Array2::<f64>::zeros((0, 2)), Array1::<i32>::zeros(0));
+    let res = tree().compute((empty.view(), none.view()));
+    assert!(matches!(err(res), CalcError::Shape(_)));
+
+    let res = tree().compute((x.view(), y.slice(s![1..])));
+    assert!(matches!(err(res), CalcError::Shape(_)));
+
+    let mut nan = x.clone();
+    nan[[3, 1]] = f64::NAN;
+    let res = tree().compute((nan.view(), y.view()));
+    assert!(matches!(err(res), CalcError::NonFinite { observation: 3 }));
+
+    let mut gap = y.clone();
+    gap.mapv_inplace(|c| if c == 1 { 5 } else { c });
+    let res = tree().compute((x.view(), gap.view()));
+    assert!(matches!(err(res), CalcError::Labels(_)));
+
+    let noise = y.mapv(|c| if c == 0 { -1 } else { c });
+    let res = tree().compute((x.view(), noise.view()));
+    assert!(matches!(err(res), CalcError::Labels(_)));
+
+    let single = Array1::<i32>::zeros(y.len());
+    let res = tree().compute((x.view(), single.view()));
+    // every index gets the same error instead of a panic
+    assert!(matches!(
+        res.tau.unwrap().unwrap_err(),
+        CalcError::TooFewClusters { clusters: 1 }
+    ));
+
+    let all = Array1::from_iter(0..y.len() as i32);
+    let res = tree().compute((x.view(), all.view()));
+    assert!(matches!(err(res), CalcError::TooFewObservations { .. }));
+
+    let mut singleton = y.clone();
+    singleton[0] = 3;
+    let res = tree().compute((x.view(), singleton.view()));
+    assert!(res.silhouette.unwrap().is_ok());
+    let res = IndexTreeBuilder::default()
+        .validation(ValidationConfig {
+            reject_singletons: true,
+        })
+        .add_silhouette()
+        .finish()
+        .compute((x.view(), singleton.view()));
+    assert!(matches!(
+        err(res),
+        CalcError::SingletonCluster { cluster: 3 }
+    ));
+
+    let d = Array1::from_elem(x.nrows() * (x.nrows() - 1) / 2, f64::INFINITY);
+    let res = tree().compute_with_distances(DistanceMatrix::Condensed(d.view()), y.view(), None);
+    assert!(matches!(err(res), CalcError::NonFinite { observation: 0 }));
+}
+
+#[test]
+fn test_weights() {
+    let (x, y) = initialize();
+    let weights = Array1::from_iter((0..y.len()).map(|i| (1 + i % 3) as f64));
+    // an integer weight has to act like the observation repeated that many times
+    let repeated = Array1::from_iter(
+        weights
+            .iter()
+            .enumerate()
+            .flat_map(|(i, w)| std::iter::repeat_n(i, *w as usize)),
+    )
+    .to_vec();
+    let x_repeated = x.select(Axis(0), &repeated);
+    let y_repeated = y.select(Axis(0), &repeated);
+    let tree = || {
+        IndexTreeBuilder::default()
+            .add_calinski_harabasz()
+            .add_ball_hall()
+            .add_rubin()
+            .add_friedman()
+            .add_scott()
+            .add_mariott()
+            .add_ratkowsky()
+            .add_tracew()
+            .add_trcovw()
+            .finish()
+    };
+    let values = |res: IndexTreeReturnValue| {
+        vec![
+            res.calinski_harabasz.unwrap().unwrap().val,
+            res.ball_hall.unwrap().unwrap().val,
+            res.rubin.unwrap().unwrap().val,
+            res.friedman.unwrap().unwrap().val,
+            res.scott.unwrap().unwrap().val,
+            res.mariott.unwrap().unwrap().val,
+            res.ratkowsky.unwrap().unwrap().val,
+            res.tracew.unwrap().unwrap().val,
+            res.trcovw.unwrap().unwrap().val,
+        ]
+    };
+
+    let weighted = values(tree().compute_with_weights((x.view(), y.view()), weights.view()));
+    let expected = values(tree().compute((x_repeated.view(), y_repeated.view())));
+    for (weighted, expected) in zip(weighted, expected) {
+        assert_float_absolute_eq!(weighted, expected, ACCURACY);
+    }
+
+    let ones = Array1::ones(y.len());
+    let weighted = values(tree().compute_with_weights((x.view(), y.view()), ones.view()));
+    let expected = values(tree().compute((x.view(), y.view())));
+    for (weighted, expected) in zip(weighted, expected) {
+        assert_float_absolute_eq!(weighted, expected, ACCURACY);
+    }
+
+    let mut negative = weights.clone();
+    negative[2] = -1.;
+    let res = tree().compute_with_weights((x.view(), y.view()), negative.view());
+    assert!(matches!(
+        res.calinski_harabasz.unwrap().unwrap_err(),
+        CalcError::Weights(_)
+    ));
+
+    let tree = IndexTreeBuilder::default()
+        .add_gap(GapConfig::default())
+        .finish();
+    let res = tree.compute_with_weights((x.view(), y.view()), weights.view());
+    assert!(matches!(
+        res.gap.unwrap().unwrap_err(),
+        CalcError::Weights(_)
+    ));
+}
+
+#[test]
+fn test_batch() {
+    let (x, y) = initialize();
+    let labelings = vec![
+        y.clone(),
+        y.mapv(|c| c.min(1)),
+        Array1::from_iter((0..y.len()).map(|i| (i % 3) as i32)),
+    ];
+    fn configure(builder: IndexTreeBuilder) -> IndexTreeBuilder {
+        builder
+            .add_silhouette()
+            .add_dunn()
+            .add_calinski_harabasz()
+            .add_ratkowsky()
+    }
+    let res = compute_batch(x.view(), &labelings, None, NoisePolicy::Exclude, configure).unwrap();
+    let (names, table) = res.table();
+    assert_eq!(
+        names,
+        vec!["calinski_harabasz", "dunn", "silhouette", "ratkowsky"]
+    );
+    assert_eq!(table.dim(), (3, 4));
+    for (row, y) in labelings.iter().enumerate() {
+        let expected = configure(IndexTreeBuilder::default())
+            .finish()
+            .compute((x.view(), y.view()))
+            .scalars()
+            .into_iter()
+            .filter_map(|(_, v)| v.map(|v| v.unwrap()))
+            .collect::<Vec<f64>>();
+        for (col, expected) in expected.into_iter().enumerate() {
+            assert_float_absolute_eq!(table[[row, col]], expected, ACCURACY);
+        }
+    }
+
+    let short = vec![y.slice(s![1..]).to_owned()];
+    assert!(compute_batch(x.view(), &short, None, NoisePolicy::Exclude, configure).is_err());
+
+    // labelings are normalized like by compute_normalized
+    let mut noisy = y.mapv(|c| c * 2 + 1);
+    noisy[0] = -1;
+    let values = |res: &IndexTreeReturnValue| {
+        res.scalars()
+            .into_iter()
+            .filter_map(|(_, v)| v.map(|v| v.unwrap()))
+            .collect::<Vec<f64>>()
+    };
+    for noise in [NoisePolicy::Exclude, NoisePolicy::Singletons] {
+        let res = compute_batch(x.view(), &[noisy.clone()], None, noise, configure).unwrap();
+        let data = NormalizedPartition::new(x.view(), noisy.view(), noise).unwrap();
+        let expected = configure(IndexTreeBuilder::default())
+            .finish()
+            .compute_normalized(&data);
+        assert_eq!(values(&res.results[0]), values(&expected));
+        assert_eq!(res.results[0].cluster_ids, expected.cluster_ids);
+    }
+
+    // the features are checked once for the batch, the labels of every labeling
+    let mut nan = x.clone();
+    nan[[3, 1]] = f64::NAN;
+    let res = compute_batch(
+        nan.view(),
+        &labelings,
+        None,
+        NoisePolicy::Exclude,
+        configure,
+    );
+    assert!(matches!(res, Err(CalcError::NonFinite { observation: 3 })));
+    let single = vec![y.clone(), Array1::zeros(y.len())];
+    let res = compute_batch(x.view(), &single, None, NoisePolicy::Exclude, configure).unwrap();
+    assert!(res.results[0].silhouette.as_ref().unwrap().is_ok());
+    assert!(matches!(
+        res.results[1].silhouette.as_ref().unwrap(),
+        Err(CalcError::TooFewClusters { clusters: 1 })
+    ));
+}
+
+#[derive(Clone)]
+struct TotalCount(f64);
+impl Product for TotalCount {}
+
+#[derive(Clone)]
+struct Slow;
+impl Product for Slow {}
+
+fn total_count() -> Node<TotalCount> {
+    Node::new("total_count", |_, (counts,): (CountsValue,)| {
+        Ok(TotalCount(counts.val.sum()))
+    })
+}
+
+#[test]
+fn test_dataflow() {
+    let (x, y) = initialize();
+    let ctx = Context {
+        features: Ok(x.view()),
+        labels: Ok(y.view()),
+        previous: Ok(y.view()),
+        next: Err(CalcError::missing_input("Index requires next partition")),
+        reference: Err(CalcError::missing_input(
+            "Index requires reference partition",
+        )),
+        weights: Ok(None),
+        precomputed: Err(CalcError::from("Distances are computed from the features")),
+        metric: Metric::default(),
+    };
+    let mut graph = Graph::default();
+    graph.add(counts::node());
+    graph.add(clusters_centroids::node());
+    graph.add(within_group_dispercion::node());
+    graph.add(total_dispercion::node());
+    graph.add(total_count());
+    graph.request::<TotalCount>();
+    // only the nodes the requested product depends on are instantiated
+    let plan = graph.plan(&[]);
+    let names = plan
+        .nodes
+        .iter()
+        .map(|node| node.template.name)
+        .collect::<Vec<&str>>();
+    assert_eq!(names, vec!["counts", "total_count"]);
+    let products = plan.execute(&ctx, Products::default());
+    let total = products.get::<TotalCount>(Side::Current).unwrap();
+    assert_float_absolute_eq!(total.0, y.len() as f64, ACCURACY);
+    // intermediate products are dropped once every reader ran
+    assert!(products.get::<CountsValue>(Side::Current).is_err());
+
+    // label-independent products are computed once for all partitions
+    graph.add(Node::new(
+        "td_ratio",
+        |_, (td, PreviousPartition(previous)): (TDValue, PreviousPartition<TDValue>)| {
+            Ok(TotalCount(td.val.sum() / previous.val.sum()))
+        },
+    ));
+    let plan = graph.plan(&[]);
+    let td_nodes = plan
+        .nodes
+        .iter()
+        .filter(|node| node.template.name == "total_dispercion")
+        .count();
+    assert_eq!(td_nodes, 1);
+    let products = plan.execute(&ctx, Products::default());
+    let ratio = products.get::<TotalCount>(Side::Current).unwrap();
+    assert_float_absolute_eq!(ratio.0, 1., ACCURACY);
+
+    // products no node computes reach their readers as errors
+    let mut graph = Graph::default();
+    graph.add(total_count());
+    graph.request::<TotalCount>();
+    let plan = graph.plan(&[]);
+    assert_eq!(plan.missing.len(), 1);
+    let products = plan.execute(&ctx, Products::default());
+    assert!(matches!(
+        products.get::<TotalCount>(Side::Current),
+        Err(CalcError::MissingInput(_))
+    ));
+
+    // a node starts once its own inputs are ready, not after every node running with them
+    if rayon::current_num_threads() > 1 {
+        let mut graph = Graph::default();
+        graph.add(counts::node());
+        graph.add(total_count());
+        graph.add(Node::new("slow", |_, ()| {
+            std::thread::sleep(std::time::Duration::from_millis(200));
+            Ok(Slow)
+        }));
+        graph.request::<Slow>();
+        graph.request::<TotalCount>();
+        let (_, profile) = graph.plan(&[]).execute_traced(&ctx, Products::default());
+        let node = |name| profile.nodes.iter().find(|node| node.name == name).unwrap();
+        let slow = node("slow");
+        assert!(node("total_count").start < slow.start + slow.duration);
+    }
+}
+
+struct TraceW;
+impl IndexPlugin for TraceW {
+    fn name(&self) -> &str {
+        "trace_w"
+    }
+    fn requires(&self) -> Vec<Helper> {
+        vec![Helper::WithinGroupDispersion]
+    }
+    fn compute(&self, input: &PluginInput) -> Result<f64, CalcError> {
+        Ok(input.wgd.as_ref().ok_or("WGD wasnt given")?.diag().sum())
+    }
+}
+struct WithinPairs;
+impl IndexPlugin for WithinPairs {
+    fn name(&self) -> &str {
+        "within_pairs"
+    }
+    fn requires(&self) -> Vec<Helper> {
+        vec![Helper::PairsAndDistances]
+    }
+    fn compute(&self, input: &PluginInput) -> Result<f64, CalcError> {
+        let pairs = input.pairs.as_ref().ok_or("Pairs werent given")?;
+        Ok(pairs.iter().map(|p| *p as f64).sum())
+    }
+}
+
+#[test]
+fn test_plugins() {
+    let (x, y) = initialize();
+    let tree = || {
+        IndexTreeBuilder::default()
+            .add_tracew()
+            .add_plugin(Arc::new(TraceW))
+            .add_plugin(Arc::new(WithinPairs))
+            .finish()
+    };
+    let res = tree().compute((x.view(), y.view()));
+    let names = res
+        .plugins
+        .iter()
+        .map(|(name, _)| name.as_str())
+        .collect::<Vec<&str>>();
+    assert_eq!(names, vec!["trace_w", "within_pairs"]);
+    // the plugin reads the same WGD as the built-in index
+    assert_float_absolute_eq!(
+        *res.plugins[0].1.as_ref().unwrap(),
+        res.tracew.unwrap().unwrap().val,
+        ACCURACY
+    );
+    let counts = y.iter().counts();
+    let within = counts
+        .values()
+        .map(|c| (c * (c - 1) / 2) as f64)
+        .sum::<f64>();
+    assert_float_absolute_eq!(*res.plugins[1].1.as_ref().unwrap(), within, ACCURACY);
+
+    // a missing product fails only the plugins asking for it
+    let n = y.len();
+    let condensed = Array1::from_iter(
+        (0..n)
+            .flat_map(|i| (i + 1..n).map(move |j| (i, j)))
+            .map(|(i, j)| (&x.row(i) - &x.row(j)).pow2().sum().sqrt()),
+    );
+    let res =
+        tree().compute_with_distances(DistanceMatrix::Condensed(condensed.view()), y.view(), None);
+    assert!(matches!(res.plugins[0].1, Err(CalcError::MissingInput(_))));
+    assert_float_absolute_eq!(*res.plugins[1].1.as_ref().unwrap(), within, ACCURACY);
+
+    // invalid input reaches every plugin
+    let res = tree().compute((x.view(), Array1::zeros(n).view()));
+    assert_eq!(res.plugins.len(), 2);
+    assert!(res.plugins.iter().all(|(_, value)| value.is_err()));
+}
+
+#[test]
+fn test_helper_outputs() {
+    let (x, y) = initialize();
+    let res = IndexTreeBuilder::default()
+        .add_tracew()
+        .add_helper(Helper::Counts)
+        .add_helper(Helper::Centroids)
+        .add_helper(Helper::WithinGroupDispersion)
+        .add_helper(Helper::BetweenGroupDispersion)
+        .add_helper(Helper::TotalDispersion)
+        .finish()
+        .compute((x.view(), y.view()));
+    let counts = res.counts.unwrap().unwrap().val;
+    assert_float_absolute_eq!(counts.sum(), y.len() as f64, ACCURACY);
+    let centroids = res.centroids.unwrap().unwrap().val;
+    assert_eq!(centroids.dim(), (counts.len(), x.ncols()));
+    let wgd = res.wgd.unwrap().unwrap().val;
+    assert_float_absolute_eq!(wgd.diag().sum(), res.tracew.unwrap().unwrap().val, ACCURACY);
+    // total dispersion splits into the within and between group parts
+    let (bgd, td) = (res.bgd.unwrap().unwrap().val, res.td.unwrap().unwrap().val);
+    for (t, (w, b)) in zip(td.iter(), zip(wgd.iter(), bgd.iter())) {
+        assert_float_absolute_eq!(*t, w + b, ACCURACY);
+    }
+    assert!(res.scat.is_none());
+    assert!(res.pairs_and_distances.is_none());
+}
+
+#[test]
+fn test_tracing() {
+    let (x, y) = initialize();
+    let tree = |tracing| {
+        IndexTreeBuilder::default()
+            .add_calinski_harabasz()
+            .add_gamma()
+            .tracing(tracing)
+            .finish()
+    };
+    assert!(tree(false).compute((x.view(), y.view())).profile.is_none());
+
+    let res = tree(true).compute((x.view(), y.view()));
+    let profile = res.profile.unwrap();
+    let names = profile
+        .nodes
+        .iter()
+        .map(|node| node.name)
+        .collect::<Vec<&str>>();
+    for name in ["counts", "pairs_and_distances", "s_plus_and_minus", "gamma"] {
+        assert!(names.contains(&name), "{name} wasnt traced");
+    }
+    assert!(profile.nodes.iter().all(|node| node.ok));
+    assert!(profile
+        .nodes
+        .iter()
+        .all(|node| node.start + node.duration <= profile.wall));
+    assert!(profile
+        .nodes
+        .iter()
+        .tuple_windows()
+        .all(|(a, b)| a.start + a.duration <= b.start + b.duration));
+    // the distances of every pair of observations
+    let n = y.len();
+    let distances = profile
+        .nodes
+        .iter()
+        .find(|node| node.name == "distances")
+        .unwrap();
+    assert_eq!(
+        distances.memory,
+        n * (n - 1) / 2 * std::mem::size_of::<f64>()
+    );
+    assert!(profile.time_of("gamma") <= profile.wall);
+
+    let trace = profile.chrome_trace();
+    assert!(trace.starts_with("{\"traceEvents\":["));
+    assert_eq!(trace.matches("\"ph\":\"X\"").count(), profile.nodes.len());
+}
+
+#[test]
+fn test_dot() {
+    let tree = IndexTreeBuilder::default()
+        .add_calinski_harabasz()
+        .add_kl()
+        .finish();
+    let dot = tree.dot();
+    assert!(dot.starts_with("digraph plan {"));
+    assert!(dot.contains("\"CountsValue:Current\" -> \"CalinskiHarabaszIndexValue:Current\""));
+    // the neighbouring partitions get their own nodes
+    assert!(dot.contains("\"WGDValue:Previous\" -> \"KLIndexValue:Current\""));
+    assert!(dot.contains("[label=\"kl\", shape=box]"));
+    // pair-based helpers arent needed by the selection
+    let skipped = &dot[dot.find("cluster_skipped").unwrap()..];
+    assert!(skipped.contains("\"distances\""));
+    assert!(!skipped.contains("\"counts\""));
+}
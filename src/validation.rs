@@ -0,0 +1,151 @@
+use crate::calc_error::CalcError;
+use crate::indexes::helpers::distances::{condensed_index, DistanceMatrix};
+use ndarray::{ArrayView1, ArrayView2};
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ValidationConfig {
+    // clusters of a single observation leave within-cluster statistics undefined,
+    // they are accepted unless rejected here
+    pub reject_singletons: bool,
+}
+
+// checks the input once before any index is computed, so that bad input gives one
+// typed error instead of a panic or a different message from every index
+pub fn validate(
+    features: Option<&ArrayView2<f64>>,
+    distances: Option<&DistanceMatrix>,
+    y: &ArrayView1<i32>,
+    weights: Option<&ArrayView1<f64>>,
+    config: &ValidationConfig,
+) -> Result<(), CalcError> {
+    let n = y.len();
+    if n == 0 {
+        return Err(CalcError::shape("Partition is empty"));
+    }
+    if let Some(x) = features {
+        if x.nrows() != n {
+            return Err(CalcError::shape(
+                "Number of labels doesnt match number of observations",
+            ));
+        }
+        validate_features(x)?;
+    }
+    if let Some(distances) = distances {
+        validate_distances(distances, n)?;
+    }
+
+    if let Some(c) = y.iter().find(|c| **c < 0) {
+        return Err(CalcError::labels(format!(
+            "Label {c} is negative, noise has to be normalized first"
+        )));
+    }
+    let max = *y.iter().max().ok_or("Cant get numb of clusters")? as usize;
+    // n observations cant fill more than n clusters
+    if max >= n {
+        return Err(CalcError::labels(format!(
+            "Label {max} leaves clusters without observations, labels have to be 0..q-1"
+        )));
+    }
+    let mut counts = vec![0usize; max + 1];
+    for c in y {
+        counts[*c as usize] += 1;
+    }
+    if let Some(c) = counts.iter().position(|count| *count == 0) {
+        return Err(CalcError::labels(format!(
+            "Cluster {c} has no observations, labels have to be 0..q-1"
+        )));
+    }
+    let q = counts.len();
+    if q < 2 {
+        return Err(CalcError::TooFewClusters { clusters: q });
+    }
+    if n <= q {
+        return Err(CalcError::TooFewObservations {
+            observations: n,
+            clusters: q,
+        });
+    }
+    if let Some(weights) = weights {
+        validate_weights(weights, y, q)?;
+    }
+    if config.reject_singletons {
+        if let Some(c) = counts.iter().position(|count| *count == 1) {
+            return Err(CalcError::SingletonCluster { cluster: c as i32 });
+        }
+    }
+    Ok(())
+}
+
+// features shared by several partitions are checked once through here
+pub fn validate_features(x: &ArrayView2<f64>) -> Result<(), CalcError> {
+    if x.ncols() == 0 {
+        return Err(CalcError::shape("Observations have no features"));
+    }
+    match x
+        .rows()
+        .into_iter()
+        .position(|row| row.iter().any(|v| !v.is_finite()))
+    {
+        Some(observation) => Err(CalcError::NonFinite { observation }),
+        None => Ok(()),
+    }
+}
+
+fn validate_distances(distances: &DistanceMatrix, n: usize) -> Result<(), CalcError> {
+    let shape_err =
+        || CalcError::shape("Shape of distance matrix doesnt match number of observations");
+    let non_finite = match distances {
+        DistanceMatrix::Condensed(d) => {
+            if d.len() != n * (n - 1) / 2 {
+                return Err(shape_err());
+            }
+            d.iter().position(|v| !v.is_finite()).map(|k| {
+                // the observation whose row of the condensed matrix holds position k
+                (0..n)
+                    .take_while(|i| *i + 1 < n && condensed_index(n, *i, *i + 1) <= k)
+                    .last()
+                    .unwrap_or_default()
+            })
+        }
+        DistanceMatrix::Square(d) => {
+            if d.dim() != (n, n) {
+                return Err(shape_err());
+            }
+            d.rows()
+                .into_iter()
+                .position(|row| row.iter().any(|v| !v.is_finite()))
+        }
+    };
+    match non_finite {
+        Some(observation) => Err(CalcError::NonFinite { observation }),
+        None => Ok(()),
+    }
+}
+
+fn validate_weights(
+    weights: &ArrayView1<f64>,
+    y: &ArrayView1<i32>,
+    q: usize,
+) -> Result<(), CalcError> {
+    if weights.len() != y.len() {
+        return Err(CalcError::shape(
+            "Number of weights doesnt match number of observations",
+        ));
+    }
+    if let Some(i) = weights.iter().position(|w| !w.is_finite() || *w < 0.) {
+        return Err(CalcError::weights(format!(
+            "Weight of observation {i} is negative or not finite"
+        )));
+    }
+    // weighted centroids divide by the total weight of the cluster
+    let mut totals = vec![0.; q];
+    for (c, w) in y.iter().zip(weights) {
+        totals[*c as usize] += w;
+    }
+    if let Some(c) = totals.iter().position(|total| *total <= 0.) {
+        return Err(CalcError::weights(format!(
+            "Cluster {c} has zero total weight"
+        )));
+    }
+    Ok(())
+}
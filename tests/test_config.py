@@ -0,0 +1,45 @@
+import numpy as np
+import pytest
+import rust_ext
+
+X = np.array(
+    [
+        [0.0, 0.0],
+        [1.0, 0.0],
+        [0.0, 1.0],
+        [3.0, 3.0],
+        [2.0, 2.0],
+        [3.0, 2.0],
+        [9.0, 9.0],
+        [-5.0, 7.0],
+    ]
+)
+Y = np.array([0, 0, 0, 1, 1, 1, -1, -1], dtype=np.int32)
+
+
+def test_all_keeps_noise_policy():
+    config = rust_ext.IndexTreeConfig(["all"], noise="singletons")
+    res = rust_ext.compute_indexes(X, Y, config)
+    # each noise observation is a cluster of its own instead of being left out
+    assert list(res.cluster_ids) == [0, 1, -1, -1]
+    assert len(res.silhouette_samples) == len(Y)
+    assert not np.isnan(res.silhouette_samples).any()
+
+
+def test_all_keeps_validation():
+    y = np.array([0, 0, 0, 1, 1, 1, 2, 1], dtype=np.int32)
+    config = rust_ext.IndexTreeConfig(["all"], reject_singletons=True, trace=True)
+    res = rust_ext.compute_indexes(X, y, config)
+    with pytest.raises(rust_ext.PartitionError):
+        res.silhouette
+    assert res.profile
+
+
+def test_batch_and_selection_keep_noise_policy():
+    config = rust_ext.IndexTreeConfig(["silhouette"], noise="singletons")
+    batch = rust_ext.compute_indexes_batch(X, [Y, Y], config)
+    for res in batch.results:
+        assert list(res.cluster_ids) == [0, 1, -1, -1]
+    y3 = np.array([0, 0, 2, 1, 1, 1, -1, -1], dtype=np.int32)
+    selection = rust_ext.select_number_of_clusters(X, [Y, y3], config, kmin=4)
+    assert list(selection.results[1].cluster_ids) == [0, 1, 2, -1, -1]